@@ -0,0 +1,174 @@
+//! Measures field of view, line of sight and fog of war performance
+//! on a big map with lots of obstacles.
+//!
+//! Run with `cargo run --release --example fov_bench`.
+
+extern crate cgmath;
+extern crate core;
+
+use std::rc::{Rc};
+use std::time::{Duration, Instant};
+use cgmath::{Vector2};
+use core::db::{Db};
+use core::dir::{Dir};
+use core::event::{CoreEvent, MoveMode};
+use core::fov::{fov, los};
+use core::fow::{Fow};
+use core::game_state::{State};
+use core::map::{Map, Distance, Terrain, spiral_iter};
+use core::movement::{MovePoints};
+use core::player::{PlayerId};
+use core::position::{MapPos, ExactPos, SlotId};
+use core::types::{Size2};
+use core::unit::{UnitId, new_unit};
+
+const RANGE: Distance = Distance{n: 8};
+const ITERATIONS: i32 = 5;
+const TURNS: i32 = 10;
+
+fn bench_map() -> Map<Terrain> {
+    let mut map = Map::new(Size2{w: 60, h: 60});
+    for pos in map.get_iter() {
+        if (pos.v.x * 7 + pos.v.y * 3) % 11 == 0 {
+            *map.tile_mut(pos) = Terrain::Trees;
+        }
+    }
+    map
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0
+        + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+fn measure<F: FnMut()>(name: &str, mut f: F) -> f64 {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let time = millis(start.elapsed()) / ITERATIONS as f64;
+    println!("{:<12} {:>10.3} ms", name, time);
+    time
+}
+
+/// The old line of sight check: full FoV sweep for one tile
+fn los_by_fov(state: &State, origin: MapPos, target: MapPos) -> bool {
+    let mut is_visible = false;
+    fov(state, origin, RANGE, &mut |pos| if pos == target {
+        is_visible = true;
+    });
+    is_visible
+}
+
+/// Soldiers of the first player spread over the whole map, shown
+/// directly to skip the reinforcement points check
+fn add_units(state: &mut State) -> Vec<UnitId> {
+    let db = state.db().clone();
+    let type_id = db.unit_type_id("soldier");
+    let mut ids = Vec::new();
+    let size = state.map().size();
+    for y in 0..size.h / 5 {
+        for x in 0..size.w / 5 {
+            let id = UnitId{id: ids.len() as i32};
+            let pos = ExactPos {
+                map_pos: MapPos{v: Vector2{x: x * 5 + 2, y: y * 5 + 2}},
+                slot_id: SlotId::Id(0),
+            };
+            let unit = new_unit(&db, id, PlayerId{id: 0}, type_id, pos);
+            state.apply_event(&CoreEvent::ShowUnit{unit_info: unit});
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+/// Every turn a third of the units step to a neighbour tile and
+/// then the enemy's turn starts and ends, which resets the fog
+fn turn_events(state: &State, unit_ids: &[UnitId]) -> Vec<CoreEvent> {
+    let mut state = state.clone();
+    let mut events = Vec::new();
+    for turn in 0..TURNS {
+        for (i, &id) in unit_ids.iter().enumerate() {
+            if (i as i32 + turn) % 3 != 0 {
+                continue;
+            }
+            let from = state.unit(id).pos;
+            let dir = Dir::from_int(turn % 6);
+            let to = ExactPos {
+                map_pos: Dir::get_neighbour_pos(from.map_pos, dir),
+                slot_id: from.slot_id,
+            };
+            if !state.map().is_inboard(to.map_pos) {
+                continue;
+            }
+            let event = CoreEvent::Move {
+                unit_id: id,
+                from: from,
+                to: to,
+                mode: MoveMode::Fast,
+                cost: MovePoints{n: 1},
+            };
+            state.apply_event(&event);
+            events.push(event);
+        }
+        for &(old_id, new_id) in &[(0, 1), (1, 0)] {
+            let new_id = PlayerId{id: new_id};
+            let event = CoreEvent::EndTurn {
+                old_id: PlayerId{id: old_id},
+                new_id: new_id,
+                turn: state.next_turn(new_id),
+            };
+            state.apply_event(&event);
+            events.push(event);
+        }
+    }
+    events
+}
+
+fn replay(state: &State, fow: &Fow, events: &[CoreEvent], is_cached: bool) {
+    let mut state = state.clone();
+    let mut fow = fow.clone();
+    for event in events {
+        state.apply_event(event);
+        if !is_cached {
+            fow.invalidate_all_unit_fovs();
+        }
+        fow.apply_event(&state, event);
+    }
+}
+
+fn main() {
+    let mut state = State::new_full_with_map(Rc::new(Db::new()), bench_map());
+    let positions: Vec<_> = state.map().get_iter().collect();
+    let pairs: Vec<_> = positions.iter()
+        .map(|&pos| (pos, spiral_iter(pos, RANGE).last().unwrap()))
+        .filter(|&(_, target)| state.map().is_inboard(target))
+        .collect();
+    println!("FoV from every tile of a {}x{} map:",
+        state.map().size().w, state.map().size().h);
+    measure("fov", || {
+        for &origin in &positions {
+            fov(&state, origin, RANGE, &mut |_| {});
+        }
+    });
+    println!("LoS to a distant tile from every tile:");
+    let old = measure("fov sweep", || {
+        for &(origin, target) in &pairs {
+            los_by_fov(&state, origin, target);
+        }
+    });
+    let new = measure("los", || {
+        for &(origin, target) in &pairs {
+            los(&state, origin, target, RANGE);
+        }
+    });
+    println!("speedup: {:.1}x", old / new);
+    let unit_ids = add_units(&mut state);
+    let fow = Fow::new(&state, PlayerId{id: 0});
+    let events = turn_events(&state, &unit_ids);
+    println!("Fog of war updates for {} turns of {} units ({} events):",
+        TURNS, unit_ids.len(), events.len());
+    let old = measure("no cache", || replay(&state, &fow, &events, false));
+    let new = measure("cache", || replay(&state, &fow, &events, true));
+    println!("speedup: {:.1}x", old / new);
+}
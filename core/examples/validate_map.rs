@@ -0,0 +1,54 @@
+//! Checks maps for mistakes that otherwise show up only in the game.
+//!
+//! Run with `cargo run --example validate_map [MAP_NAME...]`.
+//! Map names can be names of the built-in maps, of generated maps
+//! or paths to map files. Checks all built-in maps by default.
+
+extern crate core;
+
+use std::env;
+use std::process;
+use std::rc::{Rc};
+use core::db::{Db};
+use core::game_state::{self, State};
+use core::options::{Options, GameType};
+use core::validator::{validate};
+
+fn main() {
+    let mut map_names: Vec<String> = env::args().skip(1).collect();
+    if map_names.is_empty() {
+        map_names = game_state::MAP_NAMES.iter().map(|&name| name.to_owned()).collect();
+    }
+    let db = Rc::new(Db::new());
+    let mut errors_count = 0;
+    for map_name in map_names {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: map_name.clone(),
+            players_count: 2,
+        };
+        let state = match State::new_full(db.clone(), &options) {
+            Ok(state) => state,
+            Err(err) => {
+                errors_count += 1;
+                println!("{}: error: {}", map_name, err);
+                continue;
+            },
+        };
+        let problems = validate(&state);
+        if problems.is_empty() {
+            println!("{}: ok", map_name);
+        }
+        for problem in problems {
+            if problem.is_error() {
+                errors_count += 1;
+                println!("{}: error: {}", map_name, problem);
+            } else {
+                println!("{}: warning: {}", map_name, problem);
+            }
+        }
+    }
+    if errors_count > 0 {
+        process::exit(1);
+    }
+}
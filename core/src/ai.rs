@@ -1,6 +1,6 @@
 use std::rc::{Rc};
 use rand::{thread_rng, Rng};
-use game_state::{State};
+use game_state::{State, LoadMapError};
 use map::{distance};
 use movement::{self, MovePoints, Pathfinder, path_cost, truncate_path};
 use dir::{Dir, dirs};
@@ -9,7 +9,7 @@ use db::{Db};
 use misc::{get_shuffled_indices};
 use check::{check_command};
 use position::{ExactPos, MapPos, get_free_exact_pos};
-use object::{ObjectClass, Object};
+use object::{ObjectClass};
 use event::{CoreEvent, Command, MoveMode};
 use player::{PlayerId};
 use options::{Options};
@@ -23,15 +23,15 @@ pub struct Ai {
 }
 
 impl Ai {
-    pub fn new(db: Rc<Db>, options: &Options, id: PlayerId) -> Ai {
-        let state = State::new_partial(db.clone(), options, id);
+    pub fn new(db: Rc<Db>, options: &Options, id: PlayerId) -> Result<Ai, LoadMapError> {
+        let state = State::new_partial(db.clone(), options, id)?;
         let map_size = state.map().size();
-        Ai {
+        Ok(Ai {
             id: id,
             state: state,
             pathfinder: Pathfinder::new(db.clone(), map_size),
             db: db,
-        }
+        })
     }
 
     pub fn apply_event(&mut self, event: &CoreEvent) {
@@ -185,8 +185,15 @@ impl Ai {
         None
     }
 
-    fn get_shuffled_reinforcement_sectors(&self, player_id: PlayerId) -> Vec<&Object> {
-        let mut reinforcement_sectors = Vec::new();
+    /// Deployment zone tiles during the deployment
+    /// and reinforcement sectors' tiles after it
+    fn get_shuffled_reinforcement_positions(&self, player_id: PlayerId) -> Vec<MapPos> {
+        if self.state.is_deployment_phase() {
+            let mut positions = self.state.deployment_zone(player_id).to_vec();
+            thread_rng().shuffle(&mut positions);
+            return positions;
+        }
+        let mut positions = Vec::new();
         for object in self.state.objects().values() {
             let owner_id = match object.owner_id {
                 Some(id) => id,
@@ -198,14 +205,14 @@ impl Ai {
             if object.class != ObjectClass::ReinforcementSector {
                 continue;
             }
-            reinforcement_sectors.push(object);
+            positions.push(object.pos.map_pos);
         }
-        thread_rng().shuffle(&mut reinforcement_sectors);
-        reinforcement_sectors
+        thread_rng().shuffle(&mut positions);
+        positions
     }
 
     pub fn try_get_create_unit_command(&self) -> Option<Command> {
-        let reinforcement_sectors = self.get_shuffled_reinforcement_sectors(self.id);
+        let positions = self.get_shuffled_reinforcement_positions(self.id);
         let reinforcement_points = self.state.reinforcement_points()[&self.id];
         for type_index in get_shuffled_indices(self.db.unit_types()) {
             let unit_type_id = UnitTypeId{id: type_index as i32};
@@ -213,11 +220,11 @@ impl Ai {
             if unit_type.cost > reinforcement_points {
                 continue;
             }
-            for sector in &reinforcement_sectors {
+            for &pos in &positions {
                 let exact_pos = match get_free_exact_pos(
                     &self.state,
                     unit_type,
-                    sector.pos.map_pos,
+                    pos,
                 ) {
                     Some(pos) => pos,
                     None => continue,
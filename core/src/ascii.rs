@@ -0,0 +1,285 @@
+use game_state::{State};
+use event::{CoreEvent};
+use map::{Terrain};
+use object::{Object, ObjectClass};
+use position::{MapPos, SlotId};
+use unit::{self, Unit};
+use cgmath::{Vector2};
+
+/// Width of one tile in characters including the gap.
+/// Even rows are shifted by a half of it like in the game.
+const CELL_WIDTH: usize = 8;
+
+fn terrain_char(terrain: Terrain) -> char {
+    match terrain {
+        Terrain::Plain => '.',
+        Terrain::Trees => 't',
+        Terrain::City => 'c',
+        Terrain::Water => '~',
+    }
+}
+
+/// Objects that are drawn on top of others go first
+fn object_char(object: &Object) -> (i32, char) {
+    match object.class {
+        ObjectClass::Smoke => (0, '*'),
+        ObjectClass::ReinforcementSector => (1, 'R'),
+        ObjectClass::Building => match object.pos.slot_id {
+            SlotId::WholeTile => (2, 'B'),
+            _ => (2, 'b'),
+        },
+        ObjectClass::Bridge => (3, '='),
+        ObjectClass::Minefield => (4, 'x'),
+        ObjectClass::Trench => (5, '_'),
+        ObjectClass::Road => (6, '+'),
+    }
+}
+
+fn player_char(unit: &Unit) -> char {
+    match unit.player_id.id {
+        0 => '0',
+        1 => '1',
+        _ => '?',
+    }
+}
+
+/// Six characters: terrain, the topmost object, owners of
+/// the units in three ground slots and in the air slot.
+/// Unexplored tiles are "?" and units of fogged tiles are unknown.
+fn tile_text(state: &State, pos: MapPos) -> String {
+    if !state.is_tile_explored(pos) {
+        return "??????".to_owned();
+    }
+    let mut text = String::new();
+    text.push(terrain_char(*state.map().tile(pos)));
+    let object = state.objects_at(pos).map(object_char).min();
+    text.push(object.map_or(' ', |(_, c)| c));
+    let empty_slot = if state.is_ground_tile_visible(pos) { '-' } else { ' ' };
+    let mut slots = [empty_slot; 4];
+    for unit in state.units_at(pos) {
+        if unit::is_loaded_or_attached(unit) {
+            continue;
+        }
+        let c = player_char(unit);
+        match unit.pos.slot_id {
+            SlotId::Id(n) => slots[n as usize] = c,
+            SlotId::WholeTile => {
+                slots[0] = c;
+                slots[1] = c;
+                slots[2] = c;
+            },
+            SlotId::Air => slots[3] = c,
+            SlotId::TwoTiles(_) => unreachable!(),
+        }
+    }
+    text.extend(slots.iter());
+    text
+}
+
+fn slot_text(slot_id: SlotId) -> String {
+    match slot_id {
+        SlotId::Id(n) => format!("slot {}", n),
+        SlotId::WholeTile => "whole tile".to_owned(),
+        SlotId::Air => "air".to_owned(),
+        SlotId::TwoTiles(dir) => format!("two tiles {:?}", dir),
+    }
+}
+
+/// The map as a hex grid. See `tile_text` for the tiles' format.
+pub fn render_map(state: &State) -> String {
+    let size = state.map().size();
+    let mut text = String::from("   ");
+    for x in 0 .. size.w {
+        text.push_str(&format!("{:<width$}", x, width = CELL_WIDTH));
+    }
+    let mut text = text.trim_end().to_owned();
+    text.push('\n');
+    for y in 0 .. size.h {
+        let mut line = format!("{:>2} ", y);
+        if y % 2 == 0 {
+            line.push_str(&" ".repeat(CELL_WIDTH / 2));
+        }
+        for x in 0 .. size.w {
+            let pos = MapPos{v: Vector2{x: x, y: y}};
+            line.push_str(&format!(
+                "{:<width$}", tile_text(state, pos), width = CELL_WIDTH));
+        }
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// One line per visible unit in the order of ids
+pub fn render_units(state: &State) -> String {
+    let mut units: Vec<_> = state.units().map(|(_, unit)| unit).collect();
+    units.sort_by_key(|unit| unit.id.id);
+    let mut text = String::new();
+    for unit in units {
+        let unit_type = state.db().unit_type(unit.type_id);
+        text.push_str(&format!(
+            "unit {}: player {} {} at ({}, {}) {}, count {}, morale {}",
+            unit.id.id,
+            unit.player_id.id,
+            unit_type.name,
+            unit.pos.map_pos.v.x,
+            unit.pos.map_pos.v.y,
+            slot_text(unit.pos.slot_id),
+            unit.count,
+            unit.morale,
+        ));
+        if unit.is_loaded {
+            text.push_str(", loaded");
+        }
+        if unit.is_attached {
+            text.push_str(", attached");
+        }
+        if !unit.is_alive {
+            text.push_str(", dead");
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// Full picture of a full or partial state for bug reports and snapshot tests
+pub fn render_state(state: &State) -> String {
+    format!("{}\n{}", render_map(state), render_units(state))
+}
+
+/// Replays the events on a copy of the state and renders
+/// the state after each of them, so a whole game can be followed
+pub fn render_events(state: &State, events: &[CoreEvent]) -> String {
+    let mut state = state.clone();
+    let mut text = String::new();
+    for event in events {
+        state.apply_event(event);
+        text.push_str(&format!("{:?}\n{}\n", event, render_state(&state)));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use map::{Terrain};
+    use player::{PlayerId};
+    use position::{MapPos, ExactPos, SlotId};
+    use map_file::{MapFile};
+    use types::{Size2};
+    use unit::{UnitId, new_unit};
+    use event::{CoreEvent, MoveMode};
+    use movement::{MovePoints};
+    use fow::{Fow};
+    use super::{render_state, render_events};
+
+    fn exact_pos(x: i32, y: i32, slot_id: SlotId) -> ExactPos {
+        ExactPos {
+            map_pos: MapPos{v: Vector2{x: x, y: y}},
+            slot_id: slot_id,
+        }
+    }
+
+    fn create_unit(state: &mut State, id: i32, player_id: i32, x: i32, slot_id: SlotId) {
+        let type_id = state.db().unit_type_id("soldier");
+        let pos = exact_pos(x, 1, slot_id);
+        let unit = new_unit(
+            state.db(), UnitId{id: id}, PlayerId{id: player_id}, type_id, pos);
+        let cost = state.db().unit_type(type_id).cost;
+        state.apply_event(&CoreEvent::CreateUnit {
+            unit_info: unit,
+            cost: cost,
+        });
+    }
+
+    #[test]
+    fn test_render_state() {
+        let mut map_file = MapFile::new(Size2{w: 3, h: 2});
+        map_file.set_terrain(MapPos{v: Vector2{x: 0, y: 0}}, Terrain::Trees);
+        map_file.set_terrain(MapPos{v: Vector2{x: 2, y: 0}}, Terrain::Water);
+        map_file.add_big_building(MapPos{v: Vector2{x: 1, y: 0}});
+        map_file.add_road(
+            MapPos{v: Vector2{x: 0, y: 1}},
+            MapPos{v: Vector2{x: 1, y: 1}},
+        );
+        let mut state = State::new_from_map_file(Rc::new(Db::new()), &map_file);
+        create_unit(&mut state, 0, 0, 0, SlotId::Id(1));
+        create_unit(&mut state, 1, 1, 2, SlotId::Id(0));
+        let expected = concat!(
+            "   0       1       2\n",
+            " 0     t ----  cB----  ~ ----\n",
+            " 1 .+-0--  .+----  . 1---\n",
+            "\n",
+            "unit 0: player 0 soldier at (0, 1) slot 1, count 4, morale 100\n",
+            "unit 1: player 1 soldier at (2, 1) slot 0, count 4, morale 100\n",
+        );
+        assert_eq!(render_state(&state), expected);
+    }
+    /// Player 0's view of a long map with a soldier and
+    /// an enemy soldier at the edge of its field of view
+    fn new_partial_state() -> State {
+        let map_file = MapFile::new(Size2{w: 12, h: 2});
+        let mut state = State::new_from_map_file(Rc::new(Db::new()), &map_file);
+        create_unit(&mut state, 0, 0, 1, SlotId::Id(0));
+        create_unit(&mut state, 1, 1, 7, SlotId::Id(0));
+        let fow = Fow::new(&state, PlayerId{id: 0});
+        state.to_partial(fow);
+        state
+    }
+
+    fn step_back_event() -> CoreEvent {
+        CoreEvent::Move {
+            unit_id: UnitId{id: 0},
+            from: exact_pos(1, 1, SlotId::Id(0)),
+            to: exact_pos(0, 1, SlotId::Id(0)),
+            mode: MoveMode::Fast,
+            cost: MovePoints{n: 1},
+        }
+    }
+
+    fn end_turn(state: &mut State, old_id: i32, new_id: i32) {
+        let new_id = PlayerId{id: new_id};
+        let turn = state.next_turn(new_id);
+        state.apply_event(&CoreEvent::EndTurn {
+            old_id: PlayerId{id: old_id},
+            new_id: new_id,
+            turn: turn,
+        });
+    }
+
+    #[test]
+    fn test_render_partial_state() {
+        let mut state = new_partial_state();
+        state.apply_event(&step_back_event());
+        end_turn(&mut state, 0, 1);
+        end_turn(&mut state, 1, 0);
+        // the enemy's tile is still explored but its units are not known now
+        let expected = concat!(
+            "   0       1       2       3       4       5       6       7       8       9       10      11\n",
+            " 0     . ----  . ----  . ----  . ----  . ----  . ----  .       ??????  ??????  ??????  ??????  ??????\n",
+            " 1 . 0---  . ----  . ----  . ----  . ----  . ----  . ----  .       ??????  ??????  ??????  ??????\n",
+            "\n",
+            "unit 0: player 0 soldier at (0, 1) slot 0, count 4, morale 100\n",
+        );
+        assert_eq!(render_state(&state), expected);
+    }
+
+    #[test]
+    fn test_render_events() {
+        let state = new_partial_state();
+        let event = step_back_event();
+        let expected = concat!(
+            "   0       1       2       3       4       5       6       7       8       9       10      11\n",
+            " 0     . ----  . ----  . ----  . ----  . ----  . ----  . ----  ??????  ??????  ??????  ??????  ??????\n",
+            " 1 . 0---  . ----  . ----  . ----  . ----  . ----  . ----  . 1---  ??????  ??????  ??????  ??????\n",
+            "\n",
+            "unit 0: player 0 soldier at (0, 1) slot 0, count 4, morale 100\n",
+            "unit 1: player 1 soldier at (7, 1) slot 0, count 4, morale 100\n",
+        );
+        let text = render_events(&state, &[event.clone()]);
+        assert_eq!(text, format!("{:?}\n{}\n", event, expected));
+    }
+}
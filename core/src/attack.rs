@@ -1,9 +1,16 @@
+use std::{cmp};
 use rand::{thread_rng, Rng};
 use db::{Db};
 use game_state::{State};
-use unit::{Unit};
+use unit::{self, Unit};
 use misc::{clamp};
 use map::{Terrain};
+use dir::{Dir};
+use position::{MapPos};
+use check::{los};
+use event::{FireMode, AttackInfo};
+use veterancy::{unit_veterancy};
+use object::{ObjectClass};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct AttackPoints{pub n: i32}
@@ -16,13 +23,24 @@ pub fn hit_chance(
     state: &State,
     attacker: &Unit,
     defender: &Unit,
+    fire_mode: FireMode,
 ) -> HitChance {
     let attacker_type = db.unit_type(attacker.type_id);
     let defender_type = db.unit_type(defender.type_id);
     let weapon_type = db.weapon_type(attacker_type.weapon_type_id);
     let cover_bonus = cover_bonus(db, state, defender);
+    let veterancy = unit_veterancy(attacker);
+    let mut weapon_skill = attacker_type.weapon_skill
+        + veterancy.weapon_skill_bonus();
+    if fire_mode == FireMode::Reactive {
+        weapon_skill += veterancy.reaction_fire_bonus();
+    }
+    // indirect fire adjusted only by some other unit's reports
+    if weapon_type.is_inderect && !los(db, state, attacker, defender) {
+        weapon_skill -= 2;
+    }
     let hit_test_v = -7 - cover_bonus + defender_type.size
-        + weapon_type.accuracy + attacker_type.weapon_skill;
+        + weapon_type.accuracy + weapon_skill;
     let pierce_test_v = 10 + -defender_type.armor + weapon_type.ap;
     let wound_test_v = 5 -defender_type.toughness + weapon_type.damage;
     let hit_test_v = clamp(hit_test_v, 0, 10);
@@ -35,18 +53,120 @@ pub fn hit_chance(
 fn cover_bonus(db: &Db, state: &State, defender: &Unit) -> i32 {
     let defender_type = db.unit_type(defender.type_id);
     if defender_type.is_infantry {
-        match *state.map().tile(defender.pos) {
+        let terrain_bonus = match *state.map().tile(defender.pos) {
             Terrain::Plain | Terrain::Water => 0,
             Terrain::Trees => 2,
             Terrain::City => 3,
+        };
+        let is_in_trench = state.objects_at(defender.pos.map_pos).any(|object| {
+            object.class == ObjectClass::Trench && object.is_finished()
+        });
+        if is_in_trench {
+            terrain_bonus + 3
+        } else {
+            terrain_bonus
+        }
+    } else {
+        0
+    }
+}
+
+/// Indirect fire shells may land on a neighbour tile
+pub fn scatter_pos(
+    db: &Db,
+    state: &State,
+    attacker: &Unit,
+    defender: &Unit,
+) -> Option<MapPos> {
+    let attacker_type = db.unit_type(attacker.type_id);
+    let weapon_type = db.weapon_type(attacker_type.weapon_type_id);
+    if !weapon_type.is_inderect {
+        return None;
+    }
+    let scatter_chance = if los(db, state, attacker, defender) {
+        15
+    } else {
+        35
+    };
+    if thread_rng().gen_range(0, 100) >= scatter_chance {
+        return None;
+    }
+    let dir = Dir::from_int(thread_rng().gen_range(0, 6));
+    Some(Dir::get_neighbour_pos(defender.pos.map_pos, dir))
+}
+
+/// Destroyed air units fall down and kill everyone
+/// on the ground in their tile
+pub fn crash_attack_infos(
+    db: &Db,
+    state: &State,
+    attack_info: &AttackInfo,
+) -> Vec<AttackInfo> {
+    let mut infos = Vec::new();
+    let defender = state.unit(attack_info.defender_id);
+    let defender_type = db.unit_type(defender.type_id);
+    if !defender_type.is_air || defender.count > attack_info.killed {
+        return infos;
+    }
+    for unit in state.units_at(defender.pos.map_pos) {
+        let unit_type = db.unit_type(unit.type_id);
+        if !unit.is_alive
+            || unit_type.is_air
+            || unit::is_loaded_or_attached(unit)
+        {
+            continue;
         }
+        infos.push(AttackInfo {
+            attacker_id: None,
+            defender_id: unit.id,
+            mode: attack_info.mode,
+            killed: unit.count,
+            suppression: 0,
+            remove_move_points: false,
+            is_ambush: false,
+            is_inderect: false,
+            is_scattered: false,
+            leave_wrecks: !unit_type.is_infantry,
+            is_splash: false,
+        });
+    }
+    infos
+}
+
+/// Blast of a mine under a unit that entered a minefield
+pub fn mine_attack_info(db: &Db, defender: &Unit) -> AttackInfo {
+    let defender_type = db.unit_type(defender.type_id);
+    let killed = if defender_type.is_infantry {
+        cmp::min(thread_rng().gen_range(0, 3), defender.count)
+    } else if thread_rng().gen_range(0, 100) < 50 {
+        1
     } else {
         0
+    };
+    let per_death_suppression = 20;
+    AttackInfo {
+        attacker_id: None,
+        defender_id: defender.id,
+        mode: FireMode::Reactive,
+        killed: killed,
+        suppression: 40 + per_death_suppression * killed,
+        remove_move_points: true,
+        is_ambush: false,
+        is_inderect: false,
+        is_scattered: false,
+        leave_wrecks: !defender_type.is_infantry,
+        is_splash: false,
     }
 }
 
-pub fn get_killed_count(db: &Db, state: &State, attacker: &Unit, defender: &Unit) -> i32 {
-    let hit = attack_test(db, state, attacker, defender);
+pub fn get_killed_count(
+    db: &Db,
+    state: &State,
+    attacker: &Unit,
+    defender: &Unit,
+    fire_mode: FireMode,
+) -> i32 {
+    let hit = attack_test(db, state, attacker, defender, fire_mode);
     if !hit {
         return 0;
     }
@@ -58,8 +178,116 @@ pub fn get_killed_count(db: &Db, state: &State, attacker: &Unit, defender: &Unit
     }
 }
 
-fn attack_test(db: &Db, state: &State, attacker: &Unit, defender: &Unit) -> bool {
-    let k = hit_chance(db, state, attacker, defender).n;
+fn attack_test(
+    db: &Db,
+    state: &State,
+    attacker: &Unit,
+    defender: &Unit,
+    fire_mode: FireMode,
+) -> bool {
+    let k = hit_chance(db, state, attacker, defender, fire_mode).n;
     let r = thread_rng().gen_range(0, 100);
     r < k
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use player::{PlayerId};
+    use position::{MapPos, ExactPos, SlotId};
+    use unit::{UnitId, new_unit};
+    use event::{CoreEvent, FireMode, AttackInfo};
+    use super::{crash_attack_infos};
+
+    fn create_unit(
+        state: &mut State,
+        id: i32,
+        type_name: &str,
+        player_id: i32,
+        slot_id: SlotId,
+    ) {
+        let type_id = state.db().unit_type_id(type_name);
+        let pos = ExactPos {
+            map_pos: MapPos{v: Vector2{x: 1, y: 0}},
+            slot_id: slot_id,
+        };
+        let unit = new_unit(
+            state.db(), UnitId{id: id}, PlayerId{id: player_id}, type_id, pos);
+        let cost = state.db().unit_type(type_id).cost;
+        state.apply_event(&CoreEvent::CreateUnit {
+            unit_info: unit,
+            cost: cost,
+        });
+    }
+
+    fn test_state() -> State {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map05".to_owned(),
+            players_count: 2,
+        };
+        let mut state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        create_unit(&mut state, 0, "helicopter", 0, SlotId::Air);
+        create_unit(&mut state, 1, "soldier", 1, SlotId::Id(0));
+        create_unit(&mut state, 2, "truck", 1, SlotId::Id(1));
+        state
+    }
+
+    fn attack_helicopter_info(killed: i32) -> AttackInfo {
+        AttackInfo {
+            attacker_id: Some(UnitId{id: 1}),
+            defender_id: UnitId{id: 0},
+            mode: FireMode::Active,
+            killed: killed,
+            suppression: 0,
+            remove_move_points: false,
+            is_ambush: false,
+            is_inderect: false,
+            is_scattered: false,
+            leave_wrecks: false,
+            is_splash: false,
+        }
+    }
+
+    #[test]
+    fn test_destroyed_helicopter_crashes() {
+        let state = test_state();
+        let attack_info = attack_helicopter_info(1);
+        let mut infos = crash_attack_infos(state.db(), &state, &attack_info);
+        infos.sort_by_key(|info| info.defender_id.id);
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].defender_id, UnitId{id: 1});
+        assert_eq!(infos[0].killed, 4);
+        assert!(!infos[0].leave_wrecks);
+        assert_eq!(infos[1].defender_id, UnitId{id: 2});
+        assert_eq!(infos[1].killed, 1);
+        assert!(infos[1].leave_wrecks);
+        for info in &infos {
+            assert_eq!(info.attacker_id, None);
+        }
+    }
+
+    #[test]
+    fn test_missed_helicopter_does_not_crash() {
+        let state = test_state();
+        let attack_info = attack_helicopter_info(0);
+        let infos = crash_attack_infos(state.db(), &state, &attack_info);
+        assert!(infos.is_empty());
+    }
+
+    #[test]
+    fn test_ground_units_do_not_crash() {
+        let state = test_state();
+        let attack_info = AttackInfo {
+            defender_id: UnitId{id: 2},
+            killed: 1,
+            .. attack_helicopter_info(0)
+        };
+        let infos = crash_attack_infos(state.db(), &state, &attack_info);
+        assert!(infos.is_empty());
+    }
+}
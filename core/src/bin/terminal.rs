@@ -0,0 +1,476 @@
+//! Plays the game in a terminal without any graphics.
+//!
+//! Run with `make terminal` or
+//! `cargo run --package core --bin zoc_terminal -- [MAP_NAME] [--hotseat]`.
+//! The map is redrawn after every command, type `help` to see
+//! the commands. Commands can be piped in from a file to replay bugs.
+
+extern crate cgmath;
+extern crate core;
+
+use std::{env, process};
+use std::collections::{HashMap};
+use std::io::{self, BufRead, Write};
+use std::rc::{Rc};
+use cgmath::{Vector2};
+use core::ascii;
+use core::check::{check_command};
+use core::db::{Db};
+use core::event::{Command, CoreEvent, MoveMode, ReactionFireMode};
+use core::game_state::{State, LoadMapError};
+use core::movement::{Pathfinder};
+use core::options::{Options, GameType};
+use core::player::{PlayerId};
+use core::position::{self, MapPos, ExactPos};
+use core::print_info::{print_pos_info, print_unit_info};
+use core::unit::{self, UnitId};
+use core::{Core};
+
+const HELP: &'static str = "\
+commands:
+  select ID              select own unit
+  next                   select next own unit
+  info [X Y]             selected unit or tile details
+  move X Y               move selected unit
+  hunt X Y               move selected unit carefully
+  attack ID              attack enemy unit with selected unit
+  load ID                load unit into selected transporter
+  unload ID X Y          unload passenger of selected transporter
+  attach ID              attach unit to selected transporter
+  detach X Y             detach unit from selected transporter
+  hold / fire            toggle reaction fire of selected unit
+  smoke X Y              fire smoke shell with selected unit
+  types                  list unit types and their costs
+  buy TYPE X Y           call reinforcements
+  map                    redraw the map
+  end                    end turn
+  quit";
+
+/// Short log line about what has just happened
+fn describe_event(state: &State, event: &CoreEvent) -> Option<String> {
+    let text = match *event {
+        CoreEvent::Move{unit_id, to, ..} => format!(
+            "unit {} moved to {}", unit_id.id, to.map_pos),
+        CoreEvent::AttackUnit{ref attack_info} => {
+            let attacker = match attack_info.attacker_id {
+                Some(id) => format!("unit {}", id.id),
+                None => "hidden unit".to_owned(),
+            };
+            format!(
+                "{} attacked unit {}: killed {}, suppression {}{}",
+                attacker,
+                attack_info.defender_id.id,
+                attack_info.killed,
+                attack_info.suppression,
+                if attack_info.is_ambush { " (ambush)" } else { "" },
+            )
+        },
+        CoreEvent::ShellMissed{pos, ..} => format!(
+            "shell scattered and missed at {}", pos),
+        CoreEvent::CreateUnit{ref unit_info, ..} => format!(
+            "unit {} ({}) arrived at {}",
+            unit_info.id.id,
+            state.db().unit_type(unit_info.type_id).name,
+            unit_info.pos.map_pos,
+        ),
+        CoreEvent::ShowUnit{ref unit_info} |
+        CoreEvent::Reveal{ref unit_info} => format!(
+            "enemy unit {} ({}) spotted at {}",
+            unit_info.id.id,
+            state.db().unit_type(unit_info.type_id).name,
+            unit_info.pos.map_pos,
+        ),
+        CoreEvent::HideUnit{unit_id} => format!(
+            "enemy unit {} lost from sight", unit_id.id),
+        CoreEvent::LoadUnit{passenger_id, ..} => format!(
+            "unit {} loaded", passenger_id.id),
+        CoreEvent::UnloadUnit{ref unit_info, to, ..} => format!(
+            "unit {} unloaded at {}", unit_info.id.id, to.map_pos),
+        CoreEvent::Smoke{pos, ..} => format!("smoke at {}", pos),
+        CoreEvent::SectorOwnerChanged{sector_id, new_owner_id} => match new_owner_id {
+            Some(player_id) => format!(
+                "sector {} captured by player {}", sector_id.id, player_id.id),
+            None => format!("sector {} lost", sector_id.id),
+        },
+        CoreEvent::EndTurn{new_id, turn, ..} => format!(
+            "turn {}: player {}", turn, new_id.id),
+        CoreEvent::Message{ref text, ..} => format!("message: {}", text),
+        CoreEvent::GameOver{ref result} => match result.winner_id {
+            Some(player_id) => format!(
+                "game over: player {} won ({:?})", player_id.id, result.reason),
+            None => format!("game over: draw ({:?})", result.reason),
+        },
+        _ => return None,
+    };
+    Some(text)
+}
+
+fn parse_id(word: Option<&&str>) -> Result<UnitId, String> {
+    match word.and_then(|word| word.parse().ok()) {
+        Some(id) => Ok(UnitId{id: id}),
+        None => Err("Expected unit id".to_owned()),
+    }
+}
+
+fn parse_pos(state: &State, words: &[&str]) -> Result<MapPos, String> {
+    let x = words.first().and_then(|word| word.parse().ok());
+    let y = words.get(1).and_then(|word| word.parse().ok());
+    match (x, y) {
+        (Some(x), Some(y)) => {
+            let pos = MapPos{v: Vector2{x: x, y: y}};
+            if state.map().is_inboard(pos) {
+                Ok(pos)
+            } else {
+                Err(format!("{} is outside of the map", pos))
+            }
+        },
+        _ => Err("Expected tile coordinates".to_owned()),
+    }
+}
+
+struct Client {
+    core: Core,
+
+    /// What every human player knows about the game
+    states: HashMap<PlayerId, State>,
+
+    selected_unit_id: Option<UnitId>,
+}
+
+impl Client {
+    fn new(options: &Options) -> Result<Client, LoadMapError> {
+        let core = Core::new(options)?;
+        let mut states = HashMap::new();
+        let player_ids = match options.game_type {
+            GameType::Hotseat => vec![PlayerId{id: 0}, PlayerId{id: 1}],
+            GameType::SingleVsAi => vec![PlayerId{id: 0}],
+        };
+        for player_id in player_ids {
+            let state = State::new_partial(core.db().clone(), options, player_id)?;
+            states.insert(player_id, state);
+        }
+        let mut client = Client {
+            core: core,
+            states: states,
+            selected_unit_id: None,
+        };
+        client.apply_events();
+        Ok(client)
+    }
+
+    fn db(&self) -> &Rc<Db> {
+        self.core.db()
+    }
+
+    fn state(&self) -> &State {
+        &self.states[&self.core.player_id()]
+    }
+
+    fn apply_events(&mut self) {
+        while let Some(event) = self.core.get_event() {
+            let state = self.states.get_mut(&self.core.player_id()).unwrap();
+            if let Some(text) = describe_event(state, &event) {
+                println!("{}", text);
+            }
+            state.apply_event(&event);
+        }
+        let is_selected_unit_lost = match self.selected_unit_id {
+            Some(id) => self.state().unit_opt(id).is_none(),
+            None => false,
+        };
+        if is_selected_unit_lost {
+            self.selected_unit_id = None;
+        }
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.state().game_result().is_some()
+    }
+
+    fn draw(&self) {
+        let state = self.state();
+        let player_id = self.core.player_id();
+        println!("");
+        print!("turn {}", state.turn());
+        if let Some(turn_limit) = state.turn_limit() {
+            print!("/{}", turn_limit);
+        }
+        if state.is_deployment_phase() {
+            print!(" (deployment)");
+        }
+        println!(
+            ", player {}, reinforcement points {}",
+            player_id.id,
+            state.reinforcement_points()[&player_id].n,
+        );
+        let mut scores: Vec<_> = state.score().iter().collect();
+        scores.sort_by_key(|&(id, _)| id.id);
+        for (id, score) in scores {
+            println!("score of player {}: {}/{}", id.id, score.n, state.target_score().n);
+        }
+        print!("{}", ascii::render_map(state));
+        let mut sector_ids: Vec<_> = state.sectors().keys().cloned().collect();
+        sector_ids.sort_by_key(|id| id.id);
+        for sector_id in sector_ids {
+            let sector = &state.sectors()[&sector_id];
+            let owner = match sector.owner_id {
+                Some(id) => format!("player {}", id.id),
+                None => "nobody".to_owned(),
+            };
+            println!(
+                "sector {} at {}: {} tiles, income {}, owned by {}",
+                sector_id.id,
+                sector.mean_pos(),
+                sector.positions.len(),
+                sector.income.n,
+                owner,
+            );
+        }
+        print!("{}", ascii::render_units(state));
+        if let Some(unit_id) = self.selected_unit_id {
+            let unit = state.unit(unit_id);
+            println!(
+                "selected unit {}: MP={}, AP={}, RAP={}",
+                unit_id.id,
+                unit.move_points.map_or(0, |points| points.n),
+                unit.attack_points.map_or(0, |points| points.n),
+                unit.reactive_attack_points.map_or(0, |points| points.n),
+            );
+        }
+    }
+
+    fn selected_unit_id(&self) -> Result<UnitId, String> {
+        self.selected_unit_id.ok_or_else(|| "No unit is selected".to_owned())
+    }
+
+    fn free_pos(&self, unit_id: UnitId, pos: MapPos) -> Result<ExactPos, String> {
+        let state = self.state();
+        let unit_type = self.db().unit_type(state.unit(unit_id).type_id);
+        position::get_free_exact_pos(state, unit_type, pos)
+            .ok_or_else(|| format!("No free slot in {}", pos))
+    }
+
+    fn do_command(&mut self, command: Command) -> Result<(), String> {
+        check_command(self.db(), self.core.player_id(), self.state(), &command)
+            .map_err(|err| format!("Can't do that: {:?}", err))?;
+        self.core.do_command(command);
+        self.apply_events();
+        Ok(())
+    }
+
+    fn select(&mut self, unit_id: UnitId) -> Result<(), String> {
+        let is_commandable = match self.state().unit_opt(unit_id) {
+            Some(unit) => unit::is_commandable(self.core.player_id(), unit),
+            None => false,
+        };
+        if !is_commandable {
+            return Err(format!("Can't select unit {}", unit_id.id));
+        }
+        self.selected_unit_id = Some(unit_id);
+        Ok(())
+    }
+
+    fn select_next(&mut self) -> Result<(), String> {
+        let player_id = self.core.player_id();
+        let next_id = {
+            let state = self.state();
+            let mut ids: Vec<_> = state.units()
+                .filter(|&(_, unit)| unit::is_commandable(player_id, unit))
+                .map(|(&id, _)| id)
+                .collect();
+            ids.sort_by_key(|id| id.id);
+            match self.selected_unit_id {
+                Some(selected_id) => ids.iter().cloned()
+                    .find(|id| id.id > selected_id.id)
+                    .or_else(|| ids.first().cloned()),
+                None => ids.first().cloned(),
+            }
+        };
+        match next_id {
+            Some(id) => self.select(id),
+            None => Err("No units to select".to_owned()),
+        }
+    }
+
+    fn move_unit(&mut self, pos: MapPos, mode: MoveMode) -> Result<(), String> {
+        let unit_id = self.selected_unit_id()?;
+        let destination = self.free_pos(unit_id, pos)?;
+        let path = {
+            let state = self.state();
+            let mut pathfinder = Pathfinder::new(self.db().clone(), state.map().size());
+            pathfinder.fill_map(state, state.unit(unit_id));
+            pathfinder.get_path(destination)
+        };
+        match path {
+            Some(path) => self.do_command(Command::Move {
+                unit_id: unit_id,
+                path: path,
+                mode: mode,
+            }),
+            None => Err(format!("Unit {} can't reach {}", unit_id.id, pos)),
+        }
+    }
+
+    fn buy(&mut self, words: &[&str]) -> Result<(), String> {
+        let type_name = words.first().ok_or_else(|| "Expected unit type".to_owned())?;
+        let type_id = self.db().unit_type_id_opt(type_name)
+            .ok_or_else(|| format!("No unit type with name \"{}\"", type_name))?;
+        let pos = parse_pos(self.state(), words.get(1..).unwrap_or(&[]))?;
+        let exact_pos = {
+            let unit_type = self.db().unit_type(type_id);
+            position::get_free_exact_pos(self.state(), unit_type, pos)
+                .ok_or_else(|| format!("No free slot in {}", pos))?
+        };
+        self.do_command(Command::CreateUnit{pos: exact_pos, type_id: type_id})
+    }
+
+    fn print_types(&self) {
+        for unit_type in self.db().unit_types() {
+            println!("{}: cost {}", unit_type.name, unit_type.cost.n);
+        }
+    }
+
+    fn print_info(&self, words: &[&str]) -> Result<(), String> {
+        if words.is_empty() {
+            let unit_id = self.selected_unit_id()?;
+            print_unit_info(self.db(), self.state().unit(unit_id));
+        } else {
+            let pos = parse_pos(self.state(), words)?;
+            print_pos_info(self.db(), self.state(), pos);
+        }
+        Ok(())
+    }
+
+    /// Returns false if the game should be closed
+    fn execute(&mut self, words: &[&str]) -> Result<bool, String> {
+        let args = &words[1..];
+        match words[0] {
+            "help" => println!("{}", HELP),
+            "map" => self.draw(),
+            "select" => self.select(parse_id(args.first())?)?,
+            "next" => self.select_next()?,
+            "info" => self.print_info(args)?,
+            "move" => {
+                let pos = parse_pos(self.state(), args)?;
+                self.move_unit(pos, MoveMode::Fast)?;
+            },
+            "hunt" => {
+                let pos = parse_pos(self.state(), args)?;
+                self.move_unit(pos, MoveMode::Hunt)?;
+            },
+            "attack" => {
+                let command = Command::AttackUnit {
+                    attacker_id: self.selected_unit_id()?,
+                    defender_id: parse_id(args.first())?,
+                };
+                self.do_command(command)?;
+            },
+            "load" => {
+                let command = Command::LoadUnit {
+                    transporter_id: self.selected_unit_id()?,
+                    passenger_id: parse_id(args.first())?,
+                };
+                self.do_command(command)?;
+            },
+            "unload" => {
+                let passenger_id = parse_id(args.first())?;
+                let pos = parse_pos(self.state(), args.get(1..).unwrap_or(&[]))?;
+                let command = Command::UnloadUnit {
+                    transporter_id: self.selected_unit_id()?,
+                    passenger_id: passenger_id,
+                    pos: self.free_pos(passenger_id, pos)?,
+                };
+                self.do_command(command)?;
+            },
+            "attach" => {
+                let command = Command::Attach {
+                    transporter_id: self.selected_unit_id()?,
+                    attached_unit_id: parse_id(args.first())?,
+                };
+                self.do_command(command)?;
+            },
+            "detach" => {
+                let transporter_id = self.selected_unit_id()?;
+                let pos = parse_pos(self.state(), args)?;
+                let command = Command::Detach {
+                    transporter_id: transporter_id,
+                    pos: self.free_pos(transporter_id, pos)?,
+                };
+                self.do_command(command)?;
+            },
+            "hold" | "fire" => {
+                let mode = if words[0] == "hold" {
+                    ReactionFireMode::HoldFire
+                } else {
+                    ReactionFireMode::Normal
+                };
+                let command = Command::SetReactionFireMode {
+                    unit_id: self.selected_unit_id()?,
+                    mode: mode,
+                };
+                self.do_command(command)?;
+            },
+            "smoke" => {
+                let command = Command::Smoke {
+                    unit_id: self.selected_unit_id()?,
+                    pos: parse_pos(self.state(), args)?,
+                };
+                self.do_command(command)?;
+            },
+            "types" => self.print_types(),
+            "buy" => self.buy(args)?,
+            "end" => {
+                self.selected_unit_id = None;
+                self.do_command(Command::EndTurn)?;
+                self.draw();
+            },
+            "quit" => return Ok(false),
+            _ => return Err(format!("Unknown command \"{}\", try \"help\"", words[0])),
+        }
+        Ok(true)
+    }
+}
+
+fn main() {
+    let mut options = Options {
+        game_type: GameType::SingleVsAi,
+        map_name: "map01".to_owned(),
+        players_count: 2,
+    };
+    for arg in env::args().skip(1) {
+        if arg == "--hotseat" {
+            options.game_type = GameType::Hotseat;
+        } else {
+            options.map_name = arg;
+        }
+    }
+    let mut client = match Client::new(&options) {
+        Ok(client) => client,
+        Err(err) => {
+            println!("{}: {}", options.map_name, err);
+            process::exit(1);
+        },
+    };
+    client.draw();
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap() == 0 {
+            break;
+        }
+        let words: Vec<_> = line.split_whitespace().collect();
+        if words.is_empty() {
+            continue;
+        }
+        match client.execute(&words) {
+            Ok(true) => {},
+            Ok(false) => break,
+            Err(text) => println!("{}", text),
+        }
+        if client.is_game_over() {
+            break;
+        }
+    }
+}
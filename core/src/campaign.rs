@@ -0,0 +1,196 @@
+use std::{fs, io};
+use std::io::{Read, Write};
+use std::path::{Path};
+use db::{Db};
+use game_state::{State, ReinforcementPoints};
+use player::{PlayerId};
+
+/// Scenarios of the campaign in the order they are played
+pub const MAP_NAMES: &'static [&'static str] = &["map01", "map07", "map06"];
+
+pub const SAVE_FILE_NAME: &'static str = "campaign.txt";
+
+/// Unit that has survived a mission and goes to the next one
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoreUnit {
+    pub type_name: String,
+    pub count: i32,
+    pub morale: i32,
+    pub experience: i32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CampaignProgress {
+    /// Index of the next mission in `MAP_NAMES`
+    pub mission_index: usize,
+
+    pub core_units: Vec<CoreUnit>,
+
+    /// Core units that didn't fit into the current mission.
+    /// They sit it out and join the survivors afterwards.
+    pub reserve_units: Vec<CoreUnit>,
+
+    /// Reinforcement points left after the previous missions
+    pub reinforcement_points: ReinforcementPoints,
+}
+
+impl CampaignProgress {
+    pub fn new() -> CampaignProgress {
+        CampaignProgress {
+            mission_index: 0,
+            core_units: Vec::new(),
+            reserve_units: Vec::new(),
+            reinforcement_points: ReinforcementPoints{n: 0},
+        }
+    }
+
+    /// None if all the missions are won
+    pub fn map_name(&self) -> Option<&'static str> {
+        MAP_NAMES.get(self.mission_index).cloned()
+    }
+
+    /// Carries player's survivors and points into the next mission
+    pub fn complete_mission(&mut self, state: &State, player_id: PlayerId) {
+        let mut core_units: Vec<_> = state.units()
+            .filter(|&(_, unit)| unit.player_id == player_id && unit.is_alive)
+            .map(|(&id, unit)| (id, CoreUnit {
+                type_name: state.db().unit_type(unit.type_id).name.clone(),
+                count: unit.count,
+                morale: unit.morale,
+                experience: unit.experience,
+            }))
+            .collect();
+        core_units.sort_by_key(|&(id, _)| id.id);
+        self.core_units = core_units.into_iter().map(|(_, unit)| unit).collect();
+        self.core_units.extend(self.reserve_units.drain(..));
+        self.reinforcement_points = state.reinforcement_points()[&player_id];
+        self.mission_index += 1;
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "mission {}\npoints {}\n",
+            self.mission_index,
+            self.reinforcement_points.n,
+        );
+        for unit in &self.core_units {
+            text.push_str(&format!(
+                "unit {} {} {} {}\n",
+                unit.type_name,
+                unit.count,
+                unit.morale,
+                unit.experience,
+            ));
+        }
+        text
+    }
+
+    /// None if the text is malformed or has units of unknown types
+    pub fn from_text(db: &Db, text: &str) -> Option<CampaignProgress> {
+        let mut progress = CampaignProgress::new();
+        for line in text.lines() {
+            let words: Vec<_> = line.split_whitespace().collect();
+            match words.as_slice() {
+                &["mission", index] => {
+                    progress.mission_index = match index.parse() {
+                        Ok(index) => index,
+                        Err(_) => return None,
+                    };
+                },
+                &["points", n] => {
+                    progress.reinforcement_points.n = match n.parse() {
+                        Ok(n) => n,
+                        Err(_) => return None,
+                    };
+                },
+                &["unit", type_name, count, morale, experience] => {
+                    if db.unit_type_id_opt(type_name).is_none() {
+                        return None;
+                    }
+                    let unit = match (count.parse(), morale.parse(), experience.parse()) {
+                        (Ok(count), Ok(morale), Ok(experience)) => CoreUnit {
+                            type_name: type_name.to_owned(),
+                            count: count,
+                            morale: morale,
+                            experience: experience,
+                        },
+                        _ => return None,
+                    };
+                    progress.core_units.push(unit);
+                },
+                &[] => {},
+                _ => return None,
+            }
+        }
+        Some(progress)
+    }
+
+    pub fn load<P: AsRef<Path>>(db: &Db, path: P) -> io::Result<CampaignProgress> {
+        let mut text = String::new();
+        fs::File::open(path)?.read_to_string(&mut text)?;
+        CampaignProgress::from_text(db, &text).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "Bad campaign progress")
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::File::create(path)?.write_all(self.to_text().as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use player::{PlayerId};
+    use position::{MapPos, ExactPos, SlotId};
+    use unit::{Unit, UnitId, new_unit};
+    use event::{CoreEvent};
+    use super::{CampaignProgress};
+
+    #[test]
+    fn test_survivors_are_saved_with_their_experience() {
+        let options = Options {
+            game_type: GameType::SingleVsAi,
+            map_name: "map01".to_owned(),
+            players_count: 2,
+        };
+        let mut state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        let type_id = state.db().unit_type_id("soldier");
+        let pos = ExactPos {
+            map_pos: MapPos{v: Vector2{x: 0, y: 0}},
+            slot_id: SlotId::Id(0),
+        };
+        let unit = new_unit(
+            state.db(), UnitId{id: 0}, PlayerId{id: 0}, type_id, pos);
+        let cost = state.db().unit_type(type_id).cost;
+        state.apply_event(&CoreEvent::CreateUnit {
+            unit_info: Unit {
+                count: 2,
+                morale: 50,
+                experience: 7,
+                .. unit
+            },
+            cost: cost,
+        });
+        let mut progress = CampaignProgress::new();
+        progress.complete_mission(&state, PlayerId{id: 0});
+        assert_eq!(progress.mission_index, 1);
+        assert_eq!(progress.core_units.len(), 1);
+        assert_eq!(progress.core_units[0].experience, 7);
+        let text = progress.to_text();
+        assert_eq!(CampaignProgress::from_text(state.db(), &text), Some(progress));
+    }
+
+    #[test]
+    fn test_unknown_unit_types_are_rejected() {
+        let db = Db::new();
+        let text = "mission 1\npoints 5\nunit soldier 4 100 0\n";
+        assert!(CampaignProgress::from_text(&db, text).is_some());
+        let text = "mission 1\npoints 5\nunit cavalry 4 100 0\n";
+        assert_eq!(CampaignProgress::from_text(&db, text), None);
+    }
+}
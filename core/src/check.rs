@@ -1,14 +1,16 @@
 use std::{fmt, error};
 use game_state::{State};
-use map::{distance};
+use map::{Terrain, Distance, distance};
 use movement::{path_cost, tile_cost, move_cost_modifier};
-use unit::{Unit};
+use unit::{self, Unit, UnitId};
 use db::{Db};
-use fov::{fov, simple_fov};
-use position::{can_place_unit};
+use fov;
+use position::{self, MapPos, ExactPos, can_place_unit};
 use event::{Command, FireMode};
 use object::{ObjectClass};
 use player::{PlayerId};
+use morale::{MoraleState, unit_morale_state};
+use fow::{TileVisibility, calc_visibility};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CommandError {
@@ -16,6 +18,8 @@ pub enum CommandError {
     CanNotCommandEnemyUnits,
     CanNotChainAttachments,
     NotInReinforcementSector,
+    NotInDeploymentZone,
+    NotAllowedDuringDeployment,
     NotEnoughMovePoints,
     NotEnoughAttackPoints,
     NotEnoughReactiveAttackPoints,
@@ -26,7 +30,7 @@ pub enum CommandError {
     NoLos,
     BadTransporterType,
     BadPassengerType,
-    TransporterIsNotEmpty,
+    TransporterIsFull,
     TransporterIsEmpty,
     TransporterIsTooFarAway,
     PassengerHasNotEnoughMovePoints,
@@ -45,6 +49,13 @@ pub enum CommandError {
     BadAttachedUnitType,
     NoAttachedUnit,
     TooManyAttachedUnits,
+    UnitIsRouted,
+    CanNotAdvanceWhileRetreating,
+    NoSpotter,
+    CanNotBuildHere,
+    AlreadyBuilt,
+    NothingToClear,
+    GameIsOver,
 }
 
 impl CommandError {
@@ -54,6 +65,8 @@ impl CommandError {
             CommandError::CanNotCommandEnemyUnits => "Can not command enemy units",
             CommandError::CanNotChainAttachments => "Can not chain attachments",
             CommandError::NotInReinforcementSector => "Not in reinforcement sector",
+            CommandError::NotInDeploymentZone => "Not in deployment zone",
+            CommandError::NotAllowedDuringDeployment => "Not allowed during deployment",
             CommandError::NotEnoughMovePoints => "Not enough move points",
             CommandError::NotEnoughAttackPoints => "No attack points",
             CommandError::NotEnoughReactiveAttackPoints => "No reactive attack points",
@@ -64,7 +77,7 @@ impl CommandError {
             CommandError::NoLos => "No Line of Sight",
             CommandError::BadTransporterType => "Bad transporter type",
             CommandError::BadPassengerType => "Bad passenger type",
-            CommandError::TransporterIsNotEmpty => "Transporter is not empty",
+            CommandError::TransporterIsFull => "Transporter is full",
             CommandError::TransporterIsEmpty => "Transporter is empty",
             CommandError::TransporterIsTooFarAway => "Transporter is too far away",
             CommandError::PassengerHasNotEnoughMovePoints => "Passenger has not enough move points",
@@ -83,6 +96,13 @@ impl CommandError {
             CommandError::BadAttachedUnitType => "Bad attached unit type",
             CommandError::NoAttachedUnit => "No attached unit",
             CommandError::TooManyAttachedUnits => "too many attached units",
+            CommandError::UnitIsRouted => "Unit is routed",
+            CommandError::CanNotAdvanceWhileRetreating => "Can`t advance while retreating",
+            CommandError::NoSpotter => "No friendly unit sees the target",
+            CommandError::CanNotBuildHere => "Can`t build here",
+            CommandError::AlreadyBuilt => "Already built",
+            CommandError::NothingToClear => "Nothing to clear",
+            CommandError::GameIsOver => "Game is over",
         }
     }
 }
@@ -106,18 +126,35 @@ pub fn check_command(
     command: &Command,
 ) -> Result<(), CommandError> {
     assert!(state.is_partial());
+    if state.game_result().is_some() {
+        return Err(CommandError::GameIsOver);
+    }
+    if state.is_deployment_phase() {
+        match *command {
+            Command::EndTurn |
+            Command::CreateUnit{..} |
+            Command::SetReactionFireMode{..} => {},
+            _ => return Err(CommandError::NotAllowedDuringDeployment),
+        }
+    }
     match *command {
         Command::EndTurn => Ok(()),
         Command::CreateUnit{pos, type_id} => {
-            let mut is_sector = false;
-            for object in state.objects_at(pos.map_pos) {
-                if object.class == ObjectClass::ReinforcementSector {
-                    is_sector = true;
-                    break;
+            if state.is_deployment_phase() {
+                if !state.deployment_zone(player_id).contains(&pos.map_pos) {
+                    return Err(CommandError::NotInDeploymentZone);
+                }
+            } else {
+                let mut is_sector = false;
+                for object in state.objects_at(pos.map_pos) {
+                    if object.class == ObjectClass::ReinforcementSector {
+                        is_sector = true;
+                        break;
+                    }
+                }
+                if !is_sector {
+                    return Err(CommandError::NotInReinforcementSector);
                 }
-            }
-            if !is_sector {
-                return Err(CommandError::NotInReinforcementSector);
             }
             let unit_type = db.unit_type(type_id);
             let reinforcement_points = state.reinforcement_points()[&player_id];
@@ -143,6 +180,16 @@ pub fn check_command(
             if path.len() < 2 {
                 return Err(CommandError::BadPath);
             }
+            match unit_morale_state(unit) {
+                MoraleState::Routed => return Err(CommandError::UnitIsRouted),
+                MoraleState::Retreating => {
+                    let destination = path[path.len() - 1];
+                    if is_advancing(state, unit, destination) {
+                        return Err(CommandError::CanNotAdvanceWhileRetreating);
+                    }
+                },
+                MoraleState::Steady | MoraleState::Pinned => {},
+            }
             for window in path.windows(2) {
                 let pos = window[1];
                 if !can_place_unit(state, db.unit_type(unit.type_id), pos) {
@@ -198,22 +245,31 @@ pub fn check_command(
             if transporter.player_id != player_id {
                 return Err(CommandError::CanNotCommandEnemyUnits);
             }
-            if !db.unit_type(transporter.type_id).is_transporter {
+            if unit_morale_state(passenger) == MoraleState::Routed
+                || unit_morale_state(transporter) == MoraleState::Routed
+            {
+                return Err(CommandError::UnitIsRouted);
+            }
+            let transporter_type = db.unit_type(transporter.type_id);
+            if transporter_type.transport_capacity == 0 {
                 return Err(CommandError::BadTransporterType);
             }
             let passenger_type = db.unit_type(passenger.type_id);
             if !passenger_type.is_infantry || passenger_type.can_be_towed {
                 return Err(CommandError::BadPassengerType);
             }
-            if transporter.passenger_id.is_some() {
-                return Err(CommandError::TransporterIsNotEmpty);
+            if unit::is_loaded_or_attached(passenger) {
+                return Err(CommandError::BadPassengerId);
+            }
+            let load = passengers_size(db, state, transporter) + passenger_type.size;
+            if load > transporter_type.transport_capacity {
+                return Err(CommandError::TransporterIsFull);
             }
             if distance(transporter.pos.map_pos, passenger.pos.map_pos).n > 1 {
                 return Err(CommandError::TransporterIsTooFarAway);
             }
-            // TODO: 0 -> real move cost of transport tile for passenger
-            let passenger_move_points = passenger.move_points.unwrap();
-            if passenger_move_points.n == 0 {
+            let cost = tile_cost(db, state, passenger, passenger.pos, transporter.pos);
+            if cost > passenger.move_points.unwrap() {
                 return Err(CommandError::PassengerHasNotEnoughMovePoints);
             }
             Ok(())
@@ -236,22 +292,29 @@ pub fn check_command(
             if transporter.player_id != player_id {
                 return Err(CommandError::CanNotCommandEnemyUnits);
             }
+            if unit_morale_state(transporter) == MoraleState::Routed {
+                return Err(CommandError::UnitIsRouted);
+            }
             let transporter_type = db.unit_type(transporter.type_id);
-            if !transporter_type.is_transporter {
+            if transporter_type.transport_capacity == 0 {
                 return Err(CommandError::BadTransporterType);
             }
             if distance(transporter.pos.map_pos, pos.map_pos).n > 1 {
                 return Err(CommandError::UnloadDistanceIsTooBig);
             }
-            if transporter.passenger_id.is_none() {
+            if transporter.passenger_ids.is_empty() {
                 return Err(CommandError::TransporterIsEmpty);
             }
+            if !transporter.passenger_ids.contains(&passenger_id) {
+                return Err(CommandError::BadPassengerId);
+            }
             if !can_place_unit(state, db.unit_type(passenger.type_id), pos) {
                 return Err(CommandError::DestinationTileIsNotEmpty);
             }
-            let passenger_type = db.unit_type(passenger.type_id);
+            // passengers lose their move points when loaded so
+            // the transporter pays for dropping them off
             let cost = tile_cost(db, state, passenger, transporter.pos, pos);
-            if cost.n > passenger_type.move_points.n {
+            if cost > transporter.move_points.unwrap() {
                 return Err(CommandError::NotEnoughMovePoints);
             }
             Ok(())
@@ -267,6 +330,9 @@ pub fn check_command(
             if transporter.player_id != player_id {
                 return Err(CommandError::CanNotCommandEnemyUnits);
             }
+            if unit_morale_state(transporter) == MoraleState::Routed {
+                return Err(CommandError::UnitIsRouted);
+            }
             let transporter_type = db.unit_type(transporter.type_id);
             if transporter_type.is_infantry || transporter_type.is_air {
                 return Err(CommandError::BadTransporterType);
@@ -327,6 +393,9 @@ pub fn check_command(
             if transporter.player_id != player_id {
                 return Err(CommandError::CanNotCommandEnemyUnits);
             }
+            if unit_morale_state(transporter) == MoraleState::Routed {
+                return Err(CommandError::UnitIsRouted);
+            }
             if distance(transporter.pos.map_pos, pos.map_pos).n > 1 {
                 return Err(CommandError::UnloadDistanceIsTooBig);
             }
@@ -334,7 +403,13 @@ pub fn check_command(
                 return Err(CommandError::DestinationTileIsNotEmpty);
             }
             let transporter_move_points = transporter.move_points.unwrap();
-            let cost = tile_cost(db, state, transporter, transporter.pos, pos);
+            // the gun is left behind so there is no towing penalty
+            let detached_transporter = Unit {
+                attached_unit_id: None,
+                .. transporter.clone()
+            };
+            let cost = tile_cost(
+                db, state, &detached_transporter, transporter.pos, pos);
             if cost > transporter_move_points {
                 return Err(CommandError::NotEnoughMovePoints);
             }
@@ -364,6 +439,9 @@ pub fn check_command(
             if unit.player_id != player_id {
                 return Err(CommandError::CanNotCommandEnemyUnits);
             }
+            if unit_morale_state(unit) != MoraleState::Steady {
+                return Err(CommandError::BadMorale);
+            }
             let unit_type = db.unit_type(unit.type_id);
             let weapon_type = db.weapon_type(unit_type.weapon_type_id);
             if !weapon_type.smoke.is_some() {
@@ -378,6 +456,65 @@ pub fn check_command(
             }
             Ok(())
         },
+        Command::BuildBridge{unit_id, pos} => {
+            check_engineer(db, player_id, state, unit_id, pos)?;
+            if *state.map().tile(pos) != Terrain::Water {
+                return Err(CommandError::CanNotBuildHere);
+            }
+            if position::is_bridge_at(state, pos) {
+                return Err(CommandError::AlreadyBuilt);
+            }
+            Ok(())
+        },
+        Command::DigTrench{unit_id, pos} => {
+            check_engineer(db, player_id, state, unit_id, pos)?;
+            if *state.map().tile(pos) == Terrain::Water {
+                return Err(CommandError::CanNotBuildHere);
+            }
+            for object in state.objects_at(pos) {
+                match object.class {
+                    ObjectClass::Trench => return Err(CommandError::AlreadyBuilt),
+                    ObjectClass::Building => return Err(CommandError::CanNotBuildHere),
+                    ObjectClass::ReinforcementSector |
+                    ObjectClass::Road |
+                    ObjectClass::Smoke |
+                    ObjectClass::Bridge |
+                    ObjectClass::Minefield => {},
+                }
+            }
+            Ok(())
+        },
+        Command::LayMines{unit_id, pos} => {
+            check_engineer(db, player_id, state, unit_id, pos)?;
+            if *state.map().tile(pos) == Terrain::Water {
+                return Err(CommandError::CanNotBuildHere);
+            }
+            for object in state.objects_at(pos) {
+                match object.class {
+                    ObjectClass::Minefield => return Err(CommandError::AlreadyBuilt),
+                    ObjectClass::Building => return Err(CommandError::CanNotBuildHere),
+                    ObjectClass::ReinforcementSector |
+                    ObjectClass::Road |
+                    ObjectClass::Smoke |
+                    ObjectClass::Bridge |
+                    ObjectClass::Trench => {},
+                }
+            }
+            Ok(())
+        },
+        Command::Clear{unit_id, pos} => {
+            check_engineer(db, player_id, state, unit_id, pos)?;
+            let is_wreck = state.units_at(pos).any(|unit| !unit.is_alive);
+            // hidden enemy minefields are not returned by `objects_at`
+            let is_clearable_object = state.objects_at(pos).any(|object| {
+                object.class == ObjectClass::Trench
+                    || object.class == ObjectClass::Minefield
+            });
+            if !is_wreck && !is_clearable_object {
+                return Err(CommandError::NothingToClear);
+            }
+            Ok(())
+        },
     }
 }
 
@@ -404,8 +541,7 @@ pub fn check_attack(
             return Err(CommandError::NotEnoughReactiveAttackPoints);
         },
     }
-    let minimal_ok_morale = 50;
-    if attacker.morale < minimal_ok_morale {
+    if unit_morale_state(attacker) != MoraleState::Steady {
         return Err(CommandError::BadMorale);
     }
     let attacker_type = db.unit_type(attacker.type_id);
@@ -429,14 +565,130 @@ pub fn check_attack(
         }
     }
     let is_los_ok = los(db, state, attacker, defender);
-    if !weapon_type.is_inderect && !is_los_ok {
+    if weapon_type.is_inderect {
+        if !is_los_ok && !is_spotted(db, state, attacker, defender) {
+            return Err(CommandError::NoSpotter);
+        }
+    } else if !is_los_ok {
         return Err(CommandError::NoLos);
     }
     Ok(())
 }
 
-// TODO: profile and optimize!
-fn los(
+/// Engineers work on their own or a neighbour tile and it takes them
+/// the whole turn
+fn check_engineer(
+    db: &Db,
+    player_id: PlayerId,
+    state: &State,
+    unit_id: UnitId,
+    pos: MapPos,
+) -> Result<(), CommandError> {
+    let unit = match state.unit_opt(unit_id) {
+        Some(unit) => unit,
+        None => return Err(CommandError::BadUnitId),
+    };
+    if !unit.is_alive {
+        return Err(CommandError::UnitIsDead);
+    }
+    if unit.player_id != player_id {
+        return Err(CommandError::CanNotCommandEnemyUnits);
+    }
+    if unit::is_loaded_or_attached(unit) {
+        return Err(CommandError::BadUnitId);
+    }
+    if unit_morale_state(unit) != MoraleState::Steady {
+        return Err(CommandError::BadMorale);
+    }
+    let unit_type = db.unit_type(unit.type_id);
+    if !unit_type.is_engineer {
+        return Err(CommandError::BadUnitType);
+    }
+    if distance(unit.pos.map_pos, pos).n > 1 {
+        return Err(CommandError::OutOfRange);
+    }
+    if unit.move_points.unwrap() != unit_type.move_points {
+        return Err(CommandError::NotEnoughMovePoints);
+    }
+    Ok(())
+}
+
+/// Total size of the units that are already in the transporter
+fn passengers_size(db: &Db, state: &State, transporter: &Unit) -> i32 {
+    transporter.passenger_ids.iter()
+        .map(|&id| db.unit_type(state.unit(id).type_id).size)
+        .sum()
+}
+
+fn can_observe(
+    db: &Db,
+    state: &State,
+    observer: &Unit,
+    target: &Unit,
+) -> bool {
+    let observer_type = db.unit_type(observer.type_id);
+    let target_type = db.unit_type(target.type_id);
+    let visibility = calc_visibility(
+        state, observer_type, observer.pos.map_pos, target.pos.map_pos);
+    let is_visible = match visibility {
+        TileVisibility::Excellent => true,
+        TileVisibility::Normal => !target_type.is_infantry,
+        TileVisibility::No => false,
+    };
+    is_visible && los(db, state, observer, target)
+}
+
+/// Indirect fire needs some other friendly unit to observe the target
+fn is_spotted(
+    db: &Db,
+    state: &State,
+    attacker: &Unit,
+    defender: &Unit,
+) -> bool {
+    for (_, unit) in state.units() {
+        if unit.id == attacker.id
+            || unit.player_id != attacker.player_id
+            || !unit.is_alive
+            || unit::is_loaded_or_attached(unit)
+        {
+            continue;
+        }
+        if can_observe(db, state, unit, defender) {
+            return true;
+        }
+    }
+    false
+}
+
+fn distance_to_nearest_enemy(
+    state: &State,
+    unit: &Unit,
+    pos: MapPos,
+) -> Option<Distance> {
+    let mut min_distance = None;
+    for (_, enemy) in state.units() {
+        if enemy.player_id == unit.player_id || !enemy.is_alive {
+            continue;
+        }
+        let distance = distance(pos, enemy.pos.map_pos);
+        if min_distance.map_or(true, |min| distance < min) {
+            min_distance = Some(distance);
+        }
+    }
+    min_distance
+}
+
+/// Retreating units are not allowed to get closer to visible enemies
+fn is_advancing(state: &State, unit: &Unit, destination: ExactPos) -> bool {
+    let from = distance_to_nearest_enemy(state, unit, unit.pos.map_pos);
+    let to = distance_to_nearest_enemy(state, unit, destination.map_pos);
+    match (from, to) {
+        (Some(from), Some(to)) => to < from,
+        _ => false,
+    }
+}
+
+pub fn los(
     db: &Db,
     state: &State,
     attacker: &Unit,
@@ -447,12 +699,204 @@ fn los(
     let from = attacker.pos.map_pos;
     let to = defender.pos.map_pos;
     let range = attacker_type.los_range;
-    let mut v = false;
-    let f = if attacker_type.is_air || defender_type.is_air {
-        simple_fov
+    if attacker_type.is_air || defender_type.is_air {
+        distance(from, to) <= range
     } else {
-        fov
-    };
-    f(state, from, range, &mut |p| if p == to { v = true });
-    v
+        fov::los(state, from, to, range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use player::{PlayerId, PlayerInfo};
+    use position::{MapPos, ExactPos, SlotId, can_place_unit};
+    use unit::{Unit, UnitId, new_unit};
+    use event::{Command, CoreEvent, MoveMode};
+    use movement::{tile_cost};
+    use object::{ObjectId, ObjectClass};
+    use super::{CommandError};
+
+    fn create_unit(state: &mut State, id: i32, type_name: &str, pos: ExactPos) {
+        let type_id = state.db().unit_type_id(type_name);
+        let unit = new_unit(
+            state.db(), UnitId{id: id}, PlayerId{id: 0}, type_id, pos);
+        let cost = state.db().unit_type(type_id).cost;
+        state.apply_event(&CoreEvent::CreateUnit {
+            unit_info: unit,
+            cost: cost,
+        });
+    }
+
+    fn exact_pos(x: i32, slot_id: SlotId) -> ExactPos {
+        ExactPos {
+            map_pos: MapPos{v: Vector2{x: x, y: 0}},
+            slot_id: slot_id,
+        }
+    }
+
+    /// Transporter with id 0 and three soldiers (1, 2, 3) next to it
+    fn test_state(transporter_type_name: &str, slot_id: SlotId) -> State {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map05".to_owned(),
+            players_count: 2,
+        };
+        let mut state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        create_unit(&mut state, 0, transporter_type_name, exact_pos(1, slot_id));
+        // earn enough reinforcement points for the passengers
+        end_turn(&mut state);
+        for id in 1..4 {
+            create_unit(&mut state, id, "soldier", exact_pos(2, SlotId::Id(id as u8 - 1)));
+        }
+        state
+    }
+
+    /// Ends the turns of both players
+    fn end_turn(state: &mut State) {
+        for &(old_id, new_id) in &[(0, 1), (1, 0)] {
+            let new_id = PlayerId{id: new_id};
+            let turn = state.next_turn(new_id);
+            state.apply_event(&CoreEvent::EndTurn {
+                old_id: PlayerId{id: old_id},
+                new_id: new_id,
+                turn: turn,
+            });
+        }
+    }
+
+    fn check(state: &mut State, command: &Command) -> Result<(), CommandError> {
+        let db = state.db().clone();
+        let mut player_info = PlayerInfo::new(state, PlayerId{id: 0});
+        player_info.check_command(&db, state, command)
+    }
+
+    fn load(state: &mut State, passenger_id: i32) -> Result<(), CommandError> {
+        check(state, &Command::LoadUnit {
+            transporter_id: UnitId{id: 0},
+            passenger_id: UnitId{id: passenger_id},
+        })?;
+        let from = state.unit(UnitId{id: passenger_id}).pos;
+        let to = state.unit(UnitId{id: 0}).pos;
+        state.apply_event(&CoreEvent::LoadUnit {
+            transporter_id: Some(UnitId{id: 0}),
+            passenger_id: UnitId{id: passenger_id},
+            from: from,
+            to: to,
+        });
+        Ok(())
+    }
+
+    #[test]
+    fn test_truck_capacity() {
+        let mut state = test_state("truck", SlotId::Id(0));
+        assert_eq!(load(&mut state, 1), Ok(()));
+        assert_eq!(load(&mut state, 2), Ok(()));
+        assert_eq!(load(&mut state, 3), Err(CommandError::TransporterIsFull));
+        let transporter = state.unit(UnitId{id: 0});
+        assert_eq!(transporter.passenger_ids, vec![UnitId{id: 1}, UnitId{id: 2}]);
+    }
+
+    #[test]
+    fn test_helicopter_airlift() {
+        let mut state = test_state("helicopter", SlotId::Air);
+        let unload_command = |passenger_id| Command::UnloadUnit {
+            transporter_id: UnitId{id: 0},
+            passenger_id: UnitId{id: passenger_id},
+            pos: exact_pos(1, SlotId::Id(0)),
+        };
+        assert_eq!(load(&mut state, 1), Ok(()));
+        assert_eq!(state.unit(UnitId{id: 1}).pos, exact_pos(1, SlotId::Air));
+        // the crew can be dropped off in the same turn
+        assert_eq!(check(&mut state, &unload_command(1)), Ok(()));
+        assert_eq!(
+            check(&mut state, &unload_command(2)),
+            Err(CommandError::BadPassengerId),
+        );
+        let move_points = state.unit(UnitId{id: 0}).move_points.unwrap();
+        state.apply_event(&CoreEvent::Move {
+            unit_id: UnitId{id: 0},
+            from: exact_pos(1, SlotId::Air),
+            to: exact_pos(1, SlotId::Air),
+            mode: MoveMode::Fast,
+            cost: move_points,
+        });
+        assert_eq!(
+            check(&mut state, &unload_command(1)),
+            Err(CommandError::NotEnoughMovePoints),
+        );
+    }
+
+    #[test]
+    fn test_truck_tows_gun_with_crew() {
+        let mut state = test_state("truck", SlotId::Id(0));
+        create_unit(&mut state, 4, "field_gun", exact_pos(0, SlotId::Id(0)));
+        assert_eq!(load(&mut state, 1), Ok(()));
+        let attach_command = Command::Attach {
+            transporter_id: UnitId{id: 0},
+            attached_unit_id: UnitId{id: 4},
+        };
+        assert_eq!(check(&mut state, &attach_command), Ok(()));
+        state.apply_event(&CoreEvent::Attach {
+            transporter_id: UnitId{id: 0},
+            attached_unit_id: UnitId{id: 4},
+            from: exact_pos(1, SlotId::Id(0)),
+            to: exact_pos(0, SlotId::Id(0)),
+        });
+        let truck = state.unit(UnitId{id: 0}).clone();
+        assert_eq!(truck.passenger_ids, vec![UnitId{id: 1}]);
+        assert_eq!(state.unit(UnitId{id: 1}).pos, truck.pos);
+        let db = state.db().clone();
+        let from = truck.pos;
+        let to = exact_pos(1, SlotId::Id(0));
+        let towing_cost = tile_cost(&db, &state, &truck, from, to);
+        let unit = Unit{attached_unit_id: None, .. truck};
+        assert!(towing_cost > tile_cost(&db, &state, &unit, from, to));
+    }
+
+    #[test]
+    fn test_engineer_builds_bridge() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map01".to_owned(),
+            players_count: 2,
+        };
+        let mut state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        let bank = MapPos{v: Vector2{x: 4, y: 9}};
+        let water = MapPos{v: Vector2{x: 5, y: 9}};
+        create_unit(&mut state, 0, "engineer", ExactPos {
+            map_pos: bank,
+            slot_id: SlotId::Id(0),
+        });
+        let build_command = Command::BuildBridge {
+            unit_id: UnitId{id: 0},
+            pos: water,
+        };
+        let water_pos = ExactPos{map_pos: water, slot_id: SlotId::Id(0)};
+        let soldier_type = state.db().unit_type(state.db().unit_type_id("soldier")).clone();
+        for _ in 0..3 {
+            assert!(!can_place_unit(&state, &soldier_type, water_pos));
+            assert_eq!(check(&mut state, &build_command), Ok(()));
+            state.apply_event(&CoreEvent::Build {
+                id: ObjectId{id: 1000},
+                pos: water,
+                class: ObjectClass::Bridge,
+                unit_id: Some(UnitId{id: 0}),
+            });
+            assert_eq!(
+                check(&mut state, &build_command),
+                Err(CommandError::NotEnoughMovePoints),
+            );
+            end_turn(&mut state);
+        }
+        assert!(can_place_unit(&state, &soldier_type, water_pos));
+        assert_eq!(
+            check(&mut state, &build_command),
+            Err(CommandError::AlreadyBuilt),
+        );
+    }
 }
@@ -27,6 +27,7 @@ fn get_weapon_types() -> Vec<WeaponType> {
             max_air_distance: None,
             min_distance: Distance{n: 1},
             is_inderect: true,
+            blast_radius: Some(Distance{n: 0}),
             reaction_fire: false,
             smoke: Some(3),
         },
@@ -39,6 +40,7 @@ fn get_weapon_types() -> Vec<WeaponType> {
             max_air_distance: None,
             min_distance: Distance{n: 0},
             is_inderect: false,
+            blast_radius: Some(Distance{n: 0}),
             reaction_fire: true,
             smoke: None,
         },
@@ -51,6 +53,7 @@ fn get_weapon_types() -> Vec<WeaponType> {
             max_air_distance: None,
             min_distance: Distance{n: 0},
             is_inderect: false,
+            blast_radius: Some(Distance{n: 0}),
             reaction_fire: true,
             smoke: None,
         },
@@ -63,6 +66,7 @@ fn get_weapon_types() -> Vec<WeaponType> {
             max_air_distance: None,
             min_distance: Distance{n: 0},
             is_inderect: false,
+            blast_radius: None,
             reaction_fire: true,
             smoke: None,
         },
@@ -75,6 +79,7 @@ fn get_weapon_types() -> Vec<WeaponType> {
             max_air_distance: None,
             min_distance: Distance{n: 0},
             is_inderect: false,
+            blast_radius: None,
             reaction_fire: true,
             smoke: None,
         },
@@ -87,6 +92,7 @@ fn get_weapon_types() -> Vec<WeaponType> {
             max_air_distance: Some(Distance{n: 2}),
             min_distance: Distance{n: 0},
             is_inderect: false,
+            blast_radius: None,
             reaction_fire: true,
             smoke: None,
         },
@@ -99,6 +105,7 @@ fn get_weapon_types() -> Vec<WeaponType> {
             max_air_distance: Some(Distance{n: 1}),
             min_distance: Distance{n: 0},
             is_inderect: false,
+            blast_radius: None,
             reaction_fire: true,
             smoke: None,
         },
@@ -111,6 +118,7 @@ fn get_weapon_types() -> Vec<WeaponType> {
             max_air_distance: Some(Distance{n: 2}),
             min_distance: Distance{n: 0},
             is_inderect: false,
+            blast_radius: None,
             reaction_fire: true,
             smoke: None,
         },
@@ -131,12 +139,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 5},
             attack_points: AttackPoints{n: 1},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 120,
+            morale_recovery: 20,
             los_range: Distance{n: 7},
             cover_los_range: Distance{n: 0},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: true,
             is_air: false,
             is_infantry: false,
+            is_engineer: false,
             can_be_towed: false,
             cost: ReinforcementPoints{n: 16},
         },
@@ -151,12 +162,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 7},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 110,
+            morale_recovery: 15,
             los_range: Distance{n: 7},
             cover_los_range: Distance{n: 0},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: false,
+            is_engineer: false,
             can_be_towed: true,
             cost: ReinforcementPoints{n: 10},
         },
@@ -171,12 +185,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 8},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 100,
+            morale_recovery: 15,
             los_range: Distance{n: 7},
             cover_los_range: Distance{n: 0},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: false,
+            is_engineer: false,
             can_be_towed: true,
             cost: ReinforcementPoints{n: 8},
         },
@@ -191,12 +208,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 10},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 100,
+            morale_recovery: 15,
             los_range: Distance{n: 7},
             cover_los_range: Distance{n: 0},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: false,
+            is_engineer: false,
             can_be_towed: true,
             cost: ReinforcementPoints{n: 6},
         },
@@ -211,12 +231,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 10},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 100,
+            morale_recovery: 15,
             los_range: Distance{n: 7},
             cover_los_range: Distance{n: 0},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: false,
+            is_engineer: false,
             can_be_towed: true,
             cost: ReinforcementPoints{n: 6},
         },
@@ -232,12 +255,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 7},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 90,
+            morale_recovery: 10,
             los_range: Distance{n: 7},
             cover_los_range: Distance{n: 0},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: true,
+            is_engineer: false,
             can_be_towed: true,
             cost: ReinforcementPoints{n: 5},
         },
@@ -252,12 +278,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 12},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 80,
+            morale_recovery: 10,
             los_range: Distance{n: 8},
             cover_los_range: Distance{n: 0},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: false,
+            is_engineer: false,
             can_be_towed: true,
             cost: ReinforcementPoints{n: 4},
         },
@@ -272,12 +301,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 10},
             attack_points: AttackPoints{n: 0},
             reactive_attack_points: AttackPoints{n: 0},
+            max_morale: 70,
+            morale_recovery: 10,
             los_range: Distance{n: 6},
             cover_los_range: Distance{n: 0},
-            is_transporter: true,
+            transport_capacity: 8,
             is_big: false,
             is_air: false,
             is_infantry: false,
+            is_engineer: false,
             can_be_towed: true,
             cost: ReinforcementPoints{n: 4},
         },
@@ -292,12 +324,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 10},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 100,
+            morale_recovery: 15,
             los_range: Distance{n: 8},
             cover_los_range: Distance{n: 0},
-            is_transporter: false,
+            transport_capacity: 8,
             is_big: true,
             is_air: true,
             is_infantry: false,
+            is_engineer: false,
             can_be_towed: false,
             cost: ReinforcementPoints{n: 10},
         },
@@ -312,12 +347,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 9},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 100,
+            morale_recovery: 10,
             los_range: Distance{n: 6},
             cover_los_range: Distance{n: 1},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: true,
+            is_engineer: false,
             can_be_towed: false,
             cost: ReinforcementPoints{n: 2},
         },
@@ -332,12 +370,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 9},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 100,
+            morale_recovery: 10,
             los_range: Distance{n: 6},
             cover_los_range: Distance{n: 1},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: true,
+            is_engineer: false,
             can_be_towed: false,
             cost: ReinforcementPoints{n: 2},
         },
@@ -352,12 +393,15 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 11},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 80,
+            morale_recovery: 15,
             los_range: Distance{n: 8},
             cover_los_range: Distance{n: 2},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: true,
+            is_engineer: false,
             can_be_towed: false,
             cost: ReinforcementPoints{n: 3},
         },
@@ -372,15 +416,41 @@ fn get_unit_types(weapon_types: &[WeaponType]) -> Vec<UnitType> {
             move_points: MovePoints{n: 7},
             attack_points: AttackPoints{n: 2},
             reactive_attack_points: AttackPoints{n: 0},
+            max_morale: 90,
+            morale_recovery: 10,
             los_range: Distance{n: 6},
             cover_los_range: Distance{n: 1},
-            is_transporter: false,
+            transport_capacity: 0,
             is_big: false,
             is_air: false,
             is_infantry: true,
+            is_engineer: false,
             can_be_towed: false,
             cost: ReinforcementPoints{n: 4},
         },
+        UnitType {
+            name: "engineer".to_owned(),
+            size: 4,
+            count: 3,
+            armor: 1,
+            toughness: 2,
+            weapon_skill: 4,
+            weapon_type_id: weapon_type_id(weapon_types, "rifle"),
+            move_points: MovePoints{n: 9},
+            attack_points: AttackPoints{n: 2},
+            reactive_attack_points: AttackPoints{n: 1},
+            max_morale: 90,
+            morale_recovery: 10,
+            los_range: Distance{n: 6},
+            cover_los_range: Distance{n: 1},
+            transport_capacity: 0,
+            is_big: false,
+            is_air: false,
+            is_infantry: true,
+            is_engineer: true,
+            can_be_towed: false,
+            cost: ReinforcementPoints{n: 3},
+        },
     ]
 }
 
@@ -406,7 +476,7 @@ impl Db {
         }
     }
 
-    fn unit_type_id_opt(&self, name: &str) -> Option<UnitTypeId> {
+    pub fn unit_type_id_opt(&self, name: &str) -> Option<UnitTypeId> {
         for (id, unit_type) in self.unit_types.iter().enumerate() {
             if unit_type.name == name {
                 return Some(UnitTypeId{id: id as i32});
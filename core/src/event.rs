@@ -2,8 +2,10 @@ use unit::{Unit, UnitId, UnitTypeId};
 use position::{ExactPos, MapPos};
 use player::{PlayerId};
 use sector::{SectorId};
-use object::{ObjectId};
+use object::{ObjectId, Object, ObjectClass};
 use movement::{MovePoints};
+use victory::{GameResult};
+use game_state::{ReinforcementPoints};
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum FireMode {
@@ -35,10 +37,15 @@ pub enum Command {
     Detach{transporter_id: UnitId, pos: ExactPos},
     SetReactionFireMode{unit_id: UnitId, mode: ReactionFireMode},
     Smoke{unit_id: UnitId, pos: MapPos},
+    BuildBridge{unit_id: UnitId, pos: MapPos},
+    DigTrench{unit_id: UnitId, pos: MapPos},
+    LayMines{unit_id: UnitId, pos: MapPos},
+    Clear{unit_id: UnitId, pos: MapPos},
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct AttackInfo {
+    /// None if the attacker is hidden or if this is a crash of an air unit
     pub attacker_id: Option<UnitId>,
     pub defender_id: UnitId,
     pub mode: FireMode,
@@ -47,7 +54,12 @@ pub struct AttackInfo {
     pub remove_move_points: bool,
     pub is_ambush: bool,
     pub is_inderect: bool,
+    pub is_scattered: bool,
     pub leave_wrecks: bool,
+
+    /// Secondary result of a blast: the shell is already fired
+    /// so attacker's attack points are not spent
+    pub is_splash: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -62,13 +74,27 @@ pub enum CoreEvent {
     EndTurn {
         old_id: PlayerId,
         new_id: PlayerId,
+
+        /// Number of the game turn that the new player plays
+        turn: i32,
     },
     CreateUnit {
         unit_info: Unit,
+
+        /// Reinforcement points spent on the unit.
+        /// Units spawned by scenario triggers are free.
+        cost: ReinforcementPoints,
     },
     AttackUnit {
         attack_info: AttackInfo,
     },
+    /// A scattered shell landed in a tile with nobody to hit
+    ShellMissed {
+        /// None if the attacker is hidden
+        attacker_id: Option<UnitId>,
+        pos: MapPos,
+        mode: FireMode,
+    },
     // Reveal is like ShowUnit but is generated directly by Core
     Reveal {
         unit_info: Unit,
@@ -90,6 +116,7 @@ pub enum CoreEvent {
         transporter_id: Option<UnitId>,
         from: ExactPos,
         to: ExactPos,
+        cost: MovePoints,
     },
     Attach {
         transporter_id: UnitId,
@@ -101,6 +128,7 @@ pub enum CoreEvent {
         transporter_id: UnitId,
         from: ExactPos,
         to: ExactPos,
+        cost: MovePoints,
     },
     SetReactionFireMode {
         unit_id: UnitId,
@@ -110,11 +138,24 @@ pub enum CoreEvent {
         sector_id: SectorId,
         new_owner_id: Option<PlayerId>,
     },
+    SectorIncomeChanged {
+        sector_id: SectorId,
+        income: ReinforcementPoints,
+    },
     VictoryPoint {
         player_id: PlayerId,
         pos: MapPos,
         count: i32,
     },
+    Experience {
+        unit_id: UnitId,
+        count: i32,
+    },
+    /// Points carried over from the previous campaign mission
+    BonusReinforcementPoints {
+        player_id: PlayerId,
+        count: i32,
+    },
     // TODO: CreateObject
     Smoke {
         id: ObjectId,
@@ -125,4 +166,35 @@ pub enum CoreEvent {
     RemoveSmoke {
         id: ObjectId,
     },
+    /// One turn of engineers' work on a new or unfinished object
+    Build {
+        id: ObjectId,
+        pos: MapPos,
+        class: ObjectClass,
+        unit_id: Option<UnitId>,
+    },
+    Clear {
+        pos: MapPos,
+        unit_id: Option<UnitId>,
+        wreck_ids: Vec<UnitId>,
+        object_ids: Vec<ObjectId>,
+    },
+    GameOver {
+        result: GameResult,
+    },
+    /// A hidden object (an enemy minefield) was discovered
+    ShowObject {
+        id: ObjectId,
+        object: Object,
+    },
+    /// Scenario message. None if the message is for everyone.
+    Message {
+        player_id: Option<PlayerId>,
+        text: String,
+    },
+    /// Scenario trigger has shown these tiles to the player
+    RevealArea {
+        player_id: PlayerId,
+        area: Vec<MapPos>,
+    },
 }
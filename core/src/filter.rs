@@ -11,7 +11,7 @@ fn filtered_unit(unit: &Unit) -> Unit {
         move_points: None,
         attack_points: None,
         reactive_attack_points: None,
-        passenger_id: None,
+        passenger_ids: Vec::new(),
         .. unit.clone()
     }
 }
@@ -22,6 +22,10 @@ pub fn get_visible_enemies(
     player_id: PlayerId,
 ) -> HashSet<UnitId> {
     let mut visible_enemies = HashSet::new();
+    // deployed forces are revealed only when the first turn starts
+    if state.is_deployment_phase() {
+        return visible_enemies;
+    }
     for (&id, unit) in state.units() {
         if unit.player_id != player_id
             && fow.is_visible(unit)
@@ -105,35 +109,70 @@ pub fn filter_events(
                 active_unit_ids.insert(unit_id);
             }
         },
-        CoreEvent::CreateUnit{ref unit_info} => {
+        CoreEvent::CreateUnit{ref unit_info, ..} => {
             let unit = state.unit(unit_info.id);
-            if player_id == unit_info.player_id
-                || fow.is_visible_at(unit, unit_info.pos)
-            {
+            let is_visible = !state.is_deployment_phase()
+                && fow.is_visible_at(unit, unit_info.pos);
+            if player_id == unit_info.player_id || is_visible {
                 events.push(event.clone());
                 active_unit_ids.insert(unit_info.id);
             }
         },
         CoreEvent::AttackUnit{ref attack_info} => {
-            let attacker_id = attack_info.attacker_id
-                .expect("Core must know about everything");
-            let attacker = state.unit(attacker_id);
-            if player_id != attacker.player_id && !attack_info.is_ambush {
-                // show attacker if this is not ambush
+            let defender = state.unit_or_killed_unit(attack_info.defender_id);
+            // attacks without an attacker are crashes of air units
+            let is_secondary = attack_info.is_splash
+                || attack_info.attacker_id.is_none();
+            if is_secondary
+                && player_id != defender.player_id
+                && !fow.is_visible(defender)
+            {
+                // nobody sees what happened to this unit
+                return (events, active_unit_ids);
+            }
+            let mut is_attacker_visible = false;
+            if let Some(attacker_id) = attack_info.attacker_id {
                 let attacker = state.unit(attacker_id);
-                if !fow.is_visible(attacker) {
-                    events.push(CoreEvent::ShowUnit {
-                        unit_info: filtered_unit(attacker),
-                    });
+                if player_id != defender.player_id
+                    && !fow.is_visible(defender)
+                    && !fow.is_ground_tile_visible(defender.pos.map_pos)
+                {
+                    // a scattered shell hit somebody in the fog: nobody
+                    // sees the hit, the attacker's owner only sees the shot
+                    if player_id == attacker.player_id {
+                        events.push(CoreEvent::ShellMissed {
+                            attacker_id: Some(attacker_id),
+                            pos: defender.pos.map_pos,
+                            mode: attack_info.mode,
+                        });
+                    }
+                    return (events, active_unit_ids);
+                }
+                if player_id != attacker.player_id
+                    && !attack_info.is_ambush
+                    && !attack_info.is_splash
+                {
+                    // show attacker if this is not ambush
+                    if !fow.is_visible(attacker) {
+                        events.push(CoreEvent::ShowUnit {
+                            unit_info: filtered_unit(attacker),
+                        });
+                    }
+                    active_unit_ids.insert(attacker_id);
                 }
-                active_unit_ids.insert(attacker_id);
+                is_attacker_visible = player_id == attacker.player_id
+                    || !attack_info.is_ambush;
+            }
+            // a scattered shell may hit an unseen unit in a visible tile
+            if player_id != defender.player_id && !fow.is_visible(defender) {
+                events.push(CoreEvent::ShowUnit {
+                    unit_info: filtered_unit(defender),
+                });
             }
             active_unit_ids.insert(attack_info.defender_id); // if defender is killed
-            let is_attacker_visible = player_id == attacker.player_id
-                || !attack_info.is_ambush;
             let attack_info = AttackInfo {
                 attacker_id: if is_attacker_visible {
-                    Some(attacker_id)
+                    attack_info.attacker_id
                 } else {
                     None
                 },
@@ -141,6 +180,23 @@ pub fn filter_events(
             };
             events.push(CoreEvent::AttackUnit{attack_info: attack_info});
         },
+        CoreEvent::ShellMissed{attacker_id, pos, mode} => {
+            let attacker_id = attacker_id.expect("Core must know about everything");
+            let attacker = state.unit(attacker_id);
+            let is_attacker_visible = player_id == attacker.player_id
+                || fow.is_visible(attacker);
+            if is_attacker_visible || fow.is_ground_tile_visible(pos) {
+                events.push(CoreEvent::ShellMissed {
+                    attacker_id: if is_attacker_visible {
+                        Some(attacker_id)
+                    } else {
+                        None
+                    },
+                    pos: pos,
+                    mode: mode,
+                });
+            }
+        },
         CoreEvent::Reveal{ref unit_info} => {
             if unit_info.player_id != player_id {
                 events.push(CoreEvent::ShowUnit {
@@ -150,37 +206,25 @@ pub fn filter_events(
         },
         CoreEvent::ShowUnit{..} |
         CoreEvent::HideUnit{..} => panic!(),
-        CoreEvent::LoadUnit{passenger_id, from, to, transporter_id} => {
+        CoreEvent::LoadUnit{passenger_id, from, to, ..} => {
             let passenger = state.unit(passenger_id);
-            let transporter = state.unit(transporter_id.unwrap());
-            let is_transporter_vis = fow.is_visible(transporter);
-            let is_passenger_vis = fow.is_visible_at(passenger, from);
             if passenger.player_id == player_id {
                 events.push(event.clone());
-            } else if is_passenger_vis || is_transporter_vis {
-                if !fow.is_visible_at(passenger, from) {
-                    events.push(CoreEvent::ShowUnit {
-                        unit_info: Unit {
-                            pos: from,
-                            .. filtered_unit(passenger)
-                        },
-                    });
-                }
-                let filtered_transporter_id = if is_transporter_vis {
-                    transporter_id
-                } else {
-                    None
-                };
+            } else if fow.is_visible_at(passenger, from) {
+                // enemy sees the unit boarding but not what it boards
                 events.push(CoreEvent::LoadUnit {
-                    transporter_id: filtered_transporter_id,
+                    transporter_id: None,
                     passenger_id: passenger_id,
                     from: from,
                     to: to,
                 });
+                events.push(CoreEvent::HideUnit {
+                    unit_id: passenger_id,
+                });
                 active_unit_ids.insert(passenger_id);
             }
         },
-        CoreEvent::UnloadUnit{ref unit_info, transporter_id, from, to} => {
+        CoreEvent::UnloadUnit{ref unit_info, transporter_id, from, to, cost} => {
             active_unit_ids.insert(unit_info.id);
             let passenger = state.unit(unit_info.id);
             let transporter = state.unit(transporter_id.unwrap());
@@ -188,7 +232,8 @@ pub fn filter_events(
             let is_passenger_vis = fow.is_visible_at(passenger, to);
             if passenger.player_id == player_id {
                 events.push(event.clone());
-            } else if is_passenger_vis || is_transporter_vis {
+            } else if is_passenger_vis {
+                // passenger is revealed only when it leaves in view
                 let filtered_transporter_id = if is_transporter_vis {
                     transporter_id
                 } else {
@@ -196,21 +241,11 @@ pub fn filter_events(
                 };
                 events.push(CoreEvent::UnloadUnit {
                     transporter_id: filtered_transporter_id,
-                    unit_info: Unit {
-                        move_points: None,
-                        attack_points: None,
-                        reactive_attack_points: None,
-                        passenger_id: None,
-                        .. unit_info.clone()
-                    },
+                    unit_info: filtered_unit(unit_info),
                     from: from,
                     to: to,
+                    cost: cost,
                 });
-                if !is_passenger_vis {
-                    events.push(CoreEvent::HideUnit {
-                        unit_id: passenger.id,
-                    });
-                }
             }
         },
         CoreEvent::Attach{transporter_id, attached_unit_id, from, to} => {
@@ -247,7 +282,7 @@ pub fn filter_events(
                 }
             }
         },
-        CoreEvent::Detach{transporter_id, from, to} => {
+        CoreEvent::Detach{transporter_id, from, to, cost} => {
             let transporter = state.unit(transporter_id);
             if transporter.player_id == player_id {
                 events.push(event.clone())
@@ -273,7 +308,7 @@ pub fn filter_events(
                     events.push(CoreEvent::Move {
                         unit_id: transporter_id,
                         mode: MoveMode::Fast,
-                        cost: MovePoints{n: 0},
+                        cost: cost,
                         from: from,
                         to: to,
                     });
@@ -286,6 +321,12 @@ pub fn filter_events(
                 events.push(event.clone());
             }
         },
+        CoreEvent::Experience{unit_id, ..} => {
+            let unit = state.unit(unit_id);
+            if unit.player_id == player_id || fow.is_visible(unit) {
+                events.push(event.clone());
+            }
+        },
         CoreEvent::Smoke{id, pos, unit_id} => {
             let unit_id = unit_id.expect("Core must know about everything");
             let unit = state.unit(unit_id);
@@ -299,12 +340,276 @@ pub fn filter_events(
                 });
             }
         },
+        CoreEvent::Build{id, pos, class, unit_id} => {
+            if !fow.is_object_visible(id, &state.objects()[&id]) {
+                // enemy minefields are laid secretly
+                return (events, active_unit_ids);
+            }
+            let unit_id = unit_id.expect("Core must know about everything");
+            let unit = state.unit(unit_id);
+            let is_unit_vis = unit.player_id == player_id || fow.is_visible(unit);
+            events.push(CoreEvent::Build {
+                id: id,
+                pos: pos,
+                class: class,
+                unit_id: if is_unit_vis { Some(unit_id) } else { None },
+            });
+        },
+        CoreEvent::Clear{pos, unit_id, ref wreck_ids, ref object_ids} => {
+            let unit_id = unit_id.expect("Core must know about everything");
+            let unit = state.unit(unit_id);
+            let is_unit_vis = unit.player_id == player_id || fow.is_visible(unit);
+            // wrecks are already removed from the state
+            let filtered_wreck_ids = wreck_ids.iter().cloned().filter(|&id| {
+                let wreck = state.unit_or_killed_unit(id);
+                wreck.player_id == player_id || fow.is_visible(wreck)
+            }).collect();
+            active_unit_ids.extend(wreck_ids.iter().cloned());
+            // objects are already removed from the state too
+            let filtered_object_ids = object_ids.iter().cloned().filter(|&id| {
+                fow.is_object_visible(id, state.object_or_removed_object(id))
+            }).collect();
+            events.push(CoreEvent::Clear {
+                pos: pos,
+                unit_id: if is_unit_vis { Some(unit_id) } else { None },
+                wreck_ids: filtered_wreck_ids,
+                object_ids: filtered_object_ids,
+            });
+        },
+        CoreEvent::ShowObject{id, ref object} => {
+            // the blast is seen by everyone who sees the tile
+            if !fow.is_object_visible(id, object)
+                && fow.is_ground_tile_visible(object.pos.map_pos)
+            {
+                events.push(event.clone());
+            }
+        },
+        CoreEvent::Message{player_id: message_player_id, ..} => {
+            if message_player_id.map_or(true, |id| id == player_id) {
+                events.push(event.clone());
+            }
+        },
+        CoreEvent::RevealArea{player_id: reveal_player_id, ..} => {
+            if reveal_player_id == player_id {
+                events.push(event.clone());
+            }
+        },
         CoreEvent::EndTurn{..} |
+        CoreEvent::GameOver{..} |
         CoreEvent::RemoveSmoke{..} |
         CoreEvent::VictoryPoint{..} |
+        CoreEvent::BonusReinforcementPoints{..} |
+        CoreEvent::SectorIncomeChanged{..} |
         CoreEvent::SectorOwnerChanged{..} => {
             events.push(event.clone());
         },
     }
     (events, active_unit_ids)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use player::{PlayerId, PlayerInfo};
+    use position::{MapPos, ExactPos, SlotId};
+    use unit::{Unit, UnitId, new_unit};
+    use event::{CoreEvent, FireMode, AttackInfo};
+    use movement::{MovePoints};
+
+    const TRUCK_ID: UnitId = UnitId{id: 0};
+    const PASSENGER_ID: UnitId = UnitId{id: 1};
+    const ENEMY_ID: UnitId = UnitId{id: 2};
+
+    fn exact_pos(x: i32, y: i32, slot_id: u8) -> ExactPos {
+        ExactPos {
+            map_pos: MapPos{v: Vector2{x: x, y: y}},
+            slot_id: SlotId::Id(slot_id),
+        }
+    }
+
+    /// Full state and the enemy's partial view on it
+    struct TestGame {
+        state: State,
+        enemy_info: PlayerInfo,
+        enemy_state: State,
+    }
+
+    impl TestGame {
+        fn new(enemy_pos: ExactPos) -> TestGame {
+            let options = Options {
+                game_type: GameType::Hotseat,
+                map_name: "map01".to_owned(),
+                players_count: 2,
+            };
+            let db = Rc::new(Db::new());
+            let state = State::new_full(db.clone(), &options).unwrap();
+            let enemy_id = PlayerId{id: 1};
+            let mut game = TestGame {
+                enemy_info: PlayerInfo::new(&state, enemy_id),
+                enemy_state: State::new_partial(db, &options, enemy_id).unwrap(),
+                state: state,
+            };
+            game.create_unit(TRUCK_ID, "truck", 0, exact_pos(1, 0, 0));
+            game.create_unit(PASSENGER_ID, "soldier", 0, exact_pos(2, 0, 0));
+            game.create_unit(ENEMY_ID, "soldier", 1, enemy_pos);
+            game
+        }
+
+        fn create_unit(
+            &mut self,
+            id: UnitId,
+            type_name: &str,
+            player_id: i32,
+            pos: ExactPos,
+        ) {
+            let type_id = self.state.db().unit_type_id(type_name);
+            let unit = new_unit(
+                self.state.db(), id, PlayerId{id: player_id}, type_id, pos);
+            let cost = self.state.db().unit_type(type_id).cost;
+            self.apply(&CoreEvent::CreateUnit {
+                unit_info: unit,
+                cost: cost,
+            });
+        }
+
+        fn apply(&mut self, event: &CoreEvent) {
+            self.state.apply_event(event);
+            self.enemy_info.filter_event(&self.state, event);
+            while let Some(event) = self.enemy_info.get_event() {
+                self.enemy_state.apply_event(&event);
+            }
+        }
+
+        fn load(&mut self) {
+            let from = self.state.unit(PASSENGER_ID).pos;
+            let to = self.state.unit(TRUCK_ID).pos;
+            self.apply(&CoreEvent::LoadUnit {
+                transporter_id: Some(TRUCK_ID),
+                passenger_id: PASSENGER_ID,
+                from: from,
+                to: to,
+            });
+        }
+
+        fn unload(&mut self, to: ExactPos) {
+            let from = self.state.unit(TRUCK_ID).pos;
+            let unit_info = Unit {
+                pos: to,
+                is_loaded: false,
+                .. self.state.unit(PASSENGER_ID).clone()
+            };
+            self.apply(&CoreEvent::UnloadUnit {
+                transporter_id: Some(TRUCK_ID),
+                unit_info: unit_info,
+                from: from,
+                to: to,
+                cost: MovePoints{n: 0},
+            });
+        }
+    }
+
+    #[test]
+    fn test_passenger_is_hidden_from_enemy() {
+        let mut game = TestGame::new(exact_pos(0, 0, 0));
+        assert!(game.enemy_state.unit_opt(PASSENGER_ID).is_some());
+        game.load();
+        assert!(game.enemy_state.unit_opt(PASSENGER_ID).is_none());
+        let truck = game.enemy_state.unit(TRUCK_ID);
+        assert!(truck.passenger_ids.is_empty());
+    }
+
+    #[test]
+    fn test_passenger_is_revealed_on_unload_in_view() {
+        let mut game = TestGame::new(exact_pos(0, 0, 0));
+        game.load();
+        game.unload(exact_pos(2, 0, 0));
+        let passenger = game.enemy_state.unit(PASSENGER_ID);
+        assert_eq!(passenger.pos, exact_pos(2, 0, 0));
+        assert!(!passenger.is_loaded);
+    }
+
+    #[test]
+    fn test_unload_out_of_view_is_not_shown() {
+        let mut game = TestGame::new(exact_pos(9, 11, 0));
+        game.load();
+        game.unload(exact_pos(2, 0, 0));
+        assert!(game.enemy_state.unit_opt(TRUCK_ID).is_none());
+        assert!(game.enemy_state.unit_opt(PASSENGER_ID).is_none());
+    }
+
+    #[test]
+    fn test_attack_reveals_only_attacker() {
+        let mut game = TestGame::new(exact_pos(9, 11, 0));
+        let neighbour_id = UnitId{id: 3};
+        game.create_unit(neighbour_id, "soldier", 0, exact_pos(2, 0, 1));
+        assert!(game.enemy_state.unit_opt(PASSENGER_ID).is_none());
+        game.apply(&CoreEvent::AttackUnit {
+            attack_info: AttackInfo {
+                attacker_id: Some(PASSENGER_ID),
+                defender_id: ENEMY_ID,
+                mode: FireMode::Active,
+                killed: 0,
+                suppression: 0,
+                remove_move_points: false,
+                is_ambush: false,
+                is_inderect: false,
+                is_scattered: false,
+                leave_wrecks: false,
+                is_splash: false,
+            },
+        });
+        assert!(game.enemy_state.unit_opt(PASSENGER_ID).is_some());
+        assert!(game.enemy_state.unit_opt(neighbour_id).is_none());
+        game.apply(&CoreEvent::EndTurn {
+            old_id: PlayerId{id: 0},
+            new_id: PlayerId{id: 1},
+            turn: 1,
+        });
+        assert!(game.enemy_state.unit_opt(PASSENGER_ID).is_none());
+    }
+
+    #[test]
+    fn test_scattered_hit_into_fog_is_not_revealed() {
+        let mut game = TestGame::new(exact_pos(9, 11, 0));
+        assert!(game.enemy_state.unit_opt(TRUCK_ID).is_none());
+        let attack_points = game.state.unit(ENEMY_ID).attack_points.unwrap();
+        game.apply(&CoreEvent::AttackUnit {
+            attack_info: AttackInfo {
+                attacker_id: Some(ENEMY_ID),
+                defender_id: TRUCK_ID,
+                mode: FireMode::Active,
+                killed: 0,
+                suppression: 20,
+                remove_move_points: false,
+                is_ambush: false,
+                is_inderect: true,
+                is_scattered: true,
+                leave_wrecks: false,
+                is_splash: false,
+            },
+        });
+        assert!(game.enemy_state.unit_opt(TRUCK_ID).is_none());
+        // the attacker's owner still knows that the shot was fired
+        let enemy = game.enemy_state.unit(ENEMY_ID);
+        assert_eq!(enemy.attack_points.unwrap().n, attack_points.n - 1);
+    }
+
+    #[test]
+    fn test_missed_shell_does_not_reveal_attacker() {
+        let mut game = TestGame::new(exact_pos(9, 11, 0));
+        let attack_points = game.state.unit(PASSENGER_ID).attack_points.unwrap();
+        let pos = MapPos{v: Vector2{x: 8, y: 11}};
+        game.apply(&CoreEvent::ShellMissed {
+            attacker_id: Some(PASSENGER_ID),
+            pos: pos,
+            mode: FireMode::Active,
+        });
+        let passenger = game.state.unit(PASSENGER_ID);
+        assert_eq!(passenger.attack_points.unwrap().n, attack_points.n - 1);
+        assert!(game.enemy_state.unit_opt(PASSENGER_ID).is_none());
+    }
+}
@@ -1,9 +1,9 @@
 /// Field of View
 
 use std::f32::consts::{PI};
-use cgmath::{InnerSpace};
+use cgmath::{InnerSpace, Vector2};
 use game_state::{State};
-use map::{Terrain, Distance, spiral_iter};
+use map::{Terrain, Distance, spiral_iter, distance};
 use geom;
 use position::{MapPos};
 use object::{ObjectClass};
@@ -32,13 +32,31 @@ fn is_obstacle(state: &State, pos: MapPos) -> bool {
             ObjectClass::Building |
             ObjectClass::Smoke => return true,
             ObjectClass::ReinforcementSector |
-            ObjectClass::Road => {},
+            ObjectClass::Road |
+            ObjectClass::Bridge |
+            ObjectClass::Trench |
+            ObjectClass::Minefield => {},
         }
     }
     false
 }
 
-// TODO: precalculate all 'atan2' and 'asin' stuff
+/// Direction from the origin to the tile and distance to it
+fn angle_and_distance(origin3d: Vector2<f32>, pos: MapPos) -> (f32, f32) {
+    let diff = geom::map_pos_to_world_pos(pos) - origin3d;
+    (diff.x.atan2(diff.y), diff.magnitude())
+}
+
+fn add_shadow(shadows: &mut Vec<Shadow>, angle: f32, distance: f32) {
+    let obstacle_radius = geom::HEX_IN_RADIUS * 1.1;
+    let a = (obstacle_radius / distance).asin();
+    let shadow = Shadow{left: angle - a, right: angle + a};
+    if shadow.right > PI {
+        shadows.push(Shadow{left: -PI, right: shadow.right - PI * 2.0});
+    }
+    shadows.push(shadow);
+}
+
 pub fn fov(
     state: &State,
     origin: MapPos,
@@ -53,25 +71,50 @@ pub fn fov(
         if !map.is_inboard(pos) {
             continue;
         }
-        let pos3d = geom::map_pos_to_world_pos(pos);
-        let diff = pos3d - origin3d;
-        let distance = diff.magnitude();
-        let angle = diff.x.atan2(diff.y); // TODO: optimize
+        let (angle, distance) = angle_and_distance(origin3d, pos);
         if is_tile_visible(angle, &shadows) {
             callback(pos);
         }
         if is_obstacle(state, pos) {
-            let obstacle_radius = geom::HEX_IN_RADIUS * 1.1;
-            let a = (obstacle_radius / distance).asin();
-            let shadow = Shadow{left: angle - a, right: angle + a};
-            if shadow.right > PI {
-                shadows.push(Shadow{left: -PI, right: shadow.right - PI * 2.0});
-            }
-            shadows.push(shadow);
+            add_shadow(&mut shadows, angle, distance);
         }
     }
 }
 
+/// Same as `fov` but checks only one tile and stops as soon as
+/// the answer is known. Only obstacles' angles are calculated.
+pub fn los(
+    state: &State,
+    origin: MapPos,
+    target: MapPos,
+    range: Distance,
+) -> bool {
+    if origin == target {
+        return true;
+    }
+    if distance(origin, target) > range {
+        return false;
+    }
+    let map = state.map();
+    let mut shadows = vec!();
+    let origin3d = geom::map_pos_to_world_pos(origin);
+    let (target_angle, _) = angle_and_distance(origin3d, target);
+    for pos in spiral_iter(origin, range) {
+        if pos == target {
+            return true;
+        }
+        if !map.is_inboard(pos) || !is_obstacle(state, pos) {
+            continue;
+        }
+        let (angle, distance) = angle_and_distance(origin3d, pos);
+        add_shadow(&mut shadows, angle, distance);
+        if !is_tile_visible(target_angle, &shadows) {
+            return false;
+        }
+    }
+    unreachable!("Target must be in range")
+}
+
 pub fn simple_fov(
     state: &State,
     origin: MapPos,
@@ -85,3 +128,34 @@ pub fn simple_fov(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use map::{Distance};
+    use super::{fov, los};
+
+    #[test]
+    fn test_los_matches_fov() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map01".to_owned(),
+            players_count: 2,
+        };
+        let state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        let range = Distance{n: 7};
+        for origin in state.map().get_iter() {
+            let mut visible = Vec::new();
+            fov(&state, origin, range, &mut |pos| visible.push(pos));
+            for target in state.map().get_iter() {
+                assert_eq!(
+                    los(&state, origin, target, range),
+                    visible.contains(&target),
+                );
+            }
+        }
+    }
+}
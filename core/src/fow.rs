@@ -1,14 +1,15 @@
+use std::collections::{HashMap, HashSet};
 use std::default::{Default};
 use std::rc::{Rc};
 use game_state::{State};
-use map::{Map, Terrain, distance};
+use map::{Map, Terrain, Distance, distance};
 use fov::{fov, simple_fov};
 use db::{Db};
-use unit::{Unit, UnitType};
+use unit::{Unit, UnitId, UnitType, UnitTypeId};
 use position::{MapPos, ExactPos, SlotId};
 use event::{CoreEvent};
 use player::{PlayerId};
-use object::{ObjectClass};
+use object::{ObjectId, Object, ObjectClass};
 
 #[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
 pub enum TileVisibility {
@@ -22,7 +23,7 @@ impl Default for TileVisibility {
     fn default() -> Self { TileVisibility::No }
 }
 
-fn calc_visibility(
+pub fn calc_visibility(
     state: &State,
     unit_type: &UnitType,
     origin: MapPos,
@@ -46,17 +47,71 @@ fn calc_visibility(
                 vis = TileVisibility::Normal;
             }
             ObjectClass::Road |
-            ObjectClass::ReinforcementSector => {},
+            ObjectClass::ReinforcementSector |
+            ObjectClass::Bridge |
+            ObjectClass::Trench |
+            ObjectClass::Minefield => {},
         }
     }
     vis
 }
 
+/// Tiles seen by a unit from its current position
+#[derive(Clone, Debug)]
+struct UnitFov {
+    pos: ExactPos,
+    range: Distance,
+    ground: Vec<(MapPos, TileVisibility)>,
+    air: Vec<MapPos>,
+}
+
+fn calc_unit_fov(state: &State, unit_type: &UnitType, pos: ExactPos) -> UnitFov {
+    let origin = pos.map_pos;
+    let range = unit_type.los_range;
+    let mut ground = Vec::new();
+    {
+        let ground_cb = &mut |pos| {
+            let vis = calc_visibility(state, unit_type, origin, pos);
+            ground.push((pos, vis));
+        };
+        if pos.slot_id == SlotId::Air {
+            simple_fov(state, origin, range, ground_cb);
+        } else {
+            fov(state, origin, range, ground_cb);
+        }
+    }
+    let mut air = Vec::new();
+    simple_fov(state, origin, range, &mut |pos| air.push(pos));
+    UnitFov {
+        pos: pos,
+        range: range,
+        ground: ground,
+        air: air,
+    }
+}
+
 /// Fog of War
 #[derive(Clone, Debug)]
 pub struct Fow {
     map: Map<TileVisibility>,
     air_map: Map<TileVisibility>,
+
+    /// Tiles that were seen at least once. Unlike visibility
+    /// this layer is never reset.
+    explored: Map<bool>,
+
+    /// Units that gave themselves away (by firing, for example)
+    /// are visible at their positions until the end of the turn
+    /// even if their tiles are fogged.
+    revealed_units: HashMap<UnitId, ExactPos>,
+
+    /// Cached FoVs of player's units. Only units that have moved
+    /// or have seen the map change around them are recalculated.
+    unit_fovs: HashMap<UnitId, UnitFov>,
+
+    /// Enemy minefields that player's units have run into
+    discovered_objects: HashSet<ObjectId>,
+
     player_id: PlayerId,
     db: Rc<Db>,
 }
@@ -68,6 +123,10 @@ impl Fow {
         let mut fow = Fow {
             map: Map::new(map_size),
             air_map: Map::new(map_size),
+            explored: Map::new(map_size),
+            revealed_units: HashMap::new(),
+            unit_fovs: HashMap::new(),
+            discovered_objects: HashSet::new(),
             player_id: player_id,
             db: db,
         };
@@ -83,15 +142,29 @@ impl Fow {
         }
     }
 
+    pub fn is_explored(&self, pos: MapPos) -> bool {
+        *self.explored.tile(pos)
+    }
+
     pub fn is_visible(&self, unit: &Unit) -> bool {
+        // nobody can look inside enemy transporters
+        if unit.is_loaded && unit.player_id != self.player_id {
+            return false;
+        }
         self.is_visible_at(unit, unit.pos)
     }
 
     pub fn is_visible_at(&self, unit: &Unit, pos: ExactPos) -> bool {
+        self.revealed_units.get(&unit.id) == Some(&pos)
+            || self.is_type_visible_at(unit.type_id, pos)
+    }
+
+    /// Would a unit of this type be visible at this position
+    pub fn is_type_visible_at(&self, type_id: UnitTypeId, pos: ExactPos) -> bool {
         if pos.slot_id == SlotId::Air {
             *self.air_map.tile(pos.map_pos) != TileVisibility::No
         } else {
-            let unit_type = self.db.unit_type(unit.type_id);
+            let unit_type = self.db.unit_type(type_id);
             match *self.map.tile(pos.map_pos) {
                 TileVisibility::Excellent => true,
                 TileVisibility::Normal => !unit_type.is_infantry,
@@ -100,26 +173,43 @@ impl Fow {
         }
     }
 
+    pub fn is_object_visible(&self, id: ObjectId, object: &Object) -> bool {
+        !object.is_hidden_from(self.player_id)
+            || self.discovered_objects.contains(&id)
+    }
+
     fn fov_unit(&mut self, state: &State, unit: &Unit) {
         assert!(unit.is_alive);
-        let origin = unit.pos.map_pos;
-        let unit_type = self.db.unit_type(unit.type_id);
-        let range = unit_type.los_range;
-        let ground_fow = &mut self.map;
-        let ground_cb = &mut |pos| {
-            let vis = calc_visibility(state, unit_type, origin, pos);
-            if vis > *ground_fow.tile_mut(pos) {
-                *ground_fow.tile_mut(pos) = vis;
+        let is_cached = self.unit_fovs.get(&unit.id)
+            .map_or(false, |unit_fov| unit_fov.pos == unit.pos);
+        if !is_cached {
+            let unit_type = self.db.unit_type(unit.type_id);
+            let unit_fov = calc_unit_fov(state, unit_type, unit.pos);
+            self.unit_fovs.insert(unit.id, unit_fov);
+        }
+        let unit_fov = &self.unit_fovs[&unit.id];
+        for &(pos, vis) in &unit_fov.ground {
+            if vis > *self.map.tile(pos) {
+                *self.map.tile_mut(pos) = vis;
             }
-        };
-        if unit.pos.slot_id == SlotId::Air {
-            simple_fov(state, origin, range, ground_cb);
-        } else {
-            fov(state, origin, range, ground_cb);
+            if vis != TileVisibility::No {
+                *self.explored.tile_mut(pos) = true;
+            }
+        }
+        for &pos in &unit_fov.air {
+            *self.air_map.tile_mut(pos) = TileVisibility::Excellent;
         }
-        let air_fow = &mut self.air_map;
-        simple_fov(state, origin, range, &mut |pos| {
-            *air_fow.tile_mut(pos) = TileVisibility::Excellent;
+    }
+
+    /// Forgets all cached FoVs so they are recalculated on the next use
+    pub fn invalidate_all_unit_fovs(&mut self) {
+        self.unit_fovs.clear();
+    }
+
+    /// Forgets cached FoVs that may be affected by a change of this tile
+    fn invalidate_unit_fovs(&mut self, pos: MapPos) {
+        self.unit_fovs.retain(|_, unit_fov| {
+            distance(unit_fov.pos.map_pos, pos) > unit_fov.range
         });
     }
 
@@ -132,6 +222,14 @@ impl Fow {
 
     fn reset(&mut self, state: &State) {
         self.clear();
+        {
+            let player_id = self.player_id;
+            self.unit_fovs.retain(|&id, _| {
+                state.unit_opt(id).map_or(false, |unit| {
+                    unit.is_alive && unit.player_id == player_id
+                })
+            });
+        }
         for (_, unit) in state.units() {
             if unit.player_id == self.player_id && unit.is_alive {
                 self.fov_unit(state, unit);
@@ -145,6 +243,7 @@ impl Fow {
             }
             *self.map.tile_mut(object.pos) = TileVisibility::Excellent;
             *self.air_map.tile_mut(object.pos) = TileVisibility::Excellent;
+            *self.explored.tile_mut(object.pos) = true;
         }
     }
 
@@ -155,17 +254,19 @@ impl Fow {
     ) {
         match *event {
             CoreEvent::Move{unit_id, ..} => {
+                self.revealed_units.remove(&unit_id);
                 let unit = state.unit(unit_id);
                 if unit.player_id == self.player_id {
                     self.fov_unit(state, unit);
                 }
             },
             CoreEvent::EndTurn{new_id, ..} => {
+                self.revealed_units.clear();
                 if self.player_id == new_id {
                     self.reset(state);
                 }
             },
-            CoreEvent::CreateUnit{ref unit_info} => {
+            CoreEvent::CreateUnit{ref unit_info, ..} => {
                 let unit = state.unit(unit_info.id);
                 if self.player_id == unit_info.player_id {
                     self.fov_unit(state, unit);
@@ -173,35 +274,69 @@ impl Fow {
             },
             CoreEvent::AttackUnit{ref attack_info} => {
                 if let Some(attacker_id) = attack_info.attacker_id {
-                    if !attack_info.is_ambush {
-                        let pos = state.unit(attacker_id).pos;
-                        // TODO: do not give away all units in this tile!
-                        *self.map.tile_mut(pos) = TileVisibility::Excellent;
+                    let attacker = state.unit(attacker_id);
+                    if !attack_info.is_ambush
+                        && attacker.player_id != self.player_id
+                    {
+                        self.revealed_units.insert(attacker_id, attacker.pos);
                     }
                 }
             },
+            CoreEvent::ShowUnit{ref unit_info} => {
+                self.revealed_units.insert(unit_info.id, unit_info.pos);
+            },
+            CoreEvent::HideUnit{unit_id} => {
+                self.revealed_units.remove(&unit_id);
+            },
             CoreEvent::UnloadUnit{ref unit_info, ..} => {
                 if self.player_id == unit_info.player_id {
                     let unit = state.unit(unit_info.id);
                     self.fov_unit(state, unit);
                 }
             },
+            CoreEvent::Attach{transporter_id, ..} => {
+                self.revealed_units.remove(&transporter_id);
+            },
             CoreEvent::Detach{transporter_id, ..} => {
+                self.revealed_units.remove(&transporter_id);
                 let transporter = state.unit(transporter_id);
                 if self.player_id == transporter.player_id {
                     self.fov_unit(state, transporter);
                 }
             },
+            CoreEvent::Smoke{pos, ..} => {
+                self.invalidate_unit_fovs(pos);
+            },
+            CoreEvent::RemoveSmoke{..} => {
+                // the smoke is already removed from the state
+                // so it's not known where it was
+                self.invalidate_all_unit_fovs();
+            },
+            CoreEvent::ShowObject{id, ..} => {
+                self.discovered_objects.insert(id);
+            },
+            CoreEvent::RevealArea{player_id, ref area} => {
+                if self.player_id == player_id {
+                    for &pos in area {
+                        *self.map.tile_mut(pos) = TileVisibility::Excellent;
+                        *self.air_map.tile_mut(pos) = TileVisibility::Excellent;
+                        *self.explored.tile_mut(pos) = true;
+                    }
+                }
+            },
             CoreEvent::Reveal{..} |
-            CoreEvent::ShowUnit{..} |
-            CoreEvent::HideUnit{..} |
+            CoreEvent::ShellMissed{..} |
+            CoreEvent::GameOver{..} |
+            CoreEvent::Message{..} |
+            CoreEvent::SectorIncomeChanged{..} |
             CoreEvent::LoadUnit{..} |
-            CoreEvent::Attach{..} |
+            CoreEvent::Build{..} |
+            CoreEvent::Clear{..} |
             CoreEvent::SetReactionFireMode{..} |
             CoreEvent::SectorOwnerChanged{..} |
-            CoreEvent::Smoke{..} |
-            CoreEvent::RemoveSmoke{..} |
-            CoreEvent::VictoryPoint{..} => {},
+            CoreEvent::VictoryPoint{..} |
+            CoreEvent::BonusReinforcementPoints{..} |
+            CoreEvent::Experience{..} => {},
         }
     }
 }
@@ -1,21 +1,27 @@
 use std::collections::hash_map::{self, HashMap};
-use std::collections::{HashSet};
 use std::rc::{Rc};
+use std::{error, fmt, io, mem, slice};
 use cgmath::{Vector2};
 use types::{Size2};
-use unit::{Unit, UnitId};
+use unit::{Unit, UnitId, UnitType};
 use db::{Db};
-use map::{Map, Terrain};
+use map::{self, Map, Terrain};
 use dir::{Dir};
 use fow::{Fow};
 use sector::{Sector, SectorId};
 use position::{self, MapPos, ExactPos, SlotId};
 use event::{CoreEvent, FireMode};
 use player::{PlayerId};
-use object::{ObjectId, Object, ObjectClass};
+use object::{self, ObjectId, Object, ObjectClass};
 use movement::{MovePoints};
 use attack::{AttackPoints};
 use options::{Options};
+use morale::{self, MoraleState};
+use veterancy;
+use victory::{self, Objective, Condition, GameResult};
+use trigger::{self, Trigger, Action};
+use mapgen::{self, MapGenOptions};
+use map_file::{self, MapFile};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ReinforcementPoints{pub n: i32}
@@ -23,27 +29,36 @@ pub struct ReinforcementPoints{pub n: i32}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Score{pub n: i32}
 
-#[derive(Clone)]
-pub struct ObjectsAtIter<'a> {
-    it: hash_map::Iter<'a, ObjectId, Object>,
-    pos: MapPos,
+/// Reinforcement points that a player gets at the end of the turn
+#[derive(Clone, Debug, PartialEq)]
+pub struct Income {
+    pub base: ReinforcementPoints,
+    pub sectors: Vec<(SectorId, ReinforcementPoints)>,
 }
 
-impl<'a> ObjectsAtIter<'a> {
-    pub fn new(objects: &HashMap<ObjectId, Object>, pos: MapPos) -> ObjectsAtIter {
-        ObjectsAtIter{it: objects.iter(), pos: pos}
+impl Income {
+    pub fn total(&self) -> ReinforcementPoints {
+        let sectors_income: i32 = self.sectors.iter()
+            .map(|&(_, points)| points.n)
+            .sum();
+        ReinforcementPoints{n: self.base.n + sectors_income}
     }
 }
 
+#[derive(Clone)]
+pub struct ObjectsAtIter<'a> {
+    ids: slice::Iter<'a, ObjectId>,
+    state: &'a State,
+}
+
 impl<'a> Iterator for ObjectsAtIter<'a> {
     type Item = &'a Object;
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (_, object) in &mut self.it {
-            for map_pos in object.pos.map_pos_iter() {
-                if self.pos == map_pos {
-                    return Some(object);
-                }
+        for &id in &mut self.ids {
+            let object = &self.state.objects[&id];
+            if self.state.is_object_visible(id, object) {
+                return Some(object);
             }
         }
         None
@@ -52,16 +67,17 @@ impl<'a> Iterator for ObjectsAtIter<'a> {
 
 #[derive(Clone)]
 pub struct UnitsAtIter<'a> {
-    it: UnitIter<'a>,
-    pos: MapPos,
+    ids: slice::Iter<'a, UnitId>,
+    state: &'a State,
 }
 
 impl<'a> Iterator for UnitsAtIter<'a> {
     type Item = &'a Unit;
 
     fn next(&mut self) -> Option<Self::Item> {
-        for (_, unit) in &mut self.it {
-            if self.pos == unit.pos.map_pos {
+        for id in &mut self.ids {
+            let unit = &self.state.units[id];
+            if self.state.is_unit_visible(unit) {
                 return Some(unit);
             }
         }
@@ -98,6 +114,19 @@ pub struct State {
     score: HashMap<PlayerId, Score>,
     target_score: Score,
     reinforcement_points: HashMap<PlayerId, ReinforcementPoints>,
+    base_income: ReinforcementPoints,
+    objectives: Vec<Objective>,
+    game_result: Option<GameResult>,
+
+    /// Game turn. All players make a move during one turn.
+    /// Turn 0 is the deployment phase of scenarios that have one.
+    turn: i32,
+
+    turn_limit: Option<i32>,
+
+    /// Tiles where players place their starting forces
+    deployment_zones: HashMap<PlayerId, Vec<MapPos>>,
+
     players_count: i32,
     db: Rc<Db>,
 
@@ -110,43 +139,156 @@ pub struct State {
     //
     fow: Option<Fow>,
 
-    /// Hack for not filtering fogged units from ShowUnit events
-    shown_unit_ids: HashSet<UnitId>,
+    /// Ids of units and objects in every tile
+    /// for fast `units_at` and `objects_at` lookups
+    unit_ids_at: Map<Vec<UnitId>>,
+    object_ids_at: Map<Vec<ObjectId>>,
+
+    /// Units destroyed by the last applied event. Events are filtered
+    /// after they are applied so the filter needs to know who was there.
+    killed_units: HashMap<UnitId, Unit>,
+
+    /// Same as `killed_units` but for objects
+    removed_objects: HashMap<ObjectId, Object>,
 }
 
-fn basic_state(db: Rc<Db>, options: &Options) -> State {
+/// Why a map can't be loaded
+#[derive(Debug)]
+pub enum LoadMapError {
+    UnknownMapName,
+
+    /// Map file can't be read or is malformed
+    BadMapFile(io::Error),
+
+    RoadBetweenNonAdjacentTiles{from: MapPos, to: MapPos},
+}
+
+impl fmt::Display for LoadMapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadMapError::UnknownMapName => f.write_str("Unknown map name"),
+            LoadMapError::BadMapFile(ref err) => write!(
+                f, "Can`t load the map file: {}", err),
+            LoadMapError::RoadBetweenNonAdjacentTiles{from, to} => write!(
+                f, "Road connects non-adjacent tiles {} and {}", from, to),
+        }
+    }
+}
+
+impl error::Error for LoadMapError {
+    fn description(&self) -> &str {
+        match *self {
+            LoadMapError::UnknownMapName => "Unknown map name",
+            LoadMapError::BadMapFile(_) => "Bad map file",
+            LoadMapError::RoadBetweenNonAdjacentTiles{..} => {
+                "Road connects non-adjacent tiles"
+            },
+        }
+    }
+}
+
+fn basic_state(db: Rc<Db>, players_count: i32, map_info: MapInfo) -> State {
     let mut score = HashMap::new();
     score.insert(PlayerId{id: 0}, Score{n: 0});
     score.insert(PlayerId{id: 1}, Score{n: 0});
-    let mut reinforcement_points = HashMap::new();
-    reinforcement_points.insert(PlayerId{id: 0}, ReinforcementPoints{n: 10});
-    reinforcement_points.insert(PlayerId{id: 1}, ReinforcementPoints{n: 10});
-    let (map, objects, sectors, target_score) = load_map(&options.map_name);
+    let mut reinforcement_points = map_info.start_points;
+    let (turn, deployment_zones) = match map_info.deployment {
+        Some(deployment) => {
+            for points in reinforcement_points.values_mut() {
+                points.n += deployment.budget.n;
+            }
+            (0, deployment.zones)
+        },
+        None => (1, HashMap::new()),
+    };
+    let map = map_info.map;
+    let objects = map_info.objects;
+    let mut object_ids_at: Map<Vec<ObjectId>> = Map::new(map.size());
+    for (&id, object) in &objects {
+        for map_pos in object.pos.map_pos_iter() {
+            object_ids_at.tile_mut(map_pos).push(id);
+        }
+    }
     State {
+        unit_ids_at: Map::new(map.size()),
+        object_ids_at: object_ids_at,
         units: HashMap::new(),
         objects: objects,
         map: map,
-        sectors: sectors,
+        sectors: map_info.sectors,
         score: score,
-        target_score: target_score,
+        target_score: map_info.target_score,
         reinforcement_points: reinforcement_points,
-        players_count: options.players_count,
+        base_income: map_info.base_income,
+        objectives: map_info.objectives,
+        game_result: None,
+        turn: turn,
+        turn_limit: map_info.turn_limit,
+        deployment_zones: deployment_zones,
+        players_count: players_count,
         db: db,
         fow: None,
-        shown_unit_ids: HashSet::new(),
+        killed_units: HashMap::new(),
+        removed_objects: HashMap::new(),
     }
 }
 
 impl State {
-    pub fn new_full(db: Rc<Db>, options: &Options) -> State {
-        basic_state(db, options)
+    pub fn new_full(db: Rc<Db>, options: &Options) -> Result<State, LoadMapError> {
+        State::new_full_with_triggers(db, options).map(|(state, _)| state)
+    }
+
+    /// Full state and the scenario triggers that haven't fired yet.
+    /// Triggers are returned separately: only Core may know the script.
+    pub fn new_full_with_triggers(
+        db: Rc<Db>,
+        options: &Options,
+    ) -> Result<(State, Vec<Trigger>), LoadMapError> {
+        let mut map_info = load_map(&options.map_name)?;
+        let triggers = mem::replace(&mut map_info.triggers, Vec::new());
+        Ok((basic_state(db, options.players_count, map_info), triggers))
     }
 
-    pub fn new_partial(db: Rc<Db>, options: &Options, id: PlayerId) -> State {
-        let mut state = basic_state(db.clone(), options);
+    /// Two players' state on a map without objects and sectors
+    /// that is not in the list of playable maps
+    pub fn new_full_with_map(db: Rc<Db>, map: Map<Terrain>) -> State {
+        let map_info = MapInfo {
+            map: map,
+            objects: HashMap::new(),
+            sectors: HashMap::new(),
+            target_score: Score{n: 5},
+            start_points: start_points(&[10, 10]),
+            base_income: ReinforcementPoints{n: 10},
+            objectives: target_score_objectives(),
+            triggers: Vec::new(),
+            turn_limit: None,
+            deployment: None,
+        };
+        basic_state(db, 2, map_info)
+    }
+
+    /// Full state of a two player game on the map from a file
+    pub fn new_from_map_file(db: Rc<Db>, map_file: &MapFile) -> State {
+        basic_state(db, 2, map_file_info(map_file.clone()))
+    }
+
+    pub fn new_partial(
+        db: Rc<Db>,
+        options: &Options,
+        id: PlayerId,
+    ) -> Result<State, LoadMapError> {
+        let map_info = load_map(&options.map_name)?;
+        let mut state = basic_state(db.clone(), options.players_count, map_info);
+        let hidden_object_ids: Vec<_> = state.objects.iter()
+            .filter(|&(_, object)| object.is_hidden_from(id))
+            .map(|(&id, _)| id)
+            .collect();
+        for object_id in hidden_object_ids {
+            state.remove_object(object_id);
+        }
         let fow = Fow::new(&state, id);
         state.to_partial(fow);
-        state
+        Ok(state)
     }
 
     pub fn to_partial(&mut self, fow: Fow) {
@@ -190,6 +332,18 @@ impl State {
         }
     }
 
+    /// Routed units recover at the end of their turn, after falling back
+    fn recover_routed_units(&mut self, player_id: PlayerId) {
+        for unit in self.units.values_mut() {
+            if unit.player_id == player_id
+                && morale::unit_morale_state(unit) == MoraleState::Routed
+            {
+                let unit_type = self.db.unit_type(unit.type_id);
+                recover_morale(unit, unit_type);
+            }
+        }
+    }
+
     fn refresh_units(&mut self, player_id: PlayerId) {
         for unit in self.units.values_mut() {
             if unit.player_id == player_id {
@@ -203,10 +357,14 @@ impl State {
                 if let Some(ref mut reactive_attack_points) = unit.reactive_attack_points {
                     *reactive_attack_points = unit_type.reactive_attack_points;
                 }
-                unit.morale += 10;
-                let max_morale = 100; // TODO: get from UnitType
-                if unit.morale > max_morale {
-                    unit.morale = max_morale;
+                // routed units must fall back first, see `recover_routed_units`
+                if morale::unit_morale_state(unit) != MoraleState::Routed {
+                    recover_morale(unit, unit_type);
+                }
+                if morale::unit_morale_state(unit) == MoraleState::Pinned {
+                    if let Some(ref mut move_points) = unit.move_points {
+                        move_points.n = 0;
+                    }
                 }
             }
         }
@@ -214,9 +372,51 @@ impl State {
 
     fn add_unit(&mut self, unit: &Unit) {
         assert!(self.units.get(&unit.id).is_none());
+        self.unit_ids_at.tile_mut(unit.pos).push(unit.id);
         self.units.insert(unit.id, unit.clone());
     }
 
+    fn remove_unit(&mut self, id: UnitId) -> Unit {
+        let unit = self.units.remove(&id).expect("Can`t find unit");
+        self.unit_ids_at.tile_mut(unit.pos).retain(|&i| i != id);
+        unit
+    }
+
+    fn set_unit_pos(&mut self, id: UnitId, pos: ExactPos) {
+        let unit = self.units.get_mut(&id).expect("Can`t find unit");
+        if unit.pos.map_pos != pos.map_pos {
+            self.unit_ids_at.tile_mut(unit.pos).retain(|&i| i != id);
+            self.unit_ids_at.tile_mut(pos).push(id);
+        }
+        unit.pos = pos;
+    }
+
+    /// Engineers' work takes the whole turn
+    fn spend_all_points(&mut self, unit_id: UnitId) {
+        let unit = self.units.get_mut(&unit_id).expect("Bad unit id");
+        if let Some(ref mut move_points) = unit.move_points {
+            move_points.n = 0;
+        }
+        if let Some(ref mut attack_points) = unit.attack_points {
+            attack_points.n = 0;
+        }
+    }
+
+    fn add_object(&mut self, id: ObjectId, object: Object) {
+        for map_pos in object.pos.map_pos_iter() {
+            self.object_ids_at.tile_mut(map_pos).push(id);
+        }
+        self.objects.insert(id, object);
+    }
+
+    fn remove_object(&mut self, id: ObjectId) -> Object {
+        let object = self.objects.remove(&id).expect("Can`t find object");
+        for map_pos in object.pos.map_pos_iter() {
+            self.object_ids_at.tile_mut(map_pos).retain(|&i| i != id);
+        }
+        object
+    }
+
     pub fn units(&self) -> UnitIter {
         UnitIter {
             iter: self.units.iter(),
@@ -229,7 +429,14 @@ impl State {
             Some(ref fow) => fow,
             None => return true,
         };
-        fow.is_visible(unit) || self.shown_unit_ids.contains(&unit.id)
+        fow.is_visible(unit)
+    }
+
+    fn is_object_visible(&self, id: ObjectId, object: &Object) -> bool {
+        match self.fow {
+            Some(ref fow) => fow.is_object_visible(id, object),
+            None => true,
+        }
     }
 
     pub fn unit_opt(&self, id: UnitId) -> Option<&Unit> {
@@ -246,12 +453,38 @@ impl State {
         self.unit_opt(id).unwrap()
     }
 
+    /// Returns units that were destroyed by the last event too
+    pub fn unit_or_killed_unit(&self, id: UnitId) -> &Unit {
+        match self.killed_units.get(&id) {
+            Some(unit) => unit,
+            None => self.unit(id),
+        }
+    }
+
     pub fn units_at(&self, pos: MapPos) -> UnitsAtIter {
-        UnitsAtIter{it: self.units(), pos: pos}
+        UnitsAtIter {
+            ids: self.unit_ids_at.tile(pos).iter(),
+            state: self,
+        }
     }
 
     pub fn objects_at(&self, pos: MapPos) -> ObjectsAtIter {
-        ObjectsAtIter::new(self.objects(), pos)
+        ObjectsAtIter {
+            ids: self.object_ids_at.tile(pos).iter(),
+            state: self,
+        }
+    }
+
+    /// Returns objects that were removed by the last event too
+    pub fn object_or_removed_object(&self, id: ObjectId) -> &Object {
+        match self.removed_objects.get(&id) {
+            Some(object) => object,
+            None => &self.objects[&id],
+        }
+    }
+
+    pub fn object_ids_at(&self, pos: MapPos) -> &[ObjectId] {
+        self.object_ids_at.tile(pos)
     }
 
     pub fn unit_at_opt(&self, pos: ExactPos) -> Option<&Unit> {
@@ -287,6 +520,82 @@ impl State {
         &self.reinforcement_points
     }
 
+    pub fn objectives(&self) -> &[Objective] {
+        &self.objectives
+    }
+
+    pub fn game_result(&self) -> Option<GameResult> {
+        self.game_result
+    }
+
+    pub fn turn(&self) -> i32 {
+        self.turn
+    }
+
+    /// Turn after passing the move to `new_id`: a new turn starts
+    /// when the first player moves again
+    pub fn next_turn(&self, new_id: PlayerId) -> i32 {
+        if new_id == (PlayerId{id: 0}) {
+            self.turn + 1
+        } else {
+            self.turn
+        }
+    }
+
+    /// The game ends after this turn
+    pub fn turn_limit(&self) -> Option<i32> {
+        self.turn_limit
+    }
+
+    pub fn is_deployment_phase(&self) -> bool {
+        self.turn == 0
+    }
+
+    pub fn deployment_zone(&self, player_id: PlayerId) -> &[MapPos] {
+        match self.deployment_zones.get(&player_id) {
+            Some(zone) => zone,
+            None => &[],
+        }
+    }
+
+    /// Counts own turns of players' `HoldSectors` and `Survive` objectives
+    fn update_objectives(&mut self, player_id: PlayerId) {
+        for objective in &mut self.objectives {
+            if objective.player_id != player_id {
+                continue;
+            }
+            match objective.condition {
+                Condition::HoldSectors{ref sector_ids, ..} => {
+                    if victory::are_sectors_held(
+                        &self.sectors, player_id, sector_ids)
+                    {
+                        objective.turns += 1;
+                    } else {
+                        objective.turns = 0;
+                    }
+                },
+                Condition::Survive{..} => {
+                    objective.turns += 1;
+                },
+                Condition::TargetScore |
+                Condition::DestroyUnit{..} |
+                Condition::EliminateEnemies => {},
+            }
+        }
+    }
+
+    pub fn income(&self, player_id: PlayerId) -> Income {
+        let mut sectors: Vec<_> = self.sectors.iter()
+            .filter(|&(_, sector)| sector.owner_id == Some(player_id))
+            .map(|(&id, sector)| (id, sector.income))
+            .collect();
+        sectors.sort_by_key(|&(id, _)| id.id);
+        Income {
+            base: self.base_income,
+            sectors: sectors,
+        }
+    }
+
     pub fn is_ground_tile_visible(&self, pos: MapPos) -> bool {
         if let Some(ref fow) = self.fow {
             fow.is_ground_tile_visible(pos)
@@ -295,34 +604,59 @@ impl State {
         }
     }
 
+    pub fn is_tile_explored(&self, pos: MapPos) -> bool {
+        if let Some(ref fow) = self.fow {
+            fow.is_explored(pos)
+        } else {
+            true
+        }
+    }
+
+    fn spend_attack_point(&mut self, unit_id: UnitId, mode: FireMode) {
+        let unit = match self.units.get_mut(&unit_id) {
+            Some(unit) => unit,
+            None => return,
+        };
+        let points = match mode {
+            FireMode::Active => &mut unit.attack_points,
+            FireMode::Reactive => &mut unit.reactive_attack_points,
+        };
+        if let Some(ref mut points) = *points {
+            assert!(points.n >= 1);
+            points.n -= 1;
+        }
+    }
+
     pub fn apply_event(&mut self, event: &CoreEvent) {
+        self.killed_units.clear();
+        self.removed_objects.clear();
         match *event {
             CoreEvent::Move{unit_id, to, cost, ..} => {
+                self.set_unit_pos(unit_id, to);
                 {
                     let unit = self.units.get_mut(&unit_id).unwrap();
-                    unit.pos = to;
                     if let Some(ref mut move_points) = unit.move_points {
                         assert!(move_points.n > 0);
                         move_points.n -= cost.n;
                         assert!(move_points.n >= 0);
                     }
                 }
-                if let Some(passenger_id) = self.units[&unit_id].passenger_id {
-                    let passenger = self.units.get_mut(&passenger_id).unwrap();
-                    passenger.pos = to;
+                for passenger_id in self.units[&unit_id].passenger_ids.clone() {
+                    self.set_unit_pos(passenger_id, to);
                 }
                 if let Some(attached_unit_id) = self.units[&unit_id].attached_unit_id {
-                    let attached_unit = self.units.get_mut(&attached_unit_id).unwrap();
-                    attached_unit.pos = to;
+                    self.set_unit_pos(attached_unit_id, to);
                 }
             },
-            CoreEvent::EndTurn{new_id, old_id} => {
-                self.shown_unit_ids.clear();
-                {
-                    let reinforcement_points = self.reinforcement_points
-                        .get_mut(&old_id).unwrap();
-                    reinforcement_points.n += 10;
+            CoreEvent::EndTurn{new_id, old_id, turn} => {
+                // deployment is not a real turn
+                if !self.is_deployment_phase() {
+                    let income = self.income(old_id).total();
+                    self.reinforcement_points.get_mut(&old_id).unwrap().n += income.n;
+                    self.update_objectives(old_id);
                 }
+                self.recover_routed_units(old_id);
+                self.turn = turn;
                 self.refresh_units(new_id);
                 self.convert_ap(old_id);
                 for object in self.objects.values_mut() {
@@ -332,24 +666,34 @@ impl State {
                     }
                 }
             },
-            CoreEvent::CreateUnit{ref unit_info} => {
+            CoreEvent::CreateUnit{ref unit_info, cost} => {
                 {
-                    let unit_type = self.db.unit_type(unit_info.type_id);
                     let reinforcement_points = self.reinforcement_points
                         .get_mut(&unit_info.player_id).unwrap();
-                    assert!(*reinforcement_points >= unit_type.cost);
-                    reinforcement_points.n -= unit_type.cost.n;
+                    assert!(*reinforcement_points >= cost);
+                    reinforcement_points.n -= cost.n;
                 }
                 self.add_unit(unit_info);
             },
             CoreEvent::AttackUnit{ref attack_info} => {
+                let morale_penalties = {
+                    let defender = &self.units[&attack_info.defender_id];
+                    morale::nearby_deaths_morale_penalties(
+                        &self.db, self, defender, attack_info.killed)
+                };
+                for (unit_id, penalty) in morale_penalties {
+                    let unit = self.units.get_mut(&unit_id).unwrap();
+                    lower_morale(unit, penalty);
+                }
                 let count;
                 {
                     let unit = self.units.get_mut(&attack_info.defender_id)
                         .expect("Can`t find defender");
                     unit.count -= attack_info.killed;
-                    unit.morale -= attack_info.suppression;
-                    if attack_info.remove_move_points {
+                    lower_morale(unit, attack_info.suppression);
+                    let is_pinned = morale::unit_morale_state(unit)
+                        == MoraleState::Pinned;
+                    if attack_info.remove_move_points || is_pinned {
                         if let Some(ref mut move_points) = unit.move_points {
                             move_points.n = 0;
                         }
@@ -357,10 +701,10 @@ impl State {
                     count = unit.count;
                 }
                 if count <= 0 {
-                    if let Some(passenger_id)
-                        = self.unit(attack_info.defender_id).passenger_id
-                    {
-                        self.units.remove(&passenger_id).unwrap();
+                    let passenger_ids = self.unit(attack_info.defender_id)
+                        .passenger_ids.clone();
+                    for passenger_id in passenger_ids {
+                        self.remove_unit(passenger_id);
                     }
                     if let Some(attached_unit_id)
                         = self.unit(attack_info.defender_id).attached_unit_id
@@ -373,94 +717,84 @@ impl State {
                     if attack_info.leave_wrecks {
                         let unit = self.units.get_mut(&attack_info.defender_id).unwrap();
                         unit.attached_unit_id = None;
-                        unit.passenger_id = None;
+                        unit.passenger_ids.clear();
                         unit.is_alive = false;
                     } else {
-                        assert!(self.units.get(&attack_info.defender_id).is_some());
-                        self.units.remove(&attack_info.defender_id);
+                        let unit = self.remove_unit(attack_info.defender_id);
+                        self.killed_units.insert(unit.id, unit);
                     }
                 }
-                if let Some(attacker_id) = attack_info.attacker_id {
-                    if let Some(unit) = self.units.get_mut(&attacker_id) {
-                        match attack_info.mode {
-                            FireMode::Active => {
-                                if let Some(ref mut attack_points)
-                                    = unit.attack_points
-                                {
-                                    assert!(attack_points.n >= 1);
-                                    attack_points.n -= 1;
-                                }
-                            },
-                            FireMode::Reactive => {
-                                if let Some(ref mut reactive_attack_points)
-                                    = unit.reactive_attack_points
-                                {
-                                    assert!(reactive_attack_points.n >= 1);
-                                    reactive_attack_points.n -= 1;
-                                }
-                            },
-                        }
-                    }
+                // splash results don't spend attack points for the second time
+                let attacker_id = match attack_info.attacker_id {
+                    Some(id) if !attack_info.is_splash => Some(id),
+                    _ => None,
+                };
+                if let Some(attacker_id) = attacker_id {
+                    self.spend_attack_point(attacker_id, attack_info.mode);
+                }
+            },
+            CoreEvent::ShellMissed{attacker_id, mode, ..} => {
+                if let Some(attacker_id) = attacker_id {
+                    self.spend_attack_point(attacker_id, mode);
                 }
             },
             CoreEvent::Reveal{..} => (),
             CoreEvent::ShowUnit{ref unit_info} => {
                 self.add_unit(unit_info);
-                self.shown_unit_ids.insert(unit_info.id);
             },
             CoreEvent::HideUnit{unit_id} => {
-                assert!(self.units.get(&unit_id).is_some());
-                self.units.remove(&unit_id);
+                self.remove_unit(unit_id);
             },
             CoreEvent::LoadUnit{passenger_id, transporter_id, to, ..} => {
-                // TODO: hide info about passenger from enemy player
                 if let Some(transporter_id) = transporter_id {
                     self.units.get_mut(&transporter_id)
                         .expect("Bad transporter_id")
-                        .passenger_id = Some(passenger_id);
+                        .passenger_ids.push(passenger_id);
                 }
+                self.set_unit_pos(passenger_id, to);
                 let passenger = self.units.get_mut(&passenger_id)
                     .expect("Bad passenger_id");
-                passenger.pos = to;
                 passenger.is_loaded = true;
                 if let Some(ref mut move_points) = passenger.move_points {
                     move_points.n = 0;
                 }
             },
-            CoreEvent::UnloadUnit{transporter_id, ref unit_info, ..} => {
+            CoreEvent::UnloadUnit{transporter_id, ref unit_info, cost, ..} => {
                 if let Some(transporter_id) = transporter_id {
-                    self.units.get_mut(&transporter_id)
-                        .expect("Bad transporter_id")
-                        .passenger_id = None;
+                    let transporter = self.units.get_mut(&transporter_id)
+                        .expect("Bad transporter_id");
+                    transporter.passenger_ids.retain(|&id| id != unit_info.id);
+                    if let Some(ref mut move_points) = transporter.move_points {
+                        move_points.n -= cost.n;
+                        assert!(move_points.n >= 0);
+                    }
                 }
                 if self.unit_opt(unit_info.id).is_some() {
+                    self.set_unit_pos(unit_info.id, unit_info.pos);
                     let unit = self.units.get_mut(&unit_info.id).unwrap();
-                    unit.pos = unit_info.pos;
                     unit.is_loaded = false;
                 } else {
                     self.add_unit(unit_info);
                 }
             },
             CoreEvent::Attach{transporter_id, attached_unit_id, to, ..} => {
-                if let Some(passenger_id) = self.unit(transporter_id).passenger_id {
-                    let passenger = self.units.get_mut(&passenger_id).unwrap();
-                    passenger.pos = to;
+                for passenger_id in self.unit(transporter_id).passenger_ids.clone() {
+                    self.set_unit_pos(passenger_id, to);
                 }
                 {
                     let attached_unit = self.units.get_mut(&attached_unit_id).unwrap();
                     attached_unit.is_attached = true;
                 }
+                self.set_unit_pos(transporter_id, to);
                 let transporter = self.units.get_mut(&transporter_id).unwrap();
-                transporter.pos = to;
                 if let Some(ref mut move_points) = transporter.move_points {
                     move_points.n = 0;
                 }
                 transporter.attached_unit_id = Some(attached_unit_id);
             },
-            CoreEvent::Detach{transporter_id, to, ..} => {
-                if let Some(passenger_id) = self.unit(transporter_id).passenger_id {
-                    let passenger = self.units.get_mut(&passenger_id).unwrap();
-                    passenger.pos = to;
+            CoreEvent::Detach{transporter_id, to, cost, ..} => {
+                for passenger_id in self.unit(transporter_id).passenger_ids.clone() {
+                    self.set_unit_pos(passenger_id, to);
                 }
                 if let Some(attached_unit_id) = self.unit(transporter_id).attached_unit_id {
                     let attached_unit = self.units.get_mut(&attached_unit_id).unwrap();
@@ -469,11 +803,12 @@ impl State {
                         move_points.n = 0;
                     }
                 }
+                self.set_unit_pos(transporter_id, to);
                 let transporter = self.units.get_mut(&transporter_id).unwrap();
                 transporter.attached_unit_id = None;
-                transporter.pos = to;
                 if let Some(ref mut move_points) = transporter.move_points {
-                    move_points.n = 0;
+                    move_points.n -= cost.n;
+                    assert!(move_points.n >= 0);
                 }
             },
             CoreEvent::SetReactionFireMode{unit_id, mode} => {
@@ -485,9 +820,21 @@ impl State {
                 let sector = self.sectors.get_mut(&sector_id).unwrap();
                 sector.owner_id = new_owner_id;
             },
+            CoreEvent::SectorIncomeChanged{sector_id, income} => {
+                let sector = self.sectors.get_mut(&sector_id).unwrap();
+                sector.income = income;
+            },
             CoreEvent::VictoryPoint{player_id, count, ..} => {
                 self.score.get_mut(&player_id).unwrap().n += count;
             },
+            CoreEvent::BonusReinforcementPoints{player_id, count} => {
+                self.reinforcement_points.get_mut(&player_id).unwrap().n += count;
+            },
+            CoreEvent::Experience{unit_id, count} => {
+                let unit = self.units.get_mut(&unit_id)
+                    .expect("Bad unit id");
+                unit.experience += count;
+            },
             CoreEvent::Smoke{pos, id, unit_id} => {
                 if let Some(unit_id) = unit_id {
                     if let Some(unit) = self.units.get_mut(&unit_id) {
@@ -498,7 +845,7 @@ impl State {
                 }
                 let smoke_duration_in_turns = 3; // TODO: get from config
                 let timer = smoke_duration_in_turns * self.players_count - 1;
-                self.objects.insert(id, Object {
+                self.add_object(id, Object {
                     class: ObjectClass::Smoke,
                     pos: ExactPos {
                         map_pos: pos,
@@ -506,10 +853,65 @@ impl State {
                     },
                     timer: Some(timer),
                     owner_id: None,
+                    build_turns_left: 0,
                 });
             },
             CoreEvent::RemoveSmoke{id} => {
-                self.objects.remove(&id);
+                self.remove_object(id);
+            },
+            CoreEvent::GameOver{result} => {
+                self.game_result = Some(result);
+            },
+            CoreEvent::Message{..} |
+            CoreEvent::RevealArea{..} => {},
+            CoreEvent::ShowObject{id, ref object} => {
+                if !self.objects.contains_key(&id) {
+                    self.add_object(id, object.clone());
+                }
+            },
+            CoreEvent::Build{id, pos, class, unit_id} => {
+                if let Some(unit_id) = unit_id {
+                    self.spend_all_points(unit_id);
+                }
+                if self.objects.contains_key(&id) {
+                    let object = self.objects.get_mut(&id).unwrap();
+                    assert_eq!(object.class, class);
+                    assert!(!object.is_finished());
+                    object.build_turns_left -= 1;
+                } else {
+                    // only the owner is told about new minefields
+                    // so the engineer is always known here
+                    let owner_id = match class {
+                        ObjectClass::Minefield => unit_id.map(|id| {
+                            self.units[&id].player_id
+                        }),
+                        _ => None,
+                    };
+                    self.add_object(id, Object {
+                        class: class,
+                        pos: ExactPos {
+                            map_pos: pos,
+                            slot_id: SlotId::WholeTile,
+                        },
+                        timer: None,
+                        owner_id: owner_id,
+                        build_turns_left: object::build_turns(class) - 1,
+                    });
+                }
+            },
+            CoreEvent::Clear{unit_id, ref wreck_ids, ref object_ids, ..} => {
+                if let Some(unit_id) = unit_id {
+                    self.spend_all_points(unit_id);
+                }
+                for &id in wreck_ids {
+                    let unit = self.remove_unit(id);
+                    assert!(!unit.is_alive);
+                    self.killed_units.insert(id, unit);
+                }
+                for &id in object_ids {
+                    let object = self.remove_object(id);
+                    self.removed_objects.insert(id, object);
+                }
             },
         }
         if self.fow.is_some() {
@@ -521,15 +923,41 @@ impl State {
 }
 
 // TODO: create trees, buildings and roads like units - using event system
+fn recover_morale(unit: &mut Unit, unit_type: &UnitType) {
+    let veterancy = veterancy::unit_veterancy(unit);
+    unit.morale += unit_type.morale_recovery
+        + veterancy.morale_recovery_bonus();
+    if unit.morale > unit_type.max_morale {
+        unit.morale = unit_type.max_morale;
+    }
+}
+
+/// Morale never drops below zero so a routed unit can rally in a turn
+fn lower_morale(unit: &mut Unit, penalty: i32) {
+    unit.morale -= penalty;
+    if unit.morale < 0 {
+        unit.morale = 0;
+    }
+}
+
 fn add_object(objects: &mut HashMap<ObjectId, Object>, object: Object) {
     let id = ObjectId{id: objects.len() as i32 + 1};
     objects.insert(id, object);
 }
 
-fn add_road(objects: &mut HashMap<ObjectId, Object>, path: &[MapPos]) {
+fn add_road(
+    objects: &mut HashMap<ObjectId, Object>,
+    path: &[MapPos],
+) -> Result<(), LoadMapError> {
     for window in path.windows(2) {
         let from = window[0];
         let to = window[1];
+        if map::distance(from, to).n != 1 {
+            return Err(LoadMapError::RoadBetweenNonAdjacentTiles {
+                from: from,
+                to: to,
+            });
+        }
         let dir = Dir::get_dir_from_to(from, to);
         let object = Object {
             class: ObjectClass::Road,
@@ -539,9 +967,11 @@ fn add_road(objects: &mut HashMap<ObjectId, Object>, path: &[MapPos]) {
             },
             timer: None,
             owner_id: None,
+            build_turns_left: 0,
         };
         add_object(objects, object);
     }
+    Ok(())
 }
 
 fn add_reinforcement_sector(
@@ -557,6 +987,39 @@ fn add_reinforcement_sector(
         },
         timer: None,
         owner_id: owner_id,
+        build_turns_left: 0,
+    };
+    add_object(objects, object);
+}
+
+fn add_bridge(objects: &mut HashMap<ObjectId, Object>, pos: MapPos) {
+    let object = Object {
+        class: ObjectClass::Bridge,
+        pos: ExactPos {
+            map_pos: pos,
+            slot_id: SlotId::WholeTile,
+        },
+        timer: None,
+        owner_id: None,
+        build_turns_left: 0,
+    };
+    add_object(objects, object);
+}
+
+fn add_minefield(
+    objects: &mut HashMap<ObjectId, Object>,
+    pos: MapPos,
+    owner_id: PlayerId,
+) {
+    let object = Object {
+        class: ObjectClass::Minefield,
+        pos: ExactPos {
+            map_pos: pos,
+            slot_id: SlotId::WholeTile,
+        },
+        timer: None,
+        owner_id: Some(owner_id),
+        build_turns_left: 0,
     };
     add_object(objects, object);
 }
@@ -576,6 +1039,7 @@ fn add_buildings(
             pos: obj_pos,
             timer: None,
             owner_id: None,
+            build_turns_left: 0,
         };
         add_object(objects, object);
     }
@@ -595,31 +1059,142 @@ fn add_big_building(
         },
         timer: None,
         owner_id: None,
+        build_turns_left: 0,
     };
     add_object(objects, object);
 }
 
-type MapInfo = (
-    Map<Terrain>,
-    HashMap<ObjectId, Object>,
-    HashMap<SectorId, Sector>,
-    Score,
-);
+/// Optional pre-battle phase when players secretly place their starting forces
+struct Deployment {
+    zones: HashMap<PlayerId, Vec<MapPos>>,
+
+    /// Reinforcement points that every player gets for the deployment
+    budget: ReinforcementPoints,
+}
+
+struct MapInfo {
+    map: Map<Terrain>,
+    objects: HashMap<ObjectId, Object>,
+    sectors: HashMap<SectorId, Sector>,
+    target_score: Score,
+    start_points: HashMap<PlayerId, ReinforcementPoints>,
+
+    /// Income that players get even without any captured sectors
+    base_income: ReinforcementPoints,
+
+    objectives: Vec<Objective>,
+    triggers: Vec<Trigger>,
+    turn_limit: Option<i32>,
+    deployment: Option<Deployment>,
+}
+
+/// Classic objective: the first to get the target score wins
+fn target_score_objectives() -> Vec<Objective> {
+    vec![
+        Objective::new(PlayerId{id: 0}, Condition::TargetScore),
+        Objective::new(PlayerId{id: 1}, Condition::TargetScore),
+    ]
+}
+
+fn start_points(points: &[i32]) -> HashMap<PlayerId, ReinforcementPoints> {
+    points.iter().enumerate()
+        .map(|(i, &n)| (PlayerId{id: i as i32}, ReinforcementPoints{n: n}))
+        .collect()
+}
+
+/// Built-in maps
+pub const MAP_NAMES: &'static [&'static str] = &[
+    "map01",
+    "map02",
+    "map03",
+    "map04",
+    "map05",
+    "map06",
+    "map07",
+    "map_fov_bug_test",
+];
 
 // TODO: read from scenario.json?
-fn load_map(map_name: &str) -> MapInfo {
-    match map_name {
-        "map01" => load_map_01(),
+fn load_map(map_name: &str) -> Result<MapInfo, LoadMapError> {
+    let map_info = match map_name {
+        "map01" => load_map_01()?,
         "map02" => load_map_02(),
         "map03" => load_map_03(),
         "map04" => load_map_04(),
         "map05" => load_map_05(),
+        "map06" => load_map_06(),
+        "map07" => load_map_07(),
         "map_fov_bug_test" => load_map_fov_bug_test(),
-        _ => unimplemented!(),
+        _ if map_name.ends_with(map_file::FILE_EXTENSION) => {
+            let map_file = MapFile::load(map_name)
+                .map_err(LoadMapError::BadMapFile)?;
+            map_file_info(map_file)
+        },
+        _ => match MapGenOptions::from_map_name(map_name) {
+            Some(options) => load_random_map(&options)?,
+            None => return Err(LoadMapError::UnknownMapName),
+        },
+    };
+    Ok(map_info)
+}
+
+/// Map files describe only the map so the rules are the classic ones
+fn map_file_info(map_file: MapFile) -> MapInfo {
+    MapInfo {
+        map: map_file.map,
+        objects: map_file.objects,
+        sectors: map_file.sectors,
+        target_score: map_file.target_score,
+        start_points: start_points(&[10, 10]),
+        base_income: ReinforcementPoints{n: 5},
+        objectives: target_score_objectives(),
+        triggers: Vec::new(),
+        turn_limit: Some(30),
+        deployment: None,
     }
 }
 
-fn load_map_01() -> MapInfo {
+fn load_random_map(options: &MapGenOptions) -> Result<MapInfo, LoadMapError> {
+    let generated = mapgen::generate(options);
+    let mut map = generated.map;
+    let mut objects = HashMap::new();
+    // buildings go first: they need free slots
+    for &(pos, count) in &generated.buildings {
+        add_buildings(&mut map, &mut objects, pos, count);
+    }
+    for road in &generated.roads {
+        add_road(&mut objects, road)?;
+    }
+    for &pos in &generated.bridges {
+        add_bridge(&mut objects, pos);
+    }
+    for &(pos, player_id) in &generated.reinforcement_sectors {
+        add_reinforcement_sector(&mut objects, pos, Some(player_id));
+    }
+    let towns_count = generated.sectors.len() as i32 / 2;
+    let sectors = generated.sectors.into_iter()
+        .enumerate()
+        .map(|(i, positions)| (SectorId{id: i as i32}, Sector {
+            positions: positions,
+            owner_id: None,
+            income: ReinforcementPoints{n: 3},
+        }))
+        .collect();
+    Ok(MapInfo {
+        map: map,
+        objects: objects,
+        sectors: sectors,
+        target_score: Score{n: 5 + towns_count},
+        start_points: start_points(&[10, 10]),
+        base_income: ReinforcementPoints{n: 5},
+        objectives: target_score_objectives(),
+        triggers: Vec::new(),
+        turn_limit: Some(30),
+        deployment: None,
+    })
+}
+
+fn load_map_01() -> Result<MapInfo, LoadMapError> {
     let target_score = Score{n: 7};
     let map_size = Size2{w: 10, h: 12};
     let mut objects = HashMap::new();
@@ -676,7 +1251,7 @@ fn load_map_01() -> MapInfo {
         MapPos{v: Vector2{x: 7, y: 3}},
         MapPos{v: Vector2{x: 8, y: 3}},
         MapPos{v: Vector2{x: 9, y: 3}},
-    ]);
+    ])?;
     add_road(&mut objects, &[
         MapPos{v: Vector2{x: 2, y: 2}},
         MapPos{v: Vector2{x: 3, y: 3}},
@@ -689,7 +1264,12 @@ fn load_map_01() -> MapInfo {
         MapPos{v: Vector2{x: 6, y: 9}},
         MapPos{v: Vector2{x: 6, y: 10}},
         MapPos{v: Vector2{x: 7, y: 11}},
-    ]);
+    ])?;
+    for &(x, y) in &[
+        (5, 8),
+    ] {
+        add_bridge(&mut objects, MapPos{v: Vector2{x: x, y: y}});
+    }
     for &((x, y), player_index) in &[
         ((0, 1), 0),
         ((0, 7), 0),
@@ -715,6 +1295,7 @@ fn load_map_01() -> MapInfo {
                 MapPos{v: Vector2{x: 6, y: 2}},
             ],
             owner_id: None,
+            income: ReinforcementPoints{n: 3},
         },
     );
     sectors.insert(
@@ -730,9 +1311,21 @@ fn load_map_01() -> MapInfo {
                 MapPos{v: Vector2{x: 6, y: 6}},
             ],
             owner_id: None,
+            income: ReinforcementPoints{n: 3},
         },
     );
-    (map, objects, sectors, target_score)
+    Ok(MapInfo {
+        map: map,
+        objects: objects,
+        sectors: sectors,
+        target_score: target_score,
+        start_points: start_points(&[10, 10]),
+        base_income: ReinforcementPoints{n: 5},
+        objectives: target_score_objectives(),
+        triggers: Vec::new(),
+        turn_limit: None,
+        deployment: None,
+    })
 }
 
 fn load_map_02() -> MapInfo {
@@ -766,6 +1359,7 @@ fn load_map_02() -> MapInfo {
                 MapPos{v: Vector2{x: 4, y: 3}},
             ],
             owner_id: None,
+            income: ReinforcementPoints{n: 2},
         },
     );
     sectors.insert(
@@ -775,9 +1369,21 @@ fn load_map_02() -> MapInfo {
                 MapPos{v: Vector2{x: 5, y: 8}},
             ],
             owner_id: None,
+            income: ReinforcementPoints{n: 2},
         },
     );
-    (map, objects, sectors, target_score)
+    MapInfo {
+        map: map,
+        objects: objects,
+        sectors: sectors,
+        target_score: target_score,
+        start_points: start_points(&[10, 10]),
+        base_income: ReinforcementPoints{n: 5},
+        objectives: target_score_objectives(),
+        triggers: Vec::new(),
+        turn_limit: None,
+        deployment: None,
+    }
 }
 
 fn load_map_03() -> MapInfo {
@@ -801,7 +1407,18 @@ fn load_map_03() -> MapInfo {
             Some(PlayerId{id: player_index}),
         );
     }
-    (map, objects, sectors, target_score)
+    MapInfo {
+        map: map,
+        objects: objects,
+        sectors: sectors,
+        target_score: target_score,
+        start_points: start_points(&[10, 10]),
+        base_income: ReinforcementPoints{n: 10},
+        objectives: target_score_objectives(),
+        triggers: Vec::new(),
+        turn_limit: None,
+        deployment: None,
+    }
 }
 
 fn load_map_04() -> MapInfo {
@@ -825,7 +1442,18 @@ fn load_map_04() -> MapInfo {
             Some(PlayerId{id: player_index}),
         );
     }
-    (map, objects, sectors, target_score)
+    MapInfo {
+        map: map,
+        objects: objects,
+        sectors: sectors,
+        target_score: target_score,
+        start_points: start_points(&[10, 10]),
+        base_income: ReinforcementPoints{n: 10},
+        objectives: target_score_objectives(),
+        triggers: Vec::new(),
+        turn_limit: None,
+        deployment: None,
+    }
 }
 
 fn load_map_05() -> MapInfo {
@@ -844,7 +1472,227 @@ fn load_map_05() -> MapInfo {
             Some(PlayerId{id: player_index}),
         );
     }
-    (map, objects, sectors, target_score)
+    MapInfo {
+        map: map,
+        objects: objects,
+        sectors: sectors,
+        target_score: target_score,
+        start_points: start_points(&[10, 10]),
+        base_income: ReinforcementPoints{n: 10},
+        objectives: target_score_objectives(),
+        triggers: Vec::new(),
+        turn_limit: None,
+        deployment: None,
+    }
+}
+
+/// Map02 with minefields in front of both players
+fn load_map_06() -> MapInfo {
+    let target_score = Score{n: 5};
+    let map_size = Size2{w: 9, h: 12};
+    let mut objects = HashMap::new();
+    let mut map = Map::new(map_size);
+    let mut sectors = HashMap::new();
+    for &((x, y), terrain) in &[
+        ((3, 6), Terrain::Trees),
+        ((3, 7), Terrain::Trees),
+    ] {
+        *map.tile_mut(MapPos{v: Vector2{x: x, y: y}}) = terrain;
+    }
+    for &((x, y), player_index) in &[
+        ((0, 4), 0),
+        ((0, 10), 0),
+        ((8, 4), 1),
+        ((8, 10), 1),
+    ] {
+        add_reinforcement_sector(
+            &mut objects,
+            MapPos{v: Vector2{x: x, y: y}},
+            Some(PlayerId{id: player_index}),
+        );
+    }
+    for &((x, y), player_index) in &[
+        ((2, 5), 0),
+        ((2, 9), 0),
+        ((6, 5), 1),
+        ((6, 9), 1),
+    ] {
+        add_minefield(
+            &mut objects,
+            MapPos{v: Vector2{x: x, y: y}},
+            PlayerId{id: player_index},
+        );
+    }
+    sectors.insert(
+        SectorId{id: 0},
+        Sector {
+            positions: vec![
+                MapPos{v: Vector2{x: 4, y: 3}},
+            ],
+            owner_id: None,
+            income: ReinforcementPoints{n: 2},
+        },
+    );
+    sectors.insert(
+        SectorId{id: 1},
+        Sector {
+            positions: vec![
+                MapPos{v: Vector2{x: 5, y: 8}},
+            ],
+            owner_id: None,
+            income: ReinforcementPoints{n: 2},
+        },
+    );
+    MapInfo {
+        map: map,
+        objects: objects,
+        sectors: sectors,
+        target_score: target_score,
+        start_points: start_points(&[10, 10]),
+        base_income: ReinforcementPoints{n: 5},
+        objectives: vec![
+            Objective::new(PlayerId{id: 0}, Condition::TargetScore),
+            Objective::new(PlayerId{id: 1}, Condition::TargetScore),
+            Objective::new(PlayerId{id: 0}, Condition::HoldSectors {
+                sector_ids: vec![SectorId{id: 0}, SectorId{id: 1}],
+                turns: 3,
+            }),
+        ],
+        triggers: vec![
+            Trigger {
+                condition: trigger::Condition::TurnReached{turn: 4},
+                actions: vec![
+                    Action::SpawnUnits {
+                        player_id: PlayerId{id: 1},
+                        units: vec![
+                            ("medium_tank".to_owned(), MapPos{v: Vector2{x: 8, y: 4}}),
+                            ("soldier".to_owned(), MapPos{v: Vector2{x: 8, y: 10}}),
+                        ],
+                    },
+                    Action::ShowMessage {
+                        player_id: None,
+                        text: "Enemy reinforcements have arrived".to_owned(),
+                    },
+                ],
+            },
+            Trigger {
+                condition: trigger::Condition::SectorCaptured {
+                    sector_id: SectorId{id: 0},
+                    player_id: PlayerId{id: 0},
+                },
+                actions: vec![
+                    Action::ShowMessage {
+                        player_id: Some(PlayerId{id: 0}),
+                        text: "Scouts report on the southern sector".to_owned(),
+                    },
+                    Action::RevealArea {
+                        player_id: PlayerId{id: 0},
+                        area: vec![
+                            MapPos{v: Vector2{x: 5, y: 7}},
+                            MapPos{v: Vector2{x: 4, y: 8}},
+                            MapPos{v: Vector2{x: 5, y: 8}},
+                            MapPos{v: Vector2{x: 6, y: 8}},
+                            MapPos{v: Vector2{x: 5, y: 9}},
+                        ],
+                    },
+                ],
+            },
+            Trigger {
+                condition: trigger::Condition::UnitEntersArea {
+                    player_id: PlayerId{id: 0},
+                    area: vec![
+                        MapPos{v: Vector2{x: 7, y: 4}},
+                        MapPos{v: Vector2{x: 7, y: 10}},
+                    ],
+                },
+                actions: vec![
+                    Action::SetSectorIncome {
+                        sector_id: SectorId{id: 1},
+                        income: ReinforcementPoints{n: 4},
+                    },
+                    Action::ShowMessage {
+                        player_id: Some(PlayerId{id: 1}),
+                        text: "Our rear is under threat!".to_owned(),
+                    },
+                ],
+            },
+        ],
+        turn_limit: Some(15),
+        deployment: None,
+    }
+}
+
+/// Skirmish that starts with both players secretly deploying their forces
+fn load_map_07() -> MapInfo {
+    let target_score = Score{n: 10};
+    let map_size = Size2{w: 10, h: 12};
+    let mut objects = HashMap::new();
+    let mut map = Map::new(map_size);
+    let mut sectors = HashMap::new();
+    for &((x, y), terrain) in &[
+        ((3, 2), Terrain::Trees),
+        ((3, 3), Terrain::Trees),
+        ((6, 8), Terrain::Trees),
+        ((6, 9), Terrain::Trees),
+        ((4, 6), Terrain::Water),
+        ((5, 6), Terrain::Water),
+    ] {
+        *map.tile_mut(MapPos{v: Vector2{x: x, y: y}}) = terrain;
+    }
+    for &((x, y), count) in &[
+        ((5, 2), 2),
+        ((4, 9), 2),
+    ] {
+        let pos = MapPos{v: Vector2{x: x, y: y}};
+        add_buildings(&mut map, &mut objects, pos, count);
+    }
+    for &((x, y), player_index) in &[
+        ((0, 1), 0),
+        ((0, 10), 0),
+        ((9, 1), 1),
+        ((9, 10), 1),
+    ] {
+        add_reinforcement_sector(
+            &mut objects,
+            MapPos{v: Vector2{x: x, y: y}},
+            Some(PlayerId{id: player_index}),
+        );
+    }
+    for (i, &(x, y)) in [(5, 3), (4, 8)].iter().enumerate() {
+        sectors.insert(
+            SectorId{id: i as i32},
+            Sector {
+                positions: vec![MapPos{v: Vector2{x: x, y: y}}],
+                owner_id: None,
+                income: ReinforcementPoints{n: 3},
+            },
+        );
+    }
+    let mut zones = HashMap::new();
+    for &(player_index, xs) in &[(0, [0, 1]), (1, [8, 9])] {
+        let mut zone = Vec::new();
+        for y in 0..map_size.h {
+            for &x in &xs {
+                zone.push(MapPos{v: Vector2{x: x, y: y}});
+            }
+        }
+        zones.insert(PlayerId{id: player_index}, zone);
+    }
+    MapInfo {
+        map: map,
+        objects: objects,
+        sectors: sectors,
+        target_score: target_score,
+        start_points: start_points(&[0, 0]),
+        base_income: ReinforcementPoints{n: 5},
+        objectives: target_score_objectives(),
+        triggers: Vec::new(),
+        turn_limit: Some(20),
+        deployment: Some(Deployment {
+            zones: zones,
+            budget: ReinforcementPoints{n: 40},
+        }),
+    }
 }
 
 /// Map for repoducing of https://github.com/ozkriff/zoc/issues/149
@@ -869,5 +1717,118 @@ fn load_map_fov_bug_test() -> MapInfo {
             Some(PlayerId{id: player_index}),
         );
     }
-    (map, objects, sectors, target_score)
+    MapInfo {
+        map: map,
+        objects: objects,
+        sectors: sectors,
+        target_score: target_score,
+        start_points: start_points(&[10, 10]),
+        base_income: ReinforcementPoints{n: 10},
+        objectives: target_score_objectives(),
+        triggers: Vec::new(),
+        turn_limit: None,
+        deployment: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap};
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use options::{Options, GameType};
+    use player::{PlayerId};
+    use position::{MapPos, ExactPos, SlotId};
+    use unit::{UnitId, new_unit};
+    use event::{CoreEvent, MoveMode};
+    use movement::{MovePoints};
+    use sector::{SectorId};
+    use super::{State, ReinforcementPoints, LoadMapError, add_road};
+
+    fn exact_pos(x: i32, y: i32) -> ExactPos {
+        ExactPos {
+            map_pos: MapPos{v: Vector2{x: x, y: y}},
+            slot_id: SlotId::Id(0),
+        }
+    }
+
+    fn unit_ids_at(state: &State, pos: ExactPos) -> Vec<UnitId> {
+        state.units_at(pos.map_pos).map(|unit| unit.id).collect()
+    }
+
+    #[test]
+    fn test_units_at_follows_events() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map01".to_owned(),
+            players_count: 2,
+        };
+        let mut state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        let type_id = state.db().unit_type_id("soldier");
+        let id = UnitId{id: 0};
+        let (from, to) = (exact_pos(1, 0), exact_pos(2, 0));
+        let unit = new_unit(state.db(), id, PlayerId{id: 0}, type_id, from);
+        let cost = state.db().unit_type(type_id).cost;
+        state.apply_event(&CoreEvent::CreateUnit {
+            unit_info: unit,
+            cost: cost,
+        });
+        assert_eq!(unit_ids_at(&state, from), vec![id]);
+        state.apply_event(&CoreEvent::Move {
+            unit_id: id,
+            from: from,
+            to: to,
+            mode: MoveMode::Fast,
+            cost: MovePoints{n: 1},
+        });
+        assert!(unit_ids_at(&state, from).is_empty());
+        assert_eq!(unit_ids_at(&state, to), vec![id]);
+        state.apply_event(&CoreEvent::HideUnit{unit_id: id});
+        assert!(unit_ids_at(&state, to).is_empty());
+    }
+
+    #[test]
+    fn test_income_depends_on_owned_sectors() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map01".to_owned(),
+            players_count: 2,
+        };
+        let mut state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        let (player_id, enemy_id) = (PlayerId{id: 0}, PlayerId{id: 1});
+        assert!(state.income(player_id).sectors.is_empty());
+        state.apply_event(&CoreEvent::SectorOwnerChanged {
+            sector_id: SectorId{id: 1},
+            new_owner_id: Some(player_id),
+        });
+        let income = state.income(player_id);
+        assert_eq!(income.sectors, vec![(SectorId{id: 1}, ReinforcementPoints{n: 3})]);
+        assert_eq!(income.total(), ReinforcementPoints{n: 8});
+        let points = state.reinforcement_points()[&player_id];
+        state.apply_event(&CoreEvent::EndTurn {
+            old_id: player_id,
+            new_id: enemy_id,
+            turn: 1,
+        });
+        assert_eq!(state.reinforcement_points()[&player_id].n, points.n + 8);
+        assert_eq!(state.income(enemy_id).total(), ReinforcementPoints{n: 5});
+    }
+
+    #[test]
+    fn test_road_between_non_adjacent_tiles_is_an_error() {
+        let mut objects = HashMap::new();
+        let path = [
+            MapPos{v: Vector2{x: 0, y: 0}},
+            MapPos{v: Vector2{x: 1, y: 0}},
+            MapPos{v: Vector2{x: 3, y: 0}},
+        ];
+        match add_road(&mut objects, &path) {
+            Err(LoadMapError::RoadBetweenNonAdjacentTiles{from, to}) => {
+                assert_eq!(from, path[1]);
+                assert_eq!(to, path[2]);
+            },
+            result => panic!("Expected a bad road error, got {:?}", result),
+        }
+    }
 }
@@ -19,29 +19,48 @@ pub mod player;
 pub mod object;
 pub mod options;
 pub mod attack;
+pub mod morale;
+pub mod veterancy;
+pub mod fov;
+pub mod fow;
+pub mod victory;
+pub mod trigger;
+pub mod campaign;
+pub mod mapgen;
+pub mod map_file;
+pub mod validator;
+pub mod ascii;
 
 mod ai;
-mod fov;
-mod fow;
 mod filter;
 
-use std::{cmp};
+use std::{cmp, mem};
 use std::collections::{HashMap};
 use std::rc::{Rc};
 use rand::{thread_rng, Rng};
-use game_state::{State};
+use game_state::{State, ReinforcementPoints, LoadMapError};
 use options::{Options};
-use movement::{MovePoints, tile_cost, move_cost_modifier};
-use unit::{Unit, UnitId};
+use movement::{
+    MovePoints,
+    Pathfinder,
+    tile_cost,
+    move_cost_modifier,
+    truncate_path,
+};
+use unit::{Unit, UnitId, UnitTypeId};
 use db::{Db};
 use ai::{Ai};
-use dir::{Dir};
+use dir::{Dir, dirs};
 use attack::{AttackPoints, hit_chance, get_killed_count};
 use sector::{check_sectors};
 use check::{check_attack};
 use player::{Player, PlayerId, PlayerClass, PlayerInfo};
-use object::{ObjectId};
+use object::{ObjectId, ObjectClass};
 use event::{CoreEvent, Command};
+use position::{MapPos, ExactPos, get_free_exact_pos};
+use morale::{MoraleState};
+use trigger::{Trigger, Action};
+use campaign::{CampaignProgress};
 
 #[derive(PartialEq, Clone, Copy, Debug)]
 enum ReactionFireResult {
@@ -84,16 +103,19 @@ pub struct Core {
     players_info: HashMap<PlayerId, PlayerInfo>,
     next_unit_id: UnitId,
     next_object_id: ObjectId,
+
+    /// Scenario triggers that haven't fired yet
+    triggers: Vec<Trigger>,
 }
 
 impl Core {
-    pub fn new(options: &Options) -> Core {
+    pub fn new(options: &Options) -> Result<Core, LoadMapError> {
         let db = Rc::new(Db::new());
-        let state = State::new_full(db.clone(), options);
+        let (state, triggers) = State::new_full_with_triggers(db.clone(), options)?;
         let players_info = get_player_info_list(&state);
-        let ai = Ai::new(db.clone(), options, PlayerId{id:1});
+        let ai = Ai::new(db.clone(), options, PlayerId{id:1})?;
         let next_object_id = ObjectId{id: state.objects().len() as i32};
-        Core {
+        Ok(Core {
             state: state,
             players: get_players_list(options),
             current_player_id: PlayerId{id: 0},
@@ -102,7 +124,8 @@ impl Core {
             players_info: players_info,
             next_unit_id: UnitId{id: 0},
             next_object_id: next_object_id,
-        }
+            triggers: triggers,
+        })
     }
 
     pub fn db(&self) -> &Rc<Db> {
@@ -127,6 +150,10 @@ impl Core {
         self.current_player_id
     }
 
+    pub fn player_info(&self, player_id: PlayerId) -> &PlayerInfo {
+        &self.players_info[&player_id]
+    }
+
     pub fn get_event(&mut self) -> Option<CoreEvent> {
         let mut i = self.players_info.get_mut(&self.current_player_id).unwrap();
         i.get_event()
@@ -139,7 +166,7 @@ impl Core {
         fire_mode: event::FireMode,
     ) -> Option<CoreEvent> {
         let attacker = self.state.unit(attacker_id);
-        let defender = self.state.unit(defender_id);
+        let mut defender = self.state.unit(defender_id);
         let check_attack_result = check_attack(
             &self.db, &self.state, attacker, defender, fire_mode);
         if check_attack_result.is_err() {
@@ -147,11 +174,27 @@ impl Core {
         }
         let attacker_type = self.db.unit_type(attacker.type_id);
         let weapon_type = self.db.weapon_type(attacker_type.weapon_type_id);
-        let hit_chance = hit_chance(&self.db, &self.state, attacker, defender);
+        let mut is_scattered = false;
+        if let Some(pos) = attack::scatter_pos(
+            &self.db, &self.state, attacker, defender)
+        {
+            is_scattered = true;
+            match self.scattered_shell_target(pos) {
+                Some(unit) => defender = unit,
+                None => return Some(CoreEvent::ShellMissed {
+                    attacker_id: Some(attacker_id),
+                    pos: pos,
+                    mode: fire_mode,
+                }),
+            }
+        }
+        let hit_chance = hit_chance(
+            &self.db, &self.state, attacker, defender, fire_mode);
         let suppression = hit_chance.n / 2;
         let killed = cmp::min(
             defender.count,
-            get_killed_count(&self.db, &self.state, attacker, defender),
+            get_killed_count(
+                &self.db, &self.state, attacker, defender, fire_mode),
         );
         let fow = self.players_info[&defender.player_id].fow();
         let is_visible = fow.is_visible(attacker);
@@ -160,23 +203,181 @@ impl Core {
             && thread_rng().gen_range(1, 100) <= ambush_chance;
         let per_death_suppression = 20;
         let defender_type = self.db.unit_type(defender.type_id);
-        // TODO: destroyed helicopters must kill everyone
-        // on the ground in their tile
         let leave_wrecks = !defender_type.is_infantry && !defender_type.is_air;
         let attack_info = event::AttackInfo {
             attacker_id: Some(attacker_id),
-            defender_id: defender_id,
+            defender_id: defender.id,
             killed: killed,
             mode: fire_mode,
             suppression: suppression + per_death_suppression * killed,
             remove_move_points: false,
             is_ambush: is_ambush,
             is_inderect: weapon_type.is_inderect,
+            is_scattered: is_scattered,
             leave_wrecks: leave_wrecks,
+            is_splash: false,
         };
         Some(CoreEvent::AttackUnit{attack_info: attack_info})
     }
 
+    /// Picks a random ground unit in the tile where a scattered shell landed
+    fn scattered_shell_target(&self, pos: MapPos) -> Option<&Unit> {
+        let mut targets: Vec<_> = self.state.units_at(pos)
+            .filter(|unit| {
+                let unit_type = self.db.unit_type(unit.type_id);
+                unit.is_alive
+                    && !unit_type.is_air
+                    && !unit::is_loaded_or_attached(unit)
+            })
+            .collect();
+        thread_rng().shuffle(&mut targets);
+        targets.into_iter().next()
+    }
+
+    /// Secondary results of a blast in `center`: every ground unit
+    /// in the blast radius except the primary target is attacked
+    /// and units around it are suppressed
+    fn splash_attack_infos(
+        &self,
+        attacker_id: UnitId,
+        center: MapPos,
+        mode: event::FireMode,
+        primary_attack_info: Option<&event::AttackInfo>,
+    ) -> Vec<event::AttackInfo> {
+        let mut infos = Vec::new();
+        let attacker = self.state.unit(attacker_id);
+        let attacker_type = self.db.unit_type(attacker.type_id);
+        let weapon_type = self.db.weapon_type(attacker_type.weapon_type_id);
+        let blast_radius = match weapon_type.blast_radius {
+            Some(radius) => radius,
+            None => return infos,
+        };
+        let primary_defender_id = primary_attack_info
+            .map(|attack_info| attack_info.defender_id);
+        let primary_defender_player_id = primary_defender_id
+            .map(|id| self.state.unit(id).player_id);
+        let is_ambush = primary_attack_info
+            .map_or(false, |attack_info| attack_info.is_ambush);
+        for (&id, unit) in self.state.units() {
+            let unit_type = self.db.unit_type(unit.type_id);
+            if Some(id) == primary_defender_id
+                || id == attacker_id
+                || !unit.is_alive
+                || unit_type.is_air
+                || unit::is_loaded_or_attached(unit)
+            {
+                continue;
+            }
+            let distance = map::distance(center, unit.pos.map_pos);
+            if distance.n > blast_radius.n + 1 {
+                continue;
+            }
+            let hit_chance = hit_chance(
+                &self.db, &self.state, attacker, unit, mode);
+            let is_in_blast = distance <= blast_radius;
+            let (killed, suppression) = if is_in_blast {
+                let killed = cmp::min(unit.count, get_killed_count(
+                    &self.db, &self.state, attacker, unit, mode));
+                let per_death_suppression = 20;
+                (killed, hit_chance.n / 2 + per_death_suppression * killed)
+            } else {
+                (0, hit_chance.n / 4)
+            };
+            infos.push(event::AttackInfo {
+                attacker_id: Some(attacker_id),
+                defender_id: id,
+                mode: mode,
+                killed: killed,
+                suppression: suppression,
+                remove_move_points: false,
+                is_ambush: is_ambush
+                    && Some(unit.player_id) == primary_defender_player_id,
+                is_inderect: weapon_type.is_inderect,
+                is_scattered: false,
+                leave_wrecks: !unit_type.is_infantry,
+                is_splash: true,
+            });
+        }
+        infos
+    }
+
+    fn experience_events(&self, attack_info: &event::AttackInfo) -> Vec<CoreEvent> {
+        let mut events = Vec::new();
+        let defender = self.state.unit(attack_info.defender_id);
+        if let Some(attacker_id) = attack_info.attacker_id {
+            let attacker = self.state.unit(attacker_id);
+            let is_friendly_fire = attacker.player_id == defender.player_id;
+            let count = veterancy::attacker_experience(attack_info, defender);
+            if count > 0 && !is_friendly_fire {
+                events.push(CoreEvent::Experience {
+                    unit_id: attacker_id,
+                    count: count,
+                });
+            }
+        }
+        let count = veterancy::defender_experience(attack_info, defender);
+        if count > 0 {
+            events.push(CoreEvent::Experience {
+                unit_id: defender.id,
+                count: count,
+            });
+        }
+        events
+    }
+
+    fn do_attack(&mut self, attack_info: event::AttackInfo) {
+        let splash_attack_infos = {
+            let attacker_id = attack_info.attacker_id
+                .expect("Core must know about everything");
+            let center = self.state.unit(attack_info.defender_id).pos.map_pos;
+            self.splash_attack_infos(
+                attacker_id, center, attack_info.mode, Some(&attack_info))
+        };
+        let mut attack_infos = vec![attack_info];
+        attack_infos.extend(splash_attack_infos);
+        self.do_attack_infos(attack_infos);
+    }
+
+    /// The shell still explodes where it has landed
+    fn do_shell_miss(&mut self, attacker_id: UnitId, pos: MapPos, mode: event::FireMode) {
+        let splash_attack_infos = self.splash_attack_infos(
+            attacker_id, pos, mode, None);
+        self.do_core_event(&CoreEvent::ShellMissed {
+            attacker_id: Some(attacker_id),
+            pos: pos,
+            mode: mode,
+        });
+        self.do_attack_infos(splash_attack_infos);
+    }
+
+    fn do_attack_infos(&mut self, attack_infos: Vec<event::AttackInfo>) {
+        for attack_info in attack_infos {
+            let crash_attack_infos = attack::crash_attack_infos(
+                &self.db, &self.state, &attack_info);
+            let experience_events = self.experience_events(&attack_info);
+            self.do_core_event(&CoreEvent::AttackUnit{attack_info: attack_info});
+            for event in experience_events {
+                self.do_core_event(&event);
+            }
+            for attack_info in crash_attack_infos {
+                self.do_core_event(&CoreEvent::AttackUnit{attack_info: attack_info});
+            }
+        }
+    }
+
+    /// Results of an AttackUnit or a ShellMissed command event
+    fn do_attack_event(&mut self, event: CoreEvent) {
+        match event {
+            CoreEvent::AttackUnit{attack_info} => self.do_attack(attack_info),
+            CoreEvent::ShellMissed{attacker_id, pos, mode} => {
+                let attacker_id = attacker_id
+                    .expect("Core must know about everything");
+                self.do_shell_miss(attacker_id, pos, mode);
+            },
+            _ => unreachable!(),
+        }
+    }
+
     fn can_unit_make_reaction_attack(
         &self,
         defender: &Unit,
@@ -209,8 +410,11 @@ impl Core {
         let unit_ids: Vec<_> = self.state.units().map(|(&id, _)| id).collect();
         let mut result = ReactionFireResult::None;
         for enemy_unit_id in unit_ids {
-            if unit::is_loaded_or_attached(self.state.unit(enemy_unit_id)) {
-                continue;
+            match self.state.unit_opt(enemy_unit_id) {
+                // killed by a blast or a crash
+                None => continue,
+                Some(unit) if unit::is_loaded_or_attached(unit) => continue,
+                Some(_) => {},
             }
             let event = {
                 let enemy_unit = self.state.unit(enemy_unit_id);
@@ -223,19 +427,26 @@ impl Core {
                 }
                 let event = self.command_attack_unit_to_event(
                     enemy_unit.id, unit_id, event::FireMode::Reactive);
-                if let Some(CoreEvent::AttackUnit{mut attack_info}) = event {
-                    let hit_chance = attack::hit_chance(
-                        &self.db, &self.state, enemy_unit, unit);
-                    let unit_type = self.db.unit_type(unit.type_id);
-                    if hit_chance.n > 15 && !unit_type.is_air && stop_on_attack {
-                        attack_info.remove_move_points = true;
-                    }
-                    CoreEvent::AttackUnit{attack_info: attack_info}
-                } else {
-                    continue;
+                match event {
+                    Some(CoreEvent::AttackUnit{mut attack_info}) => {
+                        let hit_chance = attack::hit_chance(
+                            &self.db,
+                            &self.state,
+                            enemy_unit,
+                            unit,
+                            event::FireMode::Reactive,
+                        );
+                        let unit_type = self.db.unit_type(unit.type_id);
+                        if hit_chance.n > 15 && !unit_type.is_air && stop_on_attack {
+                            attack_info.remove_move_points = true;
+                        }
+                        CoreEvent::AttackUnit{attack_info: attack_info}
+                    },
+                    Some(event) => event,
+                    None => continue,
                 }
             };
-            self.do_core_event(&event);
+            self.do_attack_event(event);
             result = ReactionFireResult::Attacked;
             if self.state.unit_opt(unit_id).is_none() {
                 return ReactionFireResult::Killed;
@@ -244,10 +455,271 @@ impl Core {
         result
     }
 
+    fn build(&mut self, unit_id: UnitId, pos: MapPos, class: ObjectClass) {
+        let unfinished_object_id = self.state.object_ids_at(pos).iter()
+            .cloned()
+            .find(|id| {
+                let object = &self.state.objects()[id];
+                object.class == class && !object.is_finished()
+            });
+        let id = match unfinished_object_id {
+            Some(id) => id,
+            None => self.get_new_object_id(),
+        };
+        self.do_core_event(&CoreEvent::Build {
+            id: id,
+            pos: pos,
+            class: class,
+            unit_id: Some(unit_id),
+        });
+        self.reaction_fire(unit_id);
+    }
+
+    /// Enemy minefields blow up under ground units that enter them
+    fn trigger_mines(&mut self, unit_id: UnitId) -> bool {
+        let minefield_id = {
+            let unit = self.state.unit(unit_id);
+            let unit_type = self.db.unit_type(unit.type_id);
+            if unit_type.is_air {
+                return false;
+            }
+            self.state.object_ids_at(unit.pos.map_pos).iter()
+                .cloned()
+                .find(|id| self.state.objects()[id].is_hidden_from(unit.player_id))
+        };
+        let minefield_id = match minefield_id {
+            Some(id) => id,
+            None => return false,
+        };
+        let object = self.state.objects()[&minefield_id].clone();
+        self.do_core_event(&CoreEvent::ShowObject {
+            id: minefield_id,
+            object: object,
+        });
+        let attack_info = attack::mine_attack_info(
+            &self.db, self.state.unit(unit_id));
+        let experience_events = self.experience_events(&attack_info);
+        self.do_core_event(&CoreEvent::AttackUnit{attack_info: attack_info});
+        for event in experience_events {
+            self.do_core_event(&event);
+        }
+        true
+    }
+
+    /// Own deployment zone during the deployment, otherwise
+    /// own reinforcement sectors and then the tiles around them
+    fn core_forces_positions(&self, player_id: PlayerId) -> Vec<MapPos> {
+        if self.state.is_deployment_phase() {
+            return self.state.deployment_zone(player_id).to_vec();
+        }
+        let mut positions: Vec<_> = self.state.objects().values()
+            .filter(|object| {
+                object.class == ObjectClass::ReinforcementSector
+                    && object.owner_id == Some(player_id)
+            })
+            .map(|object| object.pos.map_pos)
+            .collect();
+        positions.sort_by_key(|pos| (pos.v.y, pos.v.x));
+        for sector_pos in positions.clone() {
+            for dir in dirs() {
+                let pos = Dir::get_neighbour_pos(sector_pos, dir);
+                if self.state.map().is_inboard(pos) && !positions.contains(&pos) {
+                    positions.push(pos);
+                }
+            }
+        }
+        positions
+    }
+
+    /// Brings units and points that the player has kept
+    /// from the previous missions of the campaign.
+    /// Units that don't fit near the reinforcement sectors
+    /// stay in the progress's reserve.
+    pub fn add_core_forces(&mut self, player_id: PlayerId, progress: &mut CampaignProgress) {
+        if progress.reinforcement_points.n > 0 {
+            self.do_core_event(&CoreEvent::BonusReinforcementPoints {
+                player_id: player_id,
+                count: progress.reinforcement_points.n,
+            });
+        }
+        let positions = self.core_forces_positions(player_id);
+        progress.reserve_units.clear();
+        for core_unit in &progress.core_units {
+            let type_id = self.db.unit_type_id(&core_unit.type_name);
+            let pos = {
+                let unit_type = self.db.unit_type(type_id);
+                positions.iter()
+                    .filter_map(|&pos| get_free_exact_pos(&self.state, unit_type, pos))
+                    .next()
+            };
+            let pos = match pos {
+                Some(pos) => pos,
+                None => {
+                    progress.reserve_units.push(core_unit.clone());
+                    continue;
+                },
+            };
+            let unit = self.new_unit(player_id, type_id, pos, true);
+            self.do_core_event(&CoreEvent::CreateUnit {
+                unit_info: Unit {
+                    count: core_unit.count,
+                    morale: core_unit.morale,
+                    experience: core_unit.experience,
+                    .. unit
+                },
+                cost: ReinforcementPoints{n: 0},
+            });
+        }
+    }
+
+    /// Fresh units have no points until their first turn
+    /// unless they are ready to fight right away
+    fn new_unit(
+        &mut self,
+        player_id: PlayerId,
+        type_id: UnitTypeId,
+        pos: ExactPos,
+        is_ready: bool,
+    ) -> Unit {
+        let id = self.get_new_unit_id();
+        let mut unit = unit::new_unit(&self.db, id, player_id, type_id, pos);
+        if !is_ready {
+            unit.move_points = Some(MovePoints{n: 0});
+            unit.attack_points = Some(AttackPoints{n: 0});
+            unit.reactive_attack_points = Some(AttackPoints{n: 0});
+        }
+        unit
+    }
+
+    /// Fires (only once) all the scenario triggers whose conditions are met
+    fn check_triggers(&mut self) {
+        if self.state.game_result().is_some() || self.state.is_deployment_phase() {
+            return;
+        }
+        let triggers = mem::replace(&mut self.triggers, Vec::new());
+        let (fired_triggers, triggers): (Vec<_>, Vec<_>) = triggers.into_iter()
+            .partition(|trigger| trigger::is_triggered(&self.state, &trigger.condition));
+        self.triggers = triggers;
+        for trigger in fired_triggers {
+            for action in trigger.actions {
+                self.do_trigger_action(action);
+            }
+        }
+    }
+
+    fn do_trigger_action(&mut self, action: Action) {
+        match action {
+            Action::SpawnUnits{player_id, units} => {
+                for (type_name, pos) in units {
+                    let type_id = self.db.unit_type_id(&type_name);
+                    let pos = {
+                        let unit_type = self.db.unit_type(type_id);
+                        match get_free_exact_pos(&self.state, unit_type, pos) {
+                            Some(pos) => pos,
+                            None => continue,
+                        }
+                    };
+                    let unit = self.new_unit(player_id, type_id, pos, true);
+                    self.do_core_event(&CoreEvent::CreateUnit {
+                        unit_info: unit,
+                        cost: ReinforcementPoints{n: 0},
+                    });
+                }
+            },
+            Action::ShowMessage{player_id, text} => {
+                self.do_core_event(&CoreEvent::Message {
+                    player_id: player_id,
+                    text: text,
+                });
+            },
+            Action::SetSectorIncome{sector_id, income} => {
+                self.do_core_event(&CoreEvent::SectorIncomeChanged {
+                    sector_id: sector_id,
+                    income: income,
+                });
+            },
+            Action::RevealArea{player_id, area} => {
+                self.do_core_event(&CoreEvent::RevealArea {
+                    player_id: player_id,
+                    area: area,
+                });
+            },
+        }
+    }
+
     fn reaction_fire(&mut self, unit_id: UnitId) {
         self.reaction_fire_internal(unit_id, false);
     }
 
+    /// Finds a path that leads the unit to the nearest friendly sector
+    fn fall_back_path(&self, unit: &Unit) -> Option<Vec<ExactPos>> {
+        let mut destinations = Vec::new();
+        for sector in self.state.sectors().values() {
+            if sector.owner_id == Some(unit.player_id) {
+                destinations.extend_from_slice(&sector.positions);
+            }
+        }
+        for object in self.state.objects().values() {
+            if object.class == ObjectClass::ReinforcementSector
+                && object.owner_id == Some(unit.player_id)
+            {
+                destinations.push(object.pos.map_pos);
+            }
+        }
+        if destinations.contains(&unit.pos.map_pos) {
+            return None;
+        }
+        let map_size = self.state.map().size();
+        let mut pathfinder = Pathfinder::new(self.db.clone(), map_size);
+        pathfinder.fill_map(&self.state, unit);
+        let unit_type = self.db.unit_type(unit.type_id);
+        let mut best_path = None;
+        let mut best_cost = movement::max_cost();
+        for pos in destinations {
+            let exact_pos = match get_free_exact_pos(&self.state, unit_type, pos) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let cost = pathfinder.get_map().tile(exact_pos).cost();
+            if cost >= best_cost {
+                continue;
+            }
+            if let Some(path) = pathfinder.get_path(exact_pos) {
+                best_cost = cost;
+                best_path = Some(path);
+            }
+        }
+        best_path.and_then(|path| {
+            truncate_path(&self.db, &self.state, &path, unit)
+        })
+    }
+
+    /// Routed units ignore orders and run away to friendly sectors
+    fn fall_back_routed_units(&mut self) {
+        let player_id = self.current_player_id;
+        let unit_ids: Vec<_> = self.state.units()
+            .filter(|&(_, unit)| {
+                unit::is_commandable(player_id, unit)
+                    && morale::unit_morale_state(unit) == MoraleState::Routed
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        for unit_id in unit_ids {
+            // the unit may be already killed by reaction fire
+            let path = match self.state.unit_opt(unit_id) {
+                Some(unit) => self.fall_back_path(unit),
+                None => continue,
+            };
+            if let Some(path) = path {
+                self.simulation_step(Command::Move {
+                    unit_id: unit_id,
+                    path: path,
+                    mode: event::MoveMode::Fast,
+                });
+            }
+        }
+    }
+
     pub fn next_player_id(&self, id: PlayerId) -> PlayerId {
         let old_id = id.id;
         let max_id = self.players.len() as i32;
@@ -298,36 +770,23 @@ impl Core {
                 for event in end_turn_events {
                     self.do_core_event(&event);
                 }
+                let turn = self.state.next_turn(new_id);
                 self.do_core_event(&CoreEvent::EndTurn {
                     old_id: old_id,
                     new_id: new_id,
+                    turn: turn,
                 });
             },
             Command::CreateUnit{pos, type_id} => {
-                let event = {
-                    let id = self.get_new_unit_id();
-                    let unit_type = self.db.unit_type(type_id);
-                    CoreEvent::CreateUnit {
-                        unit_info: Unit {
-                            id: id,
-                            player_id: self.current_player_id,
-                            pos: pos,
-                            type_id: type_id,
-                            passenger_id: None,
-                            attached_unit_id: None,
-                            move_points: Some(MovePoints{n: 0}),
-                            attack_points: Some(AttackPoints{n: 0}),
-                            reactive_attack_points: Some(AttackPoints{n: 0}),
-                            reaction_fire_mode: event::ReactionFireMode::Normal,
-                            count: unit_type.count,
-                            morale: 100,
-                            is_alive: true,
-                            is_loaded: false,
-                            is_attached: false,
-                        },
-                    }
-                };
-                self.do_core_event(&event);
+                let player_id = self.current_player_id;
+                // deployed units are ready for the first turn
+                let is_ready = self.state.is_deployment_phase();
+                let unit = self.new_unit(player_id, type_id, pos, is_ready);
+                let cost = self.db.unit_type(type_id).cost;
+                self.do_core_event(&CoreEvent::CreateUnit {
+                    unit_info: unit,
+                    cost: cost,
+                });
             },
             Command::Move{unit_id, path, mode} => {
                 let player_id = self.state.unit(unit_id).player_id;
@@ -360,6 +819,12 @@ impl Core {
                     let pre_visible_enemies = self.players_info[&player_id]
                         .visible_enemies().clone();
                     self.do_core_event(&move_event);
+                    if self.trigger_mines(unit_id) {
+                        if self.state.unit_opt(unit_id).is_some() {
+                            self.reaction_fire(unit_id);
+                        }
+                        break;
+                    }
                     let reaction_fire_result = self.reaction_fire_internal(
                         unit_id, mode == event::MoveMode::Fast);
                     if reaction_fire_result != ReactionFireResult::None {
@@ -372,11 +837,14 @@ impl Core {
                 }
             },
             Command::AttackUnit{attacker_id, defender_id} => {
-                if let Some(ref event) = self.command_attack_unit_to_event(
+                if let Some(event) = self.command_attack_unit_to_event(
                     attacker_id, defender_id, event::FireMode::Active)
                 {
-                    self.do_core_event(event);
-                    self.reaction_fire(attacker_id);
+                    self.do_attack_event(event);
+                    // attacker may be crushed by a helicopter it shot down
+                    if self.state.unit_opt(attacker_id).is_some() {
+                        self.reaction_fire(attacker_id);
+                    }
                 }
             },
             Command::LoadUnit{transporter_id, passenger_id} => {
@@ -393,6 +861,8 @@ impl Core {
                 let event = {
                     let passenger = self.state.unit(passenger_id);
                     let from = self.state.unit(transporter_id).pos;
+                    let cost = tile_cost(
+                        &self.db, &self.state, passenger, from, pos);
                     CoreEvent::UnloadUnit {
                         transporter_id: Some(transporter_id),
                         unit_info: Unit {
@@ -401,6 +871,7 @@ impl Core {
                         },
                         from: from,
                         to: pos,
+                        cost: cost,
                     }
                 };
                 self.do_core_event(&event);
@@ -418,12 +889,23 @@ impl Core {
                 self.reaction_fire(transporter_id);
             },
             Command::Detach{transporter_id, pos} => {
-                let from = self.state.unit(transporter_id).pos;
-                self.do_core_event(&CoreEvent::Detach {
-                    transporter_id: transporter_id,
-                    from: from,
-                    to: pos,
-                });
+                let event = {
+                    let transporter = self.state.unit(transporter_id);
+                    let from = transporter.pos;
+                    let detached_transporter = Unit {
+                        attached_unit_id: None,
+                        .. transporter.clone()
+                    };
+                    let cost = tile_cost(
+                        &self.db, &self.state, &detached_transporter, from, pos);
+                    CoreEvent::Detach {
+                        transporter_id: transporter_id,
+                        from: from,
+                        to: pos,
+                        cost: cost,
+                    }
+                };
+                self.do_core_event(&event);
                 self.reaction_fire(transporter_id);
             },
             Command::SetReactionFireMode{unit_id, mode} => {
@@ -462,11 +944,51 @@ impl Core {
                 }
                 self.reaction_fire(unit_id);
             },
+            Command::BuildBridge{unit_id, pos} => {
+                self.build(unit_id, pos, ObjectClass::Bridge);
+            },
+            Command::DigTrench{unit_id, pos} => {
+                self.build(unit_id, pos, ObjectClass::Trench);
+            },
+            Command::LayMines{unit_id, pos} => {
+                self.build(unit_id, pos, ObjectClass::Minefield);
+            },
+            Command::Clear{unit_id, pos} => {
+                let wreck_ids = self.state.units_at(pos)
+                    .filter(|unit| !unit.is_alive)
+                    .map(|unit| unit.id)
+                    .collect();
+                let object_ids = {
+                    let player_id = self.state.unit(unit_id).player_id;
+                    let fow = self.players_info[&player_id].fow();
+                    self.state.object_ids_at(pos).iter()
+                        .cloned()
+                        .filter(|&id| {
+                            let object = &self.state.objects()[&id];
+                            let is_clearable = object.class == ObjectClass::Trench
+                                || object.class == ObjectClass::Minefield;
+                            // engineers can't clear mines they don't know about
+                            is_clearable && fow.is_object_visible(id, object)
+                        })
+                        .collect()
+                };
+                self.do_core_event(&CoreEvent::Clear {
+                    pos: pos,
+                    unit_id: Some(unit_id),
+                    wreck_ids: wreck_ids,
+                    object_ids: object_ids,
+                });
+                self.reaction_fire(unit_id);
+            },
         };
-        let sector_events = check_sectors(&self.db, &self.state);
-        for event in sector_events {
-            self.do_core_event(&event);
+        // sectors can't be captured by hidden deployed forces
+        if !self.state.is_deployment_phase() {
+            let sector_events = check_sectors(&self.db, &self.state);
+            for event in sector_events {
+                self.do_core_event(&event);
+            }
         }
+        self.check_triggers();
     }
 
     pub fn do_command(&mut self, command: Command) {
@@ -481,7 +1003,7 @@ impl Core {
             }
             let command = self.ai.get_command();
             self.do_command(command.clone());
-            if command == Command::EndTurn {
+            if command == Command::EndTurn || self.state.game_result().is_some() {
                 return;
             }
         }
@@ -496,6 +1018,14 @@ impl Core {
                 break;
             }
         }
+        if self.state.game_result().is_some() {
+            return;
+        }
+        if new_id == self.player_id() {
+            self.fall_back_routed_units();
+        }
+        // turn triggers must fire before the AI starts its turn
+        self.check_triggers();
         if self.player().class == PlayerClass::Ai
             && new_id == self.player_id()
         {
@@ -515,8 +1045,355 @@ impl Core {
         for player_id in player_ids {
             self.filter_event(player_id, event);
         }
-        if let CoreEvent::EndTurn{old_id, new_id} = *event {
+        if let Some(event) = victory::check_objectives(&self.state, event) {
+            self.do_core_event(&event);
+        }
+        if let CoreEvent::EndTurn{old_id, new_id, ..} = *event {
             self.handle_end_turn_event(old_id, new_id);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use player::{PlayerId};
+    use unit::{UnitId, new_unit};
+    use position::{MapPos, ExactPos, SlotId};
+    use event::{CoreEvent, Command, AttackInfo, FireMode, MoveMode};
+    use morale::{self, MoraleState};
+    use object::{ObjectClass};
+    use check::{CommandError, check_command};
+    use campaign::{CampaignProgress, CoreUnit};
+    use map;
+    use super::{Core};
+
+    fn new_core() -> Core {
+        Core::new(&Options {
+            game_type: GameType::Hotseat,
+            map_name: "map01".to_owned(),
+            players_count: 2,
+        }).unwrap()
+    }
+
+    fn create_unit(
+        core: &mut Core,
+        type_name: &str,
+        player_id: i32,
+        x: i32,
+        y: i32,
+        slot: u8,
+    ) -> UnitId {
+        let id = core.get_new_unit_id();
+        let type_id = core.db.unit_type_id(type_name);
+        let pos = ExactPos {
+            map_pos: MapPos{v: Vector2{x: x, y: y}},
+            slot_id: SlotId::Id(slot),
+        };
+        let unit = new_unit(&core.db, id, PlayerId{id: player_id}, type_id, pos);
+        let cost = core.db.unit_type(type_id).cost;
+        core.do_core_event(&CoreEvent::CreateUnit {
+            unit_info: unit,
+            cost: cost,
+        });
+        id
+    }
+
+    fn attack(
+        core: &mut Core,
+        attacker_id: UnitId,
+        defender_id: UnitId,
+        killed: i32,
+        suppression: i32,
+    ) {
+        core.do_core_event(&CoreEvent::AttackUnit {
+            attack_info: AttackInfo {
+                attacker_id: Some(attacker_id),
+                defender_id: defender_id,
+                mode: FireMode::Active,
+                killed: killed,
+                suppression: suppression,
+                remove_move_points: false,
+                is_ambush: false,
+                is_inderect: false,
+                is_scattered: false,
+                leave_wrecks: false,
+                is_splash: false,
+            },
+        });
+    }
+
+    fn end_turns(core: &mut Core, count: i32) {
+        for _ in 0..count {
+            core.do_command(Command::EndTurn);
+        }
+    }
+
+    #[test]
+    fn test_pinned_unit_loses_move_points() {
+        let mut core = new_core();
+        let unit_id = create_unit(&mut core, "soldier", 0, 2, 1, 0);
+        let enemy_id = create_unit(&mut core, "soldier", 1, 9, 11, 0);
+        attack(&mut core, enemy_id, unit_id, 0, 70);
+        assert_eq!(morale::unit_morale_state(core.state.unit(unit_id)), MoraleState::Pinned);
+        assert_eq!(core.state.unit(unit_id).move_points.unwrap().n, 0);
+        // still pinned after recovery so the new turn gives no move points
+        end_turns(&mut core, 2);
+        let unit = core.state.unit(unit_id);
+        assert_eq!(morale::unit_morale_state(unit), MoraleState::Pinned);
+        assert_eq!(unit.move_points.unwrap().n, 0);
+        assert!(unit.attack_points.unwrap().n > 0);
+    }
+
+    #[test]
+    fn test_routed_unit_falls_back() {
+        let mut core = new_core();
+        let unit_id = create_unit(&mut core, "soldier", 0, 2, 1, 0);
+        let enemy_id = create_unit(&mut core, "soldier", 1, 9, 11, 0);
+        end_turns(&mut core, 1);
+        attack(&mut core, enemy_id, unit_id, 0, 150);
+        assert_eq!(core.state.unit(unit_id).morale, 0);
+        end_turns(&mut core, 1);
+        // the nearest own reinforcement sector
+        assert_eq!(core.state.unit(unit_id).pos.map_pos, MapPos{v: Vector2{x: 0, y: 1}});
+        assert_eq!(core.state.unit(unit_id).morale, 0);
+        // routed units rally at the end of their turn
+        end_turns(&mut core, 1);
+        let unit = core.state.unit(unit_id);
+        assert_eq!(unit.morale, 10);
+        assert_eq!(morale::unit_morale_state(unit), MoraleState::Retreating);
+    }
+
+    #[test]
+    fn test_nearby_deaths_lower_morale() {
+        let mut core = new_core();
+        let defender_id = create_unit(&mut core, "soldier", 0, 2, 1, 0);
+        let neighbour_id = create_unit(&mut core, "soldier", 0, 3, 1, 0);
+        let distant_id = create_unit(&mut core, "soldier", 0, 2, 5, 0);
+        let enemy_id = create_unit(&mut core, "soldier", 1, 2, 2, 0);
+        let enemy2_id = create_unit(&mut core, "soldier", 1, 2, 2, 1);
+        attack(&mut core, enemy_id, defender_id, 2, 0);
+        assert_eq!(core.state.unit(neighbour_id).morale, 100 - 2 * 5);
+        assert_eq!(core.state.unit(distant_id).morale, 100);
+        assert_eq!(core.state.unit(enemy_id).morale, 100);
+        // the penalty is clamped too
+        attack(&mut core, enemy_id, neighbour_id, 0, 95);
+        attack(&mut core, enemy2_id, defender_id, 1, 0);
+        assert_eq!(core.state.unit(neighbour_id).morale, 0);
+    }
+
+    fn blast_attack_info(core: &Core, attacker_id: UnitId, defender_id: UnitId) -> AttackInfo {
+        AttackInfo {
+            attacker_id: Some(attacker_id),
+            defender_id: defender_id,
+            mode: FireMode::Active,
+            killed: 0,
+            suppression: 10,
+            remove_move_points: false,
+            is_ambush: false,
+            is_inderect: core.db.weapon_type(
+                core.db.unit_type(core.state.unit(attacker_id).type_id)
+                    .weapon_type_id).is_inderect,
+            is_scattered: false,
+            leave_wrecks: false,
+            is_splash: false,
+        }
+    }
+
+    fn player_events(core: &mut Core, player_id: i32) -> Vec<CoreEvent> {
+        let info = core.players_info.get_mut(&PlayerId{id: player_id}).unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = info.get_event() {
+            events.push(event);
+        }
+        events
+    }
+
+    fn attacked_unit_ids(events: &[CoreEvent]) -> Vec<UnitId> {
+        events.iter().filter_map(|event| match *event {
+            CoreEvent::AttackUnit{ref attack_info} => Some(attack_info.defender_id),
+            _ => None,
+        }).collect()
+    }
+
+    #[test]
+    fn test_blast_attacks_every_occupant() {
+        let mut core = new_core();
+        let mortar_id = create_unit(&mut core, "mortar", 0, 0, 1, 0);
+        let target_ids = [
+            create_unit(&mut core, "soldier", 1, 2, 3, 0),
+            create_unit(&mut core, "soldier", 1, 2, 3, 1),
+            create_unit(&mut core, "soldier", 1, 2, 3, 2),
+        ];
+        player_events(&mut core, 1);
+        let attack_info = blast_attack_info(&core, mortar_id, target_ids[0]);
+        core.do_attack(attack_info);
+        let attacked_ids = attacked_unit_ids(&player_events(&mut core, 1));
+        for &id in &target_ids {
+            assert_eq!(attacked_ids.iter().filter(|&&i| i == id).count(), 1);
+        }
+        assert_eq!(attacked_ids.len(), target_ids.len());
+    }
+
+    #[test]
+    fn test_splash_hits_on_fogged_units_are_hidden() {
+        let mut core = new_core();
+        let mortar_id = create_unit(&mut core, "mortar", 0, 0, 4, 0);
+        let target_id = create_unit(&mut core, "soldier", 1, 3, 4, 0);
+        let hidden_id = create_unit(&mut core, "soldier", 1, 4, 4, 0);
+        {
+            let fow = core.players_info[&PlayerId{id: 0}].fow();
+            assert!(fow.is_visible(core.state.unit(target_id)));
+            assert!(!fow.is_visible(core.state.unit(hidden_id)));
+        }
+        player_events(&mut core, 0);
+        let attack_info = blast_attack_info(&core, mortar_id, target_id);
+        core.do_attack(attack_info);
+        let events = player_events(&mut core, 0);
+        assert_eq!(attacked_unit_ids(&events), vec![target_id]);
+        assert!(!events.iter().any(|event| match *event {
+            CoreEvent::ShowUnit{ref unit_info} => unit_info.id == hidden_id,
+            _ => false,
+        }));
+        // the owner knows what has happened to its units
+        let attacked_ids = attacked_unit_ids(&player_events(&mut core, 1));
+        assert!(attacked_ids.contains(&hidden_id));
+    }
+
+    fn exact_pos(x: i32, y: i32) -> ExactPos {
+        ExactPos {
+            map_pos: MapPos{v: Vector2{x: x, y: y}},
+            slot_id: SlotId::Id(0),
+        }
+    }
+
+    fn is_minefield_at(state: &State, pos: ExactPos) -> bool {
+        state.objects_at(pos.map_pos)
+            .any(|object| object.class == ObjectClass::Minefield)
+    }
+
+    #[test]
+    fn test_unit_stops_in_enemy_minefield() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map06".to_owned(),
+            players_count: 2,
+        };
+        let mut core = Core::new(&options).unwrap();
+        let mut state = State::new_partial(
+            Rc::new(Db::new()), &options, PlayerId{id: 0}).unwrap();
+        let minefield_pos = exact_pos(6, 5);
+        assert!(!is_minefield_at(&state, minefield_pos));
+        let id = create_unit(&mut core, "soldier", 0, 5, 5, 0);
+        core.do_command(Command::Move {
+            unit_id: id,
+            path: vec![exact_pos(5, 5), minefield_pos, exact_pos(7, 5)],
+            mode: MoveMode::Fast,
+        });
+        let unit = core.state.unit(id).clone();
+        assert_eq!(unit.pos, minefield_pos);
+        assert_eq!(unit.move_points.unwrap().n, 0);
+        while let Some(event) = core.get_event() {
+            state.apply_event(&event);
+        }
+        assert!(is_minefield_at(&state, minefield_pos));
+    }
+
+    #[test]
+    fn test_reinforcements_arrive_on_scenario_turn() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map06".to_owned(),
+            players_count: 2,
+        };
+        let mut core = Core::new(&options).unwrap();
+        let enemy_id = PlayerId{id: 1};
+        let enemy_units_count = |core: &Core| {
+            core.state.units()
+                .filter(|&(_, unit)| unit.player_id == enemy_id)
+                .count()
+        };
+        while core.state.turn() < 4 {
+            assert_eq!(enemy_units_count(&core), 0);
+            core.do_command(Command::EndTurn);
+        }
+        assert_eq!(enemy_units_count(&core), 2);
+        let mut is_message_shown = false;
+        while let Some(event) = core.get_event() {
+            if let CoreEvent::Message{..} = event {
+                is_message_shown = true;
+            }
+        }
+        assert!(is_message_shown);
+        // triggers fire only once
+        core.do_command(Command::EndTurn);
+        core.do_command(Command::EndTurn);
+        assert_eq!(enemy_units_count(&core), 2);
+    }
+
+    #[test]
+    fn test_core_units_that_do_not_fit_stay_in_reserve() {
+        let options = Options {
+            game_type: GameType::SingleVsAi,
+            map_name: "map05".to_owned(),
+            players_count: 2,
+        };
+        let mut core = Core::new(&options).unwrap();
+        let mut progress = CampaignProgress::new();
+        for _ in 0..10 {
+            progress.core_units.push(CoreUnit {
+                type_name: "soldier".to_owned(),
+                count: 4,
+                morale: 100,
+                experience: 0,
+            });
+        }
+        core.add_core_forces(PlayerId{id: 0}, &mut progress);
+        let sector_pos = MapPos{v: Vector2{x: 0, y: 0}};
+        let units: Vec<_> = core.state.units().map(|(_, unit)| unit.clone()).collect();
+        assert!(!units.is_empty());
+        for unit in &units {
+            assert!(map::distance(unit.pos.map_pos, sector_pos).n <= 1);
+        }
+        assert_eq!(units.len() + progress.reserve_units.len(), 10);
+        assert!(!progress.reserve_units.is_empty());
+        progress.complete_mission(&core.state, PlayerId{id: 0});
+        assert_eq!(progress.core_units.len(), 10);
+        assert!(progress.reserve_units.is_empty());
+    }
+
+    #[test]
+    fn test_deployment_is_hidden_from_enemy() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map07".to_owned(),
+            players_count: 2,
+        };
+        let mut core = Core::new(&options).unwrap();
+        let type_id = core.db().unit_type_id("soldier");
+        {
+            let state = State::new_partial(
+                core.db().clone(), &options, PlayerId{id: 0}).unwrap();
+            let command = Command::CreateUnit{pos: exact_pos(4, 5), type_id: type_id};
+            assert_eq!(
+                check_command(core.db(), PlayerId{id: 0}, &state, &command),
+                Err(CommandError::NotInDeploymentZone),
+            );
+        }
+        core.do_command(Command::CreateUnit{pos: exact_pos(1, 5), type_id: type_id});
+        core.do_command(Command::EndTurn);
+        core.do_command(Command::CreateUnit{pos: exact_pos(8, 5), type_id: type_id});
+        while let Some(event) = core.get_event() {
+            if let CoreEvent::CreateUnit{unit_info, ..} = event {
+                assert_eq!(unit_info.player_id, PlayerId{id: 1});
+            }
+        }
+        assert!(core.state.is_deployment_phase());
+        core.do_command(Command::EndTurn);
+        assert_eq!(core.state.turn(), 1);
+    }
+}
@@ -0,0 +1,523 @@
+use std::{fs, io};
+use std::io::{Read, Write};
+use std::path::{Path};
+use std::collections::{HashMap};
+use std::str::{FromStr};
+use cgmath::{Vector2};
+use types::{Size2};
+use map::{self, Map, Terrain};
+use dir::{Dir};
+use game_state::{State, Score, ReinforcementPoints};
+use object::{ObjectId, Object, ObjectClass};
+use position::{self, MapPos, ExactPos, SlotId};
+use sector::{Sector, SectorId};
+use player::{PlayerId};
+
+/// Map names with this suffix are loaded from map files
+pub const FILE_EXTENSION: &'static str = ".map";
+
+/// Where the editor saves its work
+pub const EDITOR_FILE_NAME: &'static str = "custom.map";
+
+// TODO: scenario files with start points, objectives, triggers and so on
+
+/// Map and everything on it that can be edited and saved to a text file.
+/// Rules of the game are not a part of it so maps from files
+/// are played by the classic rules.
+#[derive(Clone, Debug)]
+pub struct MapFile {
+    pub map: Map<Terrain>,
+    pub objects: HashMap<ObjectId, Object>,
+    pub sectors: HashMap<SectorId, Sector>,
+    pub target_score: Score,
+}
+
+fn new_object(class: ObjectClass, pos: ExactPos, owner_id: Option<PlayerId>) -> Object {
+    Object {
+        class: class,
+        pos: pos,
+        timer: None,
+        owner_id: owner_id,
+        build_turns_left: 0,
+    }
+}
+
+/// Smoke and trenches are left by units during a game
+/// and unfinished constructions are not a part of the map either
+fn is_map_object(object: &Object) -> bool {
+    match object.class {
+        ObjectClass::Smoke | ObjectClass::Trench => false,
+        _ => object.is_finished(),
+    }
+}
+
+fn terrain_to_char(terrain: Terrain) -> char {
+    match terrain {
+        Terrain::Plain => '.',
+        Terrain::Trees => 't',
+        Terrain::City => 'c',
+        Terrain::Water => '~',
+    }
+}
+
+fn char_to_terrain(c: char) -> Option<Terrain> {
+    match c {
+        '.' => Some(Terrain::Plain),
+        't' => Some(Terrain::Trees),
+        'c' => Some(Terrain::City),
+        '~' => Some(Terrain::Water),
+        _ => None,
+    }
+}
+
+fn parse<T: FromStr>(word: &str) -> Option<T> {
+    word.parse().ok()
+}
+
+fn parse_pos(map: &Map<Terrain>, x: &str, y: &str) -> Option<MapPos> {
+    let pos = MapPos{v: Vector2{x: parse(x)?, y: parse(y)?}};
+    if map.is_inboard(pos) {
+        Some(pos)
+    } else {
+        None
+    }
+}
+
+fn parse_player_id(word: &str) -> Option<PlayerId> {
+    let id = parse(word)?;
+    if id == 0 || id == 1 {
+        Some(PlayerId{id: id})
+    } else {
+        None
+    }
+}
+
+impl MapFile {
+    pub fn new(size: Size2) -> MapFile {
+        MapFile {
+            map: Map::new(size),
+            objects: HashMap::new(),
+            sectors: HashMap::new(),
+            target_score: Score{n: 5},
+        }
+    }
+
+    /// Takes the map of a loaded game. Units are not a part of it.
+    pub fn from_state(state: &State) -> MapFile {
+        let mut sectors = state.sectors().clone();
+        for sector in sectors.values_mut() {
+            sector.owner_id = None;
+        }
+        let objects = state.objects().iter()
+            .filter(|&(_, object)| is_map_object(object))
+            .map(|(&id, object)| (id, object.clone()))
+            .collect();
+        MapFile {
+            map: state.map().clone(),
+            objects: objects,
+            sectors: sectors,
+            target_score: state.target_score(),
+        }
+    }
+
+    fn add_object(&mut self, object: Object) {
+        let id = self.objects.keys().map(|id| id.id).max().unwrap_or(0) + 1;
+        self.objects.insert(ObjectId{id: id}, object);
+    }
+
+    fn object_ids_at(&self, pos: MapPos, class: ObjectClass) -> Vec<ObjectId> {
+        self.objects.iter()
+            .filter(|&(_, object)| {
+                object.class == class
+                    && object.pos.map_pos_iter().any(|p| p == pos)
+            })
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    fn remove_buildings(&mut self, pos: MapPos) {
+        for id in self.object_ids_at(pos, ObjectClass::Building) {
+            self.objects.remove(&id);
+        }
+        if *self.map.tile(pos) == Terrain::City {
+            *self.map.tile_mut(pos) = Terrain::Plain;
+        }
+    }
+
+    /// City tiles are created by adding buildings
+    pub fn set_terrain(&mut self, pos: MapPos, terrain: Terrain) {
+        assert!(terrain != Terrain::City);
+        self.remove_buildings(pos);
+        *self.map.tile_mut(pos) = terrain;
+    }
+
+    /// Takes the first free small slot of the tile
+    pub fn add_building(&mut self, pos: MapPos) -> bool {
+        if *self.map.tile(pos) == Terrain::Water {
+            return false;
+        }
+        let mut is_slot_taken = [false; 3];
+        for id in self.object_ids_at(pos, ObjectClass::Building) {
+            match self.objects[&id].pos.slot_id {
+                SlotId::Id(n) => is_slot_taken[n as usize] = true,
+                _ => return false,
+            }
+        }
+        let slots_count = position::get_slots_count(&self.map, pos) as usize;
+        let slot_index = match is_slot_taken.iter().take(slots_count).position(|&taken| !taken) {
+            Some(index) => index,
+            None => return false,
+        };
+        *self.map.tile_mut(pos) = Terrain::City;
+        let exact_pos = ExactPos{map_pos: pos, slot_id: SlotId::Id(slot_index as u8)};
+        self.add_object(new_object(ObjectClass::Building, exact_pos, None));
+        true
+    }
+
+    /// Big building takes the whole tile
+    pub fn add_big_building(&mut self, pos: MapPos) -> bool {
+        if *self.map.tile(pos) == Terrain::Water
+            || !self.object_ids_at(pos, ObjectClass::Building).is_empty()
+        {
+            return false;
+        }
+        *self.map.tile_mut(pos) = Terrain::City;
+        let exact_pos = ExactPos{map_pos: pos, slot_id: SlotId::WholeTile};
+        self.add_object(new_object(ObjectClass::Building, exact_pos, None));
+        true
+    }
+
+    /// Road segment between two neighbouring tiles
+    pub fn add_road(&mut self, from: MapPos, to: MapPos) -> bool {
+        if map::distance(from, to).n != 1 {
+            return false;
+        }
+        let is_duplicate = self.object_ids_at(from, ObjectClass::Road).iter()
+            .any(|id| self.objects[id].pos.map_pos_iter().any(|p| p == to));
+        if is_duplicate {
+            return false;
+        }
+        let dir = Dir::get_dir_from_to(from, to);
+        let exact_pos = ExactPos{map_pos: from, slot_id: SlotId::TwoTiles(dir)};
+        self.add_object(new_object(ObjectClass::Road, exact_pos, None));
+        true
+    }
+
+    /// Switches the tile between no reinforcement sector
+    /// and reinforcement sectors of the first and the second player
+    pub fn toggle_reinforcement_sector(&mut self, pos: MapPos) {
+        let ids = self.object_ids_at(pos, ObjectClass::ReinforcementSector);
+        let next_owner_id = match ids.first() {
+            None => Some(PlayerId{id: 0}),
+            Some(id) => match self.objects[id].owner_id {
+                Some(PlayerId{id: 0}) => Some(PlayerId{id: 1}),
+                _ => None,
+            },
+        };
+        for id in ids {
+            self.objects.remove(&id);
+        }
+        if let Some(owner_id) = next_owner_id {
+            let exact_pos = ExactPos{map_pos: pos, slot_id: SlotId::WholeTile};
+            self.add_object(new_object(
+                ObjectClass::ReinforcementSector, exact_pos, Some(owner_id)));
+        }
+    }
+
+    pub fn new_sector_id(&self) -> SectorId {
+        let id = self.sectors.keys().map(|id| id.id + 1).max().unwrap_or(0);
+        SectorId{id: id}
+    }
+
+    /// Removes the tile from its sector or adds it to the given one.
+    /// Sectors without tiles are removed.
+    pub fn toggle_sector_tile(&mut self, sector_id: SectorId, pos: MapPos) {
+        if self.remove_sector_tile(pos) {
+            return;
+        }
+        self.sectors.entry(sector_id).or_insert_with(|| Sector {
+            positions: Vec::new(),
+            owner_id: None,
+            income: ReinforcementPoints{n: 3},
+        }).positions.push(pos);
+    }
+
+    fn remove_sector_tile(&mut self, pos: MapPos) -> bool {
+        let sector_id = match self.sectors.iter().find(|&(_, sector)| {
+            sector.positions.contains(&pos)
+        }) {
+            Some((&id, _)) => id,
+            None => return false,
+        };
+        let is_empty = {
+            let sector = self.sectors.get_mut(&sector_id).unwrap();
+            sector.positions.retain(|&p| p != pos);
+            sector.positions.is_empty()
+        };
+        if is_empty {
+            self.sectors.remove(&sector_id);
+        }
+        true
+    }
+
+    /// Removes all objects from the tile and the tile from its sector
+    pub fn clear_tile(&mut self, pos: MapPos) {
+        self.remove_buildings(pos);
+        self.objects.retain(|_, object| {
+            !object.pos.map_pos_iter().any(|p| p == pos)
+        });
+        self.remove_sector_tile(pos);
+    }
+
+    /// Objects are written in the order of their ids
+    /// and get the same ids back when loaded
+    pub fn to_text(&self) -> String {
+        let size = self.map.size();
+        let mut text = format!(
+            "size {} {}\ntarget_score {}\n",
+            size.w,
+            size.h,
+            self.target_score.n,
+        );
+        for y in 0 .. size.h {
+            let row: String = (0 .. size.w)
+                .map(|x| terrain_to_char(*self.map.tile(MapPos{v: Vector2{x: x, y: y}})))
+                .collect();
+            text.push_str(&format!("terrain {}\n", row));
+        }
+        let mut ids: Vec<_> = self.objects.keys().cloned().collect();
+        ids.sort();
+        for id in ids {
+            let object = &self.objects[&id];
+            let x = object.pos.map_pos.v.x;
+            let y = object.pos.map_pos.v.y;
+            let owner_id = object.owner_id.map_or(0, |id| id.id);
+            let line = match (object.class, object.pos.slot_id) {
+                (ObjectClass::Building, SlotId::Id(n)) => {
+                    format!("building {} {} {}", x, y, n)
+                },
+                (ObjectClass::Building, SlotId::WholeTile) => {
+                    format!("big_building {} {}", x, y)
+                },
+                (ObjectClass::Road, SlotId::TwoTiles(dir)) => {
+                    format!("road {} {} {}", x, y, dir.to_int())
+                },
+                (ObjectClass::Bridge, _) => format!("bridge {} {}", x, y),
+                (ObjectClass::ReinforcementSector, _) => {
+                    format!("reinforcement_sector {} {} {}", x, y, owner_id)
+                },
+                (ObjectClass::Minefield, _) => {
+                    format!("minefield {} {} {}", x, y, owner_id)
+                },
+                (class, slot_id) => {
+                    panic!("Can`t save {:?} in {:?}", class, slot_id);
+                },
+            };
+            text.push_str(&line);
+            text.push('\n');
+        }
+        let mut sector_ids: Vec<_> = self.sectors.keys().map(|id| id.id).collect();
+        sector_ids.sort();
+        for id in sector_ids {
+            let sector = &self.sectors[&SectorId{id: id}];
+            text.push_str(&format!("sector {}", sector.income.n));
+            for pos in &sector.positions {
+                text.push_str(&format!(" {} {}", pos.v.x, pos.v.y));
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Sectors get new ids in the order they are written
+    pub fn from_text(text: &str) -> Option<MapFile> {
+        let mut map_file: Option<MapFile> = None;
+        let mut terrain_row = 0;
+        for line in text.lines() {
+            let words: Vec<_> = line.split_whitespace().collect();
+            if let &["size", w, h] = words.as_slice() {
+                let size = Size2{w: parse(w)?, h: parse(h)?};
+                if map_file.is_some() || size.w <= 0 || size.h <= 0 {
+                    return None;
+                }
+                map_file = Some(MapFile::new(size));
+                continue;
+            }
+            if words.is_empty() {
+                continue;
+            }
+            // everything else needs the map
+            let map_file = map_file.as_mut()?;
+            if words[0] == "sector" {
+                let income = parse(words.get(1)?)?;
+                let coords = &words[2..];
+                if coords.is_empty() || coords.len() % 2 != 0 {
+                    return None;
+                }
+                let mut positions = Vec::new();
+                for xy in coords.chunks(2) {
+                    positions.push(parse_pos(&map_file.map, xy[0], xy[1])?);
+                }
+                let id = map_file.new_sector_id();
+                map_file.sectors.insert(id, Sector {
+                    positions: positions,
+                    owner_id: None,
+                    income: ReinforcementPoints{n: income},
+                });
+                continue;
+            }
+            match words.as_slice() {
+                &["target_score", n] => {
+                    map_file.target_score = Score{n: parse(n)?};
+                },
+                &["terrain", row] => {
+                    let size = map_file.map.size();
+                    if terrain_row >= size.h || row.len() != size.w as usize {
+                        return None;
+                    }
+                    for (x, c) in row.chars().enumerate() {
+                        let pos = MapPos{v: Vector2{x: x as i32, y: terrain_row}};
+                        *map_file.map.tile_mut(pos) = char_to_terrain(c)?;
+                    }
+                    terrain_row += 1;
+                },
+                &["building", x, y, n] => {
+                    let n = parse(n)?;
+                    if n > 2 {
+                        return None;
+                    }
+                    let slot_id = SlotId::Id(n);
+                    let pos = ExactPos{map_pos: parse_pos(&map_file.map, x, y)?, slot_id: slot_id};
+                    map_file.add_object(new_object(ObjectClass::Building, pos, None));
+                },
+                &["big_building", x, y] => {
+                    let pos = ExactPos{map_pos: parse_pos(&map_file.map, x, y)?, slot_id: SlotId::WholeTile};
+                    map_file.add_object(new_object(ObjectClass::Building, pos, None));
+                },
+                &["road", x, y, dir] => {
+                    let dir = parse(dir)?;
+                    if dir < 0 || dir > 5 {
+                        return None;
+                    }
+                    let dir = Dir::from_int(dir);
+                    let pos = ExactPos{map_pos: parse_pos(&map_file.map, x, y)?, slot_id: SlotId::TwoTiles(dir)};
+                    if !map_file.map.is_inboard(Dir::get_neighbour_pos(pos.map_pos, dir)) {
+                        return None;
+                    }
+                    map_file.add_object(new_object(ObjectClass::Road, pos, None));
+                },
+                &["bridge", x, y] => {
+                    let pos = ExactPos{map_pos: parse_pos(&map_file.map, x, y)?, slot_id: SlotId::WholeTile};
+                    map_file.add_object(new_object(ObjectClass::Bridge, pos, None));
+                },
+                &["reinforcement_sector", x, y, owner_id] => {
+                    let pos = ExactPos{map_pos: parse_pos(&map_file.map, x, y)?, slot_id: SlotId::WholeTile};
+                    let owner_id = parse_player_id(owner_id)?;
+                    map_file.add_object(new_object(
+                        ObjectClass::ReinforcementSector, pos, Some(owner_id)));
+                },
+                &["minefield", x, y, owner_id] => {
+                    let pos = ExactPos{map_pos: parse_pos(&map_file.map, x, y)?, slot_id: SlotId::WholeTile};
+                    let owner_id = parse_player_id(owner_id)?;
+                    map_file.add_object(new_object(ObjectClass::Minefield, pos, Some(owner_id)));
+                },
+                _ => return None,
+            }
+        }
+        map_file
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<MapFile> {
+        let mut text = String::new();
+        fs::File::open(path)?.read_to_string(&mut text)?;
+        MapFile::from_text(&text).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "Bad map file")
+        })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::File::create(path)?.write_all(self.to_text().as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use position::{MapPos, SlotId};
+    use map::{Terrain};
+    use object::{ObjectId, ObjectClass};
+    use event::{CoreEvent};
+    use types::{Size2};
+    use super::{MapFile};
+
+    fn new_state() -> State {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map01".to_owned(),
+            players_count: 2,
+        };
+        State::new_full(Rc::new(Db::new()), &options).unwrap()
+    }
+
+    #[test]
+    fn test_saved_map_is_loaded_back() {
+        let state = new_state();
+        let map_file = MapFile::from_state(&state);
+        let text = map_file.to_text();
+        let loaded = MapFile::from_text(&text).unwrap();
+        assert_eq!(loaded.to_text(), text);
+        assert_eq!(loaded.objects.len(), state.objects().len());
+        assert_eq!(loaded.sectors.len(), state.sectors().len());
+    }
+
+    #[test]
+    fn test_objects_left_by_units_are_not_saved() {
+        let mut state = new_state();
+        let objects_count = state.objects().len();
+        let pos = |x| MapPos{v: Vector2{x: x, y: 1}};
+        state.apply_event(&CoreEvent::Smoke {
+            id: ObjectId{id: 100},
+            pos: pos(0),
+            unit_id: None,
+        });
+        for (id, class) in vec![(101, ObjectClass::Trench), (102, ObjectClass::Bridge)] {
+            state.apply_event(&CoreEvent::Build {
+                id: ObjectId{id: id},
+                pos: pos(id - 100),
+                class: class,
+                unit_id: None,
+            });
+        }
+        let map_file = MapFile::from_state(&state);
+        assert_eq!(map_file.objects.len(), objects_count);
+        let loaded = MapFile::from_text(&map_file.to_text()).unwrap();
+        assert_eq!(loaded.objects.len(), objects_count);
+    }
+
+    #[test]
+    fn test_buildings_take_free_slots() {
+        let mut map_file = MapFile::new(Size2{w: 3, h: 3});
+        let pos = MapPos{v: Vector2{x: 1, y: 1}};
+        for _ in 0 .. 3 {
+            assert!(map_file.add_building(pos));
+        }
+        assert!(!map_file.add_building(pos));
+        assert!(!map_file.add_big_building(pos));
+        let mut slot_ids: Vec<_> = map_file.objects.values()
+            .map(|object| match object.pos.slot_id {
+                SlotId::Id(n) => n,
+                _ => panic!(),
+            })
+            .collect();
+        slot_ids.sort();
+        assert_eq!(slot_ids, vec![0, 1, 2]);
+        assert_eq!(*map_file.map.tile(pos), Terrain::City);
+        map_file.clear_tile(pos);
+        assert!(map_file.objects.is_empty());
+        assert_eq!(*map_file.map.tile(pos), Terrain::Plain);
+    }
+}
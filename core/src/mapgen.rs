@@ -0,0 +1,401 @@
+use std::cmp;
+use std::collections::{HashSet};
+use cgmath::{Vector2};
+use rand::{Rng, SeedableRng, XorShiftRng};
+use types::{Size2};
+use map::{self, Map, Terrain};
+use dir::{Dir, dirs};
+use position::{MapPos};
+use player::{PlayerId};
+
+const MAP_NAME_PREFIX: &'static str = "random";
+
+pub const MIN_SIZE: i32 = 10;
+pub const MAX_SIZE: i32 = 40;
+
+/// Parameters of a generated map. Generation is deterministic
+/// so the parameters are all that is needed to recreate the map.
+#[derive(Clone, Copy, Debug)]
+pub struct MapGenOptions {
+    pub seed: u32,
+
+    /// Height must be even or the map can't be mirrored
+    pub size: Size2,
+
+    /// Percent of free land that is covered by forests
+    pub density: i32,
+}
+
+impl MapGenOptions {
+    pub fn is_valid(&self) -> bool {
+        let is_size_ok = |n| n >= MIN_SIZE && n <= MAX_SIZE;
+        is_size_ok(self.size.w)
+            && is_size_ok(self.size.h)
+            && self.size.h % 2 == 0
+            && self.density >= 0
+            && self.density <= 100
+    }
+
+    /// Generated maps are passed around as `Options::map_name`
+    /// like "random_42_12x14_30"
+    pub fn to_map_name(&self) -> String {
+        format!(
+            "{}_{}_{}x{}_{}",
+            MAP_NAME_PREFIX,
+            self.seed,
+            self.size.w,
+            self.size.h,
+            self.density,
+        )
+    }
+
+    pub fn from_map_name(map_name: &str) -> Option<MapGenOptions> {
+        let words: Vec<_> = map_name.split('_').collect();
+        let (seed, size, density) = match words.as_slice() {
+            &[prefix, seed, size, density] if prefix == MAP_NAME_PREFIX => {
+                (seed, size, density)
+            },
+            _ => return None,
+        };
+        let size: Vec<_> = size.split('x').collect();
+        let options = match (seed.parse(), size.as_slice(), density.parse()) {
+            (Ok(seed), &[w, h], Ok(density)) => match (w.parse(), h.parse()) {
+                (Ok(w), Ok(h)) => MapGenOptions {
+                    seed: seed,
+                    size: Size2{w: w, h: h},
+                    density: density,
+                },
+                _ => return None,
+            },
+            _ => return None,
+        };
+        if options.is_valid() {
+            Some(options)
+        } else {
+            None
+        }
+    }
+}
+
+/// Everything except the terrain is described by tiles only:
+/// `game_state` turns them into objects and sectors
+#[derive(Clone, Debug)]
+pub struct GeneratedMap {
+    pub map: Map<Terrain>,
+
+    /// Tiles with small buildings and the number of buildings there
+    pub buildings: Vec<(MapPos, i32)>,
+
+    pub roads: Vec<Vec<MapPos>>,
+    pub bridges: Vec<MapPos>,
+
+    /// Tiles of the sectors. The sectors go in mirrored pairs.
+    pub sectors: Vec<Vec<MapPos>>,
+
+    pub reinforcement_sectors: Vec<(MapPos, PlayerId)>,
+}
+
+/// Players' halves are symmetric relative to the center of the map.
+/// Unlike a simple flip this keeps the hex neighbourhood intact
+/// as long as the map's height is even.
+fn mirror(size: Size2, pos: MapPos) -> MapPos {
+    MapPos{v: Vector2{x: size.w - 1 - pos.v.x, y: size.h - 1 - pos.v.y}}
+}
+
+fn neighbours(size: Size2, pos: MapPos) -> Vec<MapPos> {
+    let map_rect: Map<bool> = Map::new(size);
+    dirs()
+        .map(|dir| Dir::get_neighbour_pos(pos, dir))
+        .filter(|&pos| map_rect.is_inboard(pos))
+        .collect()
+}
+
+/// Shortest path: every step gets one tile closer to the target
+fn straight_path(size: Size2, from: MapPos, to: MapPos) -> Vec<MapPos> {
+    let mut path = vec![from];
+    let mut pos = from;
+    while pos != to {
+        let next_pos = neighbours(size, pos).into_iter()
+            .min_by_key(|&next_pos| map::distance(next_pos, to).n)
+            .unwrap();
+        assert!(map::distance(next_pos, to) < map::distance(pos, to));
+        pos = next_pos;
+        path.push(pos);
+    }
+    path
+}
+
+/// Upper half of the river that splits the map between the players.
+/// Starts in the middle row next to its own mirrored tile.
+fn river<R: Rng>(rng: &mut R, size: Size2) -> Vec<MapPos> {
+    let middle_y = size.h / 2 - 1;
+    let middle_x = (0 .. size.w).find(|&x| {
+        let pos = MapPos{v: Vector2{x: x, y: middle_y}};
+        map::distance(pos, mirror(size, pos)).n == 1
+    }).expect("Can`t find the middle of the river");
+    let min_x = size.w / 2 - 2;
+    let max_x = size.w / 2 + 1;
+    let mut pos = MapPos{v: Vector2{x: middle_x, y: middle_y}};
+    let mut path = vec![pos];
+    while pos.v.y > 0 {
+        let next_positions: Vec<_> = neighbours(size, pos).into_iter()
+            .filter(|next_pos| {
+                next_pos.v.y == pos.v.y - 1
+                    && next_pos.v.x >= min_x
+                    && next_pos.v.x <= max_x
+            })
+            .collect();
+        pos = *rng.choose(&next_positions).unwrap();
+        path.push(pos);
+    }
+    path
+}
+
+/// Tiles of a town and of its sector: the center and its neighbours
+fn town_tiles(size: Size2, center: MapPos) -> Vec<MapPos> {
+    let mut tiles = vec![center];
+    tiles.extend(neighbours(size, center));
+    tiles
+}
+
+/// Town centers of the first player's (left) half
+fn place_towns<R: Rng>(
+    rng: &mut R,
+    map: &Map<Terrain>,
+) -> Vec<MapPos> {
+    let size = map.size();
+    let towns_count = cmp::max(1, size.w * size.h / 100);
+    let mut towns: Vec<MapPos> = Vec::new();
+    for _ in 0 .. 100 {
+        if towns.len() as i32 == towns_count {
+            break;
+        }
+        let center = MapPos{v: Vector2{
+            x: rng.gen_range(2, size.w / 2),
+            y: rng.gen_range(1, size.h - 1),
+        }};
+        let tiles = town_tiles(size, center);
+        // the whole sector must fit or its center will be off
+        let is_whole = tiles.len() == 7;
+        let is_dry = tiles.iter().all(|&pos| *map.tile(pos) != Terrain::Water);
+        let min_distance = map::Distance{n: 4};
+        // the town must not touch other towns or its own mirrored copy
+        let is_far = towns.iter().all(|&pos| {
+            map::distance(pos, center) >= min_distance
+        }) && towns.iter().chain(&[center]).all(|&pos| {
+            map::distance(mirror(size, pos), center) >= min_distance
+        });
+        if is_whole && is_dry && is_far {
+            towns.push(center);
+        }
+    }
+    towns
+}
+
+/// Connects the places with roads: the shortest spanning network
+fn road_network(size: Size2, places: &[MapPos]) -> Vec<Vec<MapPos>> {
+    let mut roads = Vec::new();
+    let mut connected = vec![places[0]];
+    let mut rest = places[1..].to_vec();
+    while !rest.is_empty() {
+        let mut best = None;
+        for (i, &to) in rest.iter().enumerate() {
+            for &from in &connected {
+                let distance = map::distance(from, to);
+                let is_better = match best {
+                    Some((_, _, best_distance)) => distance < best_distance,
+                    None => true,
+                };
+                if is_better {
+                    best = Some((i, from, distance));
+                }
+            }
+        }
+        let (i, from, _) = best.unwrap();
+        let to = rest.remove(i);
+        roads.push(straight_path(size, from, to));
+        connected.push(to);
+    }
+    roads
+}
+
+/// Adds the mirrored copy of every tile
+fn with_mirrored(size: Size2, tiles: &[MapPos]) -> Vec<MapPos> {
+    let mut all_tiles = tiles.to_vec();
+    all_tiles.extend(tiles.iter().map(|&pos| mirror(size, pos)));
+    all_tiles
+}
+
+fn plant_forests<R: Rng>(
+    rng: &mut R,
+    map: &mut Map<Terrain>,
+    busy_tiles: &HashSet<MapPos>,
+    density: i32,
+) {
+    let size = map.size();
+    let upper_half: Vec<_> = map.get_iter()
+        .filter(|pos| pos.v.y < size.h / 2)
+        .collect();
+    let is_free = |map: &Map<Terrain>, pos: MapPos| {
+        *map.tile(pos) == Terrain::Plain && !busy_tiles.contains(&pos)
+    };
+    // scattered trees first and then groves grow around them
+    for &(chance, needs_trees_around) in &[(density / 4, false), (density, true)] {
+        for &pos in &upper_half {
+            if !is_free(map, pos) || rng.gen_range(0, 100) >= chance {
+                continue;
+            }
+            let has_trees_around = neighbours(size, pos).into_iter()
+                .any(|pos| *map.tile(pos) == Terrain::Trees);
+            if needs_trees_around && !has_trees_around {
+                continue;
+            }
+            // keep the map symmetric after every change
+            // so the growth doesn't depend on the half
+            *map.tile_mut(pos) = Terrain::Trees;
+            *map.tile_mut(mirror(size, pos)) = Terrain::Trees;
+        }
+    }
+}
+
+/// Generates a map that is fair for both players: the second
+/// player's half is a mirrored copy of the first player's one
+pub fn generate(options: &MapGenOptions) -> GeneratedMap {
+    assert!(options.is_valid());
+    let size = options.size;
+    let mut rng = XorShiftRng::from_seed([
+        options.seed,
+        0x193a_6754,
+        0xa8a7_d469,
+        0x9783_0e05,
+    ]);
+    let mut map = Map::new(size);
+    let river = river(&mut rng, size);
+    for pos in with_mirrored(size, &river) {
+        *map.tile_mut(pos) = Terrain::Water;
+    }
+    let towns = place_towns(&mut rng, &map);
+    let mut busy_tiles = HashSet::new();
+    let mut buildings = Vec::new();
+    let mut sectors = Vec::new();
+    for &center in &towns {
+        let tiles = town_tiles(size, center);
+        for &pos in &tiles {
+            let count = if pos == center {
+                rng.gen_range(2, 4)
+            } else if rng.gen_range(0, 2) == 0 {
+                rng.gen_range(1, 3)
+            } else {
+                0
+            };
+            if count > 0 {
+                buildings.push((pos, count));
+                buildings.push((mirror(size, pos), count));
+            }
+        }
+        busy_tiles.extend(with_mirrored(size, &tiles));
+        sectors.push(tiles.clone());
+        sectors.push(tiles.iter().map(|&pos| mirror(size, pos)).collect());
+    }
+    for &(pos, _) in &buildings {
+        *map.tile_mut(pos) = Terrain::City;
+    }
+    let reinforcement_positions = vec![
+        MapPos{v: Vector2{x: 0, y: rng.gen_range(1, size.h / 2)}},
+        MapPos{v: Vector2{x: 0, y: rng.gen_range(size.h / 2, size.h - 1)}},
+    ];
+    busy_tiles.extend(with_mirrored(size, &reinforcement_positions));
+    let mut reinforcement_sectors = Vec::new();
+    for &pos in &reinforcement_positions {
+        reinforcement_sectors.push((pos, PlayerId{id: 0}));
+        reinforcement_sectors.push((mirror(size, pos), PlayerId{id: 1}));
+    }
+    let mut places = reinforcement_positions.clone();
+    places.extend_from_slice(&towns);
+    let mut roads = Vec::new();
+    for road in road_network(size, &places) {
+        roads.push(road.iter().map(|&pos| mirror(size, pos)).collect());
+        roads.push(road);
+    }
+    // the only road between the halves crosses the river in its middle
+    let river_middle = river[0];
+    let crossing_start = *places.iter()
+        .min_by_key(|&&pos| map::distance(pos, river_middle).n)
+        .unwrap();
+    let mut crossing_road = straight_path(size, crossing_start, river_middle);
+    let mirrored_crossing_road: Vec<_> = crossing_road.iter().rev()
+        .map(|&pos| mirror(size, pos))
+        .collect();
+    crossing_road.extend(mirrored_crossing_road);
+    roads.push(crossing_road);
+    let mut bridges = Vec::new();
+    for road in &roads {
+        for &pos in road {
+            busy_tiles.insert(pos);
+            if *map.tile(pos) == Terrain::Water && !bridges.contains(&pos) {
+                bridges.push(pos);
+            }
+        }
+    }
+    plant_forests(&mut rng, &mut map, &busy_tiles, options.density);
+    GeneratedMap {
+        map: map,
+        buildings: buildings,
+        roads: roads,
+        bridges: bridges,
+        sectors: sectors,
+        reinforcement_sectors: reinforcement_sectors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use types::{Size2};
+    use super::{MapGenOptions, generate, mirror};
+
+    fn options(seed: u32) -> MapGenOptions {
+        MapGenOptions {
+            seed: seed,
+            size: Size2{w: 14, h: 16},
+            density: 40,
+        }
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_map() {
+        let map_name = options(42).to_map_name();
+        assert_eq!(map_name, "random_42_14x16_40");
+        let options = MapGenOptions::from_map_name(&map_name).unwrap();
+        let a = generate(&options);
+        let b = generate(&options);
+        for pos in a.map.get_iter() {
+            assert_eq!(a.map.tile(pos), b.map.tile(pos));
+        }
+        assert_eq!(a.buildings, b.buildings);
+        assert_eq!(a.roads, b.roads);
+        assert_eq!(a.bridges, b.bridges);
+        assert_eq!(a.sectors, b.sectors);
+        assert_eq!(a.reinforcement_sectors, b.reinforcement_sectors);
+        assert!(MapGenOptions::from_map_name("random_42_14x15_40").is_none());
+    }
+
+    #[test]
+    fn test_halves_are_mirrored() {
+        for seed in 0 .. 20 {
+            let generated = generate(&options(seed));
+            let map = &generated.map;
+            let size = map.size();
+            for pos in map.get_iter() {
+                assert_eq!(map.tile(pos), map.tile(mirror(size, pos)));
+            }
+            assert!(!generated.bridges.is_empty());
+            assert_eq!(generated.sectors.len() % 2, 0);
+            for pair in generated.sectors.chunks(2) {
+                let mirrored: Vec<_> = pair[0].iter()
+                    .map(|&pos| mirror(size, pos))
+                    .collect();
+                assert_eq!(mirrored, pair[1]);
+            }
+        }
+    }
+}
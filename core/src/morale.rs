@@ -0,0 +1,95 @@
+use db::{Db};
+use game_state::{State};
+use map::{Distance, distance};
+use unit::{Unit, UnitId};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MoraleState {
+    Steady,
+    Pinned,
+    Retreating,
+    Routed,
+}
+
+impl MoraleState {
+    pub fn to_str(&self) -> &str {
+        match *self {
+            MoraleState::Steady => "steady",
+            MoraleState::Pinned => "pinned",
+            MoraleState::Retreating => "retreating",
+            MoraleState::Routed => "routed",
+        }
+    }
+}
+
+// TODO: get from config
+const PINNED_MORALE: i32 = 50;
+const RETREATING_MORALE: i32 = 25;
+const ROUTED_MORALE: i32 = 10;
+
+/// How much morale is lost by every friendly unit near a killed soldier
+const NEARBY_DEATH_MORALE_PENALTY: i32 = 5;
+
+pub fn morale_state(morale: i32) -> MoraleState {
+    if morale >= PINNED_MORALE {
+        MoraleState::Steady
+    } else if morale >= RETREATING_MORALE {
+        MoraleState::Pinned
+    } else if morale >= ROUTED_MORALE {
+        MoraleState::Retreating
+    } else {
+        MoraleState::Routed
+    }
+}
+
+pub fn unit_morale_state(unit: &Unit) -> MoraleState {
+    morale_state(unit.morale)
+}
+
+/// Morale penalties that friendly units near the defender
+/// get when its soldiers are killed.
+pub fn nearby_deaths_morale_penalties(
+    db: &Db,
+    state: &State,
+    defender: &Unit,
+    killed: i32,
+) -> Vec<(UnitId, i32)> {
+    let mut penalties = Vec::new();
+    if killed <= 0 {
+        return penalties;
+    }
+    let max_distance = Distance{n: 1};
+    for (&id, unit) in state.units() {
+        if id == defender.id
+            || unit.player_id != defender.player_id
+            || !unit.is_alive
+        {
+            continue;
+        }
+        if db.unit_type(unit.type_id).is_air {
+            continue;
+        }
+        if distance(unit.pos.map_pos, defender.pos.map_pos) > max_distance {
+            continue;
+        }
+        penalties.push((id, NEARBY_DEATH_MORALE_PENALTY * killed));
+    }
+    penalties
+}
+
+#[cfg(test)]
+mod tests {
+    use morale::{MoraleState, morale_state};
+
+    #[test]
+    fn test_morale_state() {
+        assert_eq!(morale_state(100), MoraleState::Steady);
+        assert_eq!(morale_state(50), MoraleState::Steady);
+        assert_eq!(morale_state(49), MoraleState::Pinned);
+        assert_eq!(morale_state(25), MoraleState::Pinned);
+        assert_eq!(morale_state(24), MoraleState::Retreating);
+        assert_eq!(morale_state(10), MoraleState::Retreating);
+        assert_eq!(morale_state(9), MoraleState::Routed);
+        assert_eq!(morale_state(-20), MoraleState::Routed);
+    }
+}
@@ -6,7 +6,7 @@ use unit::{Unit};
 use map::{Map, Terrain};
 use game_state::{State};
 use dir::{Dir, dirs};
-use position::{ExactPos, SlotId, get_free_exact_pos};
+use position::{self, ExactPos, SlotId, get_free_exact_pos};
 use object::{ObjectClass};
 use event::{MoveMode};
 
@@ -81,7 +81,6 @@ pub fn max_cost() -> MovePoints {
     MovePoints{n: i32::max_value()}
 }
 
-// TODO: increase cost for attached units
 pub fn tile_cost(db: &Db, state: &State, unit: &Unit, from: ExactPos, pos: ExactPos)
     -> MovePoints
 {
@@ -123,6 +122,10 @@ pub fn tile_cost(db: &Db, state: &State, unit: &Unit, from: ExactPos, pos: Exact
             Terrain::Water => 99,
         }
     };
+    if *tile == Terrain::Water && position::is_bridge_at(state, map_pos) {
+        terrain_cost = 4;
+    }
+    let mut is_on_road = false;
     for object in objects_at.clone() {
         if object.class != ObjectClass::Road {
             continue;
@@ -136,22 +139,40 @@ pub fn tile_cost(db: &Db, state: &State, unit: &Unit, from: ExactPos, pos: Exact
         if (is_road_pos_ok || is_road_pos_rev_ok) && !unit_type.is_big {
             // TODO: ultrahardcoded value :(
             terrain_cost = if unit_type.is_infantry { 4 } else { 2 };
+            is_on_road = true;
         }
     }
+    if unit.attached_unit_id.is_some() {
+        // towed guns slow transporters down, especially off-road
+        terrain_cost += if is_on_road {
+            1
+        } else {
+            match *tile {
+                Terrain::Plain | Terrain::City => 2,
+                Terrain::Trees => 4,
+                Terrain::Water => 0,
+            }
+        };
+    }
     for object in objects_at {
         let cost = if unit_type.is_infantry {
             match object.class {
                 ObjectClass::Building => 1,
                 ObjectClass::ReinforcementSector |
                 ObjectClass::Road |
-                ObjectClass::Smoke => 0,
+                ObjectClass::Smoke |
+                ObjectClass::Bridge |
+                ObjectClass::Trench |
+                ObjectClass::Minefield => 0,
             }
         } else {
             match object.class {
-                ObjectClass::Building => 2,
+                ObjectClass::Building | ObjectClass::Trench => 2,
                 ObjectClass::ReinforcementSector |
                 ObjectClass::Road |
-                ObjectClass::Smoke => 0,
+                ObjectClass::Smoke |
+                ObjectClass::Bridge |
+                ObjectClass::Minefield => 0,
             }
         };
         object_cost += cost;
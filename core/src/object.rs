@@ -7,6 +7,22 @@ pub enum ObjectClass {
     Road,
     Smoke,
     ReinforcementSector,
+    Bridge,
+    Trench,
+    Minefield,
+}
+
+/// Turns of engineers' work needed to build an object of this class
+pub fn build_turns(class: ObjectClass) -> i32 {
+    match class {
+        ObjectClass::Bridge => 3,
+        ObjectClass::Trench => 1,
+        ObjectClass::Minefield => 1,
+        ObjectClass::Building |
+        ObjectClass::Road |
+        ObjectClass::Smoke |
+        ObjectClass::ReinforcementSector => panic!("Can`t build {:?}", class),
+    }
 }
 
 #[derive(Debug, PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy)]
@@ -20,4 +36,19 @@ pub struct Object {
     pub class: ObjectClass,
     pub timer: Option<i32>,
     pub owner_id: Option<PlayerId>,
+
+    /// Turns of work left before a construction is finished
+    pub build_turns_left: i32,
+}
+
+impl Object {
+    pub fn is_finished(&self) -> bool {
+        self.build_turns_left == 0
+    }
+
+    /// Minefields are known only to their owners until discovered
+    pub fn is_hidden_from(&self, player_id: PlayerId) -> bool {
+        self.class == ObjectClass::Minefield
+            && self.owner_id != Some(player_id)
+    }
 }
@@ -1,6 +1,7 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use event::{CoreEvent};
-use unit::{UnitId};
+use unit::{self, UnitId, UnitTypeId};
+use position::{MapPos, ExactPos};
 use fow::{Fow};
 use db::{Db};
 use game_state::{State};
@@ -23,11 +24,23 @@ pub struct Player {
     pub class: PlayerClass,
 }
 
+/// What the player remembers about an enemy unit
+#[derive(Clone, Debug, PartialEq)]
+pub struct LastSeenEnemy {
+    pub unit_id: UnitId,
+    pub type_id: UnitTypeId,
+    pub pos: ExactPos,
+
+    /// Game turn when the unit was seen for the last time
+    pub turn: i32,
+}
+
 #[derive(Clone, Debug)]
 pub struct PlayerInfo {
     id: PlayerId,
     events: VecDeque<CoreEvent>,
     visible_enemies: HashSet<UnitId>,
+    last_seen_enemies: HashMap<UnitId, LastSeenEnemy>,
 
     // This filed is optional because we need to temporary
     // put its Fow into Core's State for filtering events.
@@ -44,6 +57,7 @@ impl PlayerInfo {
             fow: Some(fow),
             events: VecDeque::new(),
             visible_enemies: HashSet::new(),
+            last_seen_enemies: HashMap::new(),
         }
     }
 
@@ -52,14 +66,56 @@ impl PlayerInfo {
             state, self.id, self.fow(), event);
         for filtered_event in filtered_events {
             self.fow_mut().apply_event(state, &filtered_event);
-            self.events.push_back(filtered_event);
             let new_enemies = filter::get_visible_enemies(
                 state, self.fow(), self.id);
             let show_hide_events = filter::show_or_hide_passive_enemies(
                 state, &active_unit_ids, &self.visible_enemies, &new_enemies);
-            self.events.extend(show_hide_events);
             self.visible_enemies = new_enemies;
+            self.update_last_seen_enemies(state, &filtered_event);
+            self.events.push_back(filtered_event);
+            self.events.extend(show_hide_events);
+        }
+    }
+
+    fn update_last_seen_enemies(&mut self, state: &State, event: &CoreEvent) {
+        if let CoreEvent::AttackUnit{ref attack_info} = *event {
+            let is_killed = state.unit_opt(attack_info.defender_id)
+                .map_or(true, |unit| !unit.is_alive);
+            if is_killed {
+                self.last_seen_enemies.remove(&attack_info.defender_id);
+            }
+        }
+        for &id in &self.visible_enemies {
+            let unit = state.unit(id);
+            if !unit.is_alive || unit::is_loaded_or_attached(unit) {
+                self.last_seen_enemies.remove(&id);
+                continue;
+            }
+            self.last_seen_enemies.insert(id, LastSeenEnemy {
+                unit_id: id,
+                type_id: unit.type_id,
+                pos: unit.pos,
+                turn: state.turn(),
+            });
         }
+        // forget enemies that are not where they were seen anymore
+        let visible_enemies = &self.visible_enemies;
+        let fow = self.fow.as_ref().unwrap();
+        self.last_seen_enemies.retain(|id, enemy| {
+            visible_enemies.contains(id)
+                || !fow.is_type_visible_at(enemy.type_id, enemy.pos)
+        });
+    }
+
+    /// Enemies that the player has lost sight of
+    pub fn last_seen_enemies(&self) -> Vec<&LastSeenEnemy> {
+        self.last_seen_enemies.values()
+            .filter(|enemy| !self.visible_enemies.contains(&enemy.unit_id))
+            .collect()
+    }
+
+    pub fn is_tile_explored(&self, pos: MapPos) -> bool {
+        self.fow().is_explored(pos)
     }
 
     pub fn get_event(&mut self) -> Option<CoreEvent> {
@@ -90,3 +146,167 @@ impl PlayerInfo {
         self.fow.as_mut().unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use unit::{UnitId, new_unit};
+    use position::{MapPos, ExactPos, SlotId};
+    use movement::{MovePoints};
+    use event::{CoreEvent, MoveMode, AttackInfo, FireMode};
+    use super::{PlayerId, PlayerInfo};
+
+    const OWN_ID: UnitId = UnitId{id: 1};
+    const ENEMY_ID: UnitId = UnitId{id: 2};
+
+    fn map_pos(x: i32, y: i32) -> MapPos {
+        MapPos{v: Vector2{x: x, y: y}}
+    }
+
+    fn exact_pos(x: i32, y: i32) -> ExactPos {
+        ExactPos{map_pos: map_pos(x, y), slot_id: SlotId::Id(0)}
+    }
+
+    struct TestGame {
+        state: State,
+        info: PlayerInfo,
+    }
+
+    impl TestGame {
+        /// Own soldier in (0, 0) sees an enemy soldier in (2, 0)
+        fn new() -> TestGame {
+            let options = Options {
+                game_type: GameType::Hotseat,
+                map_name: "map01".to_owned(),
+                players_count: 2,
+            };
+            let state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+            let info = PlayerInfo::new(&state, PlayerId{id: 0});
+            let mut game = TestGame{state: state, info: info};
+            game.create_soldier(OWN_ID, 0, exact_pos(0, 0));
+            game.create_soldier(ENEMY_ID, 1, exact_pos(2, 0));
+            game
+        }
+
+        fn apply(&mut self, event: &CoreEvent) {
+            self.state.apply_event(event);
+            self.info.filter_event(&self.state, event);
+        }
+
+        fn create_soldier(&mut self, id: UnitId, player_id: i32, pos: ExactPos) {
+            let type_id = self.state.db().unit_type_id("soldier");
+            let unit = new_unit(
+                self.state.db(), id, PlayerId{id: player_id}, type_id, pos);
+            let cost = self.state.db().unit_type(type_id).cost;
+            self.apply(&CoreEvent::CreateUnit {
+                unit_info: unit,
+                cost: cost,
+            });
+        }
+
+        fn teleport(&mut self, id: UnitId, pos: ExactPos) {
+            let from = self.state.unit(id).pos;
+            self.apply(&CoreEvent::Move {
+                unit_id: id,
+                from: from,
+                to: pos,
+                mode: MoveMode::Fast,
+                cost: MovePoints{n: 1},
+            });
+        }
+
+        fn end_turn(&mut self, old_id: i32, new_id: i32) {
+            let new_id = PlayerId{id: new_id};
+            let turn = self.state.next_turn(new_id);
+            self.apply(&CoreEvent::EndTurn {
+                old_id: PlayerId{id: old_id},
+                new_id: new_id,
+                turn: turn,
+            });
+        }
+
+        /// Own view is refreshed only at the start of own turn
+        fn lose_sight_of_enemy(&mut self) {
+            self.teleport(OWN_ID, exact_pos(0, 11));
+            self.end_turn(0, 1);
+            self.end_turn(1, 0);
+            assert!(!self.info.visible_enemies().contains(&ENEMY_ID));
+        }
+    }
+
+    #[test]
+    fn test_explored_tiles_stay_explored() {
+        let mut game = TestGame::new();
+        assert!(game.info.is_tile_explored(map_pos(2, 0)));
+        assert!(!game.info.is_tile_explored(map_pos(0, 11)));
+        assert!(!game.info.is_tile_explored(map_pos(9, 11)));
+        game.lose_sight_of_enemy();
+        assert!(game.info.is_tile_explored(map_pos(2, 0)));
+        assert!(game.info.is_tile_explored(map_pos(0, 11)));
+        assert!(!game.info.is_tile_explored(map_pos(9, 11)));
+    }
+
+    #[test]
+    fn test_last_seen_enemy_is_remembered() {
+        let mut game = TestGame::new();
+        assert!(game.info.visible_enemies().contains(&ENEMY_ID));
+        // visible enemies are not "last seen"
+        assert!(game.info.last_seen_enemies().is_empty());
+        game.lose_sight_of_enemy();
+        let enemies = game.info.last_seen_enemies();
+        assert_eq!(enemies.len(), 1);
+        assert_eq!(enemies[0].unit_id, ENEMY_ID);
+        assert_eq!(enemies[0].pos, exact_pos(2, 0));
+        assert_eq!(enemies[0].turn, 1);
+    }
+
+    #[test]
+    fn test_last_seen_turn_is_updated() {
+        let mut game = TestGame::new();
+        game.end_turn(0, 1);
+        game.end_turn(1, 0);
+        game.end_turn(0, 1);
+        game.end_turn(1, 0);
+        assert!(game.info.visible_enemies().contains(&ENEMY_ID));
+        game.lose_sight_of_enemy();
+        assert_eq!(game.info.last_seen_enemies()[0].turn, 3);
+    }
+
+    #[test]
+    fn test_moved_enemy_is_forgotten() {
+        let mut game = TestGame::new();
+        game.lose_sight_of_enemy();
+        game.teleport(ENEMY_ID, exact_pos(9, 11));
+        assert_eq!(game.info.last_seen_enemies().len(), 1);
+        // the tile is empty when the player looks at it again
+        game.teleport(OWN_ID, exact_pos(0, 0));
+        assert!(game.info.last_seen_enemies().is_empty());
+    }
+
+    #[test]
+    fn test_killed_enemy_is_forgotten() {
+        let mut game = TestGame::new();
+        let count = game.state.unit(ENEMY_ID).count;
+        game.apply(&CoreEvent::AttackUnit {
+            attack_info: AttackInfo {
+                attacker_id: Some(OWN_ID),
+                defender_id: ENEMY_ID,
+                mode: FireMode::Active,
+                killed: count,
+                suppression: 0,
+                remove_move_points: false,
+                is_ambush: false,
+                is_inderect: false,
+                is_scattered: false,
+                leave_wrecks: false,
+                is_splash: false,
+            },
+        });
+        game.lose_sight_of_enemy();
+        assert!(game.info.last_seen_enemies().is_empty());
+    }
+}
@@ -2,7 +2,7 @@ use std::{fmt};
 use std::collections::{HashMap};
 use cgmath::{Vector2};
 use dir::{Dir};
-use game_state::{State, ObjectsAtIter};
+use game_state::{State};
 use map::{Map, Terrain};
 use unit::{self, UnitId, Unit, UnitType};
 use object::{Object, ObjectId, ObjectClass};
@@ -132,8 +132,11 @@ pub fn get_unit_ids_at(state: &State, pos: MapPos) -> Vec<UnitId> {
     ids
 }
 
-pub fn objects_at(objects: &HashMap<ObjectId, Object>, pos: MapPos) -> ObjectsAtIter {
-    ObjectsAtIter::new(objects, pos)
+/// Slow version of `State::objects_at` for maps that are not loaded yet
+pub fn objects_at(objects: &HashMap<ObjectId, Object>, pos: MapPos) -> Vec<&Object> {
+    objects.values()
+        .filter(|object| object.pos.map_pos_iter().any(|p| p == pos))
+        .collect()
 }
 
 pub fn get_free_slot_for_building(
@@ -210,11 +213,13 @@ fn can_place_big_ground_unit(
     unit_type: &UnitType,
     pos: ExactPos,
 ) -> bool {
-    // TODO: forbid placing on bridge tile
     assert!(unit_type.is_big);
     if pos.slot_id != SlotId::WholeTile {
         return false;
     }
+    if is_bridge_at(state, pos.map_pos) {
+        return false;
+    }
     for object in state.objects_at(pos.map_pos) {
         if object.class == ObjectClass::Building {
             return false;
@@ -285,16 +290,27 @@ fn can_place_small_ground_unit(
     true
 }
 
+/// Is there a finished bridge on this tile
+pub fn is_bridge_at(state: &State, pos: MapPos) -> bool {
+    state.objects_at(pos).any(|object| {
+        object.class == ObjectClass::Bridge && object.is_finished()
+    })
+}
+
 fn can_place_ground_unit(
     state: &State,
     unit_type: &UnitType,
     pos: ExactPos,
 ) -> bool {
-    // TODO: forbid placing on water tiles without bridge
     // TODO: check max move points
     if pos.slot_id == SlotId::Air {
         return false;
     }
+    if *state.map().tile(pos.map_pos) == Terrain::Water
+        && !is_bridge_at(state, pos.map_pos)
+    {
+        return false;
+    }
     if unit_type.is_big {
         can_place_big_ground_unit(state, unit_type, pos)
     } else {
@@ -3,6 +3,8 @@ use unit::{Unit};
 use game_state::{State};
 use map::{Terrain};
 use position::{MapPos};
+use morale::{unit_morale_state};
+use veterancy::{unit_veterancy};
 
 pub fn print_unit_info(db: &Db, unit: &Unit) {
     let unit_type = db.unit_type(unit.type_id);
@@ -25,8 +27,9 @@ pub fn print_unit_info(db: &Db, unit: &Unit) {
         println!("  reactive_attack_points: ?");
     }
     println!("  count: {}", unit.count);
-    println!("  morale: {}", unit.morale);
-    println!("  passenger_id: {:?}", unit.passenger_id);
+    println!("  morale: {} ({})", unit.morale, unit_morale_state(unit).to_str());
+    println!("  experience: {} ({})", unit.experience, unit_veterancy(unit).to_str());
+    println!("  passenger_ids: {:?}", unit.passenger_ids);
     println!("  attached_unit_id: {:?}", unit.attached_unit_id);
     println!("  is_alive: {:?}", unit.is_alive);
     println!("type:");
@@ -40,6 +43,8 @@ pub fn print_unit_info(db: &Db, unit: &Unit) {
     println!("  mp: {}", unit_type.move_points.n);
     println!("  ap: {}", unit_type.attack_points.n);
     println!("  reactive_ap: {}", unit_type.reactive_attack_points.n);
+    println!("  max_morale: {}", unit_type.max_morale);
+    println!("  morale_recovery: {}", unit_type.morale_recovery);
     println!("  los_range: {}", unit_type.los_range.n);
     println!("  cover_los_range: {}", unit_type.cover_los_range.n);
     println!("weapon:");
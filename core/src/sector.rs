@@ -1,7 +1,7 @@
 use std::collections::{HashSet};
 use cgmath::{Vector2};
 use db::{Db};
-use game_state::{State};
+use game_state::{State, ReinforcementPoints};
 use position::{MapPos};
 use event::{CoreEvent};
 use player::{PlayerId};
@@ -13,22 +13,31 @@ pub struct SectorId{pub id: i32}
 pub struct Sector {
     pub owner_id: Option<PlayerId>,
     pub positions: Vec<MapPos>,
+
+    /// Reinforcement points that the owner gets every turn
+    pub income: ReinforcementPoints,
 }
 
 impl Sector {
     pub fn center(&self) -> MapPos {
+        let pos = self.mean_pos();
+        assert!(self.positions.contains(&pos));
+        pos
+    }
+
+    /// Tile nearest to the average position of the sector's tiles.
+    /// Oddly shaped sectors may not contain it.
+    pub fn mean_pos(&self) -> MapPos {
         let mut pos = Vector2{x: 0.0, y: 0.0};
         for sector_pos in &self.positions {
             pos.x += sector_pos.v.x as f32;
             pos.y += sector_pos.v.y as f32;
         }
         pos /= self.positions.len() as f32;
-        let pos = MapPos{v: Vector2{
+        MapPos{v: Vector2{
             x: (pos.x + 0.5) as i32,
             y: (pos.y + 0.5) as i32,
-        }};
-        assert!(self.positions.contains(&pos));
-        pos
+        }}
     }
 }
 
@@ -62,6 +71,7 @@ pub fn check_sectors(db: &Db, state: &State) -> Vec<CoreEvent> {
 #[cfg(test)]
 mod tests {
     use cgmath::{Vector2};
+    use game_state::{ReinforcementPoints};
     use sector::{Sector};
     use position::{MapPos};
 
@@ -78,6 +88,7 @@ mod tests {
                 MapPos{v: Vector2{x: 6, y: 2}},
             ],
             owner_id: None,
+            income: ReinforcementPoints{n: 0},
         }.center();
         let expected = MapPos{v: Vector2{x: 6, y: 1}};
         assert_eq!(expected, real);
@@ -92,6 +103,7 @@ mod tests {
                 MapPos{v: Vector2{x: 6, y: 2}},
             ],
             owner_id: None,
+            income: ReinforcementPoints{n: 0},
         }.center();
         let expected = MapPos{v: Vector2{x: 6, y: 1}};
         assert_eq!(expected, real);
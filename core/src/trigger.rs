@@ -0,0 +1,106 @@
+use game_state::{State, ReinforcementPoints};
+use sector::{SectorId};
+use player::{PlayerId};
+use position::{MapPos};
+use unit;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    /// This game turn has started
+    TurnReached{turn: i32},
+
+    /// Some unit of the player stands in one of these tiles
+    UnitEntersArea{player_id: PlayerId, area: Vec<MapPos>},
+
+    /// The player owns the sector
+    SectorCaptured{sector_id: SectorId, player_id: PlayerId},
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Free units that appear in these tiles (if there's a free slot)
+    SpawnUnits{player_id: PlayerId, units: Vec<(String, MapPos)>},
+
+    /// None if the message is for everyone
+    ShowMessage{player_id: Option<PlayerId>, text: String},
+
+    SetSectorIncome{sector_id: SectorId, income: ReinforcementPoints},
+
+    /// The tiles stay visible to the player until the player's next turn
+    RevealArea{player_id: PlayerId, area: Vec<MapPos>},
+}
+
+/// Scripted scenario event. Every trigger fires only once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trigger {
+    pub condition: Condition,
+    pub actions: Vec<Action>,
+}
+
+pub fn is_triggered(state: &State, condition: &Condition) -> bool {
+    match *condition {
+        Condition::TurnReached{turn} => {
+            state.turn() >= turn
+        },
+        Condition::UnitEntersArea{player_id, ref area} => {
+            state.units().any(|(_, unit)| {
+                unit.player_id == player_id
+                    && unit.is_alive
+                    && !unit::is_loaded_or_attached(unit)
+                    && area.contains(&unit.pos.map_pos)
+            })
+        },
+        Condition::SectorCaptured{sector_id, player_id} => {
+            state.sectors()[&sector_id].owner_id == Some(player_id)
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use player::{PlayerId};
+    use sector::{SectorId};
+    use position::{MapPos};
+    use event::{CoreEvent};
+    use super::{Condition, is_triggered};
+
+    #[test]
+    fn test_turn_and_sector_conditions() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map02".to_owned(),
+            players_count: 2,
+        };
+        let mut state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        let player_id = PlayerId{id: 1};
+        let turn_reached = Condition::TurnReached{turn: 2};
+        let sector_captured = Condition::SectorCaptured {
+            sector_id: SectorId{id: 0},
+            player_id: player_id,
+        };
+        let unit_enters_area = Condition::UnitEntersArea {
+            player_id: player_id,
+            area: vec![MapPos{v: Vector2{x: 4, y: 3}}],
+        };
+        assert!(!is_triggered(&state, &turn_reached));
+        assert!(!is_triggered(&state, &sector_captured));
+        assert!(!is_triggered(&state, &unit_enters_area));
+        state.apply_event(&CoreEvent::EndTurn {
+            old_id: PlayerId{id: 1},
+            new_id: PlayerId{id: 0},
+            turn: 2,
+        });
+        state.apply_event(&CoreEvent::SectorOwnerChanged {
+            sector_id: SectorId{id: 0},
+            new_owner_id: Some(player_id),
+        });
+        assert!(is_triggered(&state, &turn_reached));
+        assert!(is_triggered(&state, &sector_captured));
+        assert!(!is_triggered(&state, &unit_enters_area));
+    }
+}
@@ -5,6 +5,7 @@ use map::{Distance};
 use movement::{MovePoints};
 use attack::{AttackPoints};
 use game_state::{ReinforcementPoints};
+use db::{Db};
 
 #[derive(PartialOrd, Ord, PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub struct UnitId{pub id: i32}
@@ -24,7 +25,8 @@ pub struct Unit {
     pub reaction_fire_mode: ReactionFireMode,
     pub count: i32,
     pub morale: i32,
-    pub passenger_id: Option<UnitId>,
+    pub experience: i32,
+    pub passenger_ids: Vec<UnitId>,
     pub attached_unit_id: Option<UnitId>,
     pub is_alive: bool,
     pub is_loaded: bool,
@@ -41,6 +43,7 @@ pub struct WeaponType {
     pub min_distance: Distance,
     pub max_air_distance: Option<Distance>,
     pub is_inderect: bool,
+    pub blast_radius: Option<Distance>,
     pub reaction_fire: bool,
     pub smoke: Option<i32>,
 }
@@ -60,16 +63,51 @@ pub struct UnitType {
     pub move_points: MovePoints,
     pub attack_points: AttackPoints,
     pub reactive_attack_points: AttackPoints,
+    pub max_morale: i32,
+    pub morale_recovery: i32,
     pub los_range: Distance,
     pub cover_los_range: Distance,
-    pub is_transporter: bool,
+
+    /// Total size of passengers that the unit can carry
+    pub transport_capacity: i32,
+
     pub is_big: bool,
     pub is_air: bool,
     pub is_infantry: bool,
+    pub is_engineer: bool,
     pub can_be_towed: bool,
     pub cost: ReinforcementPoints,
 }
 
+/// Fresh unit with full points that is ready to fight
+pub fn new_unit(
+    db: &Db,
+    id: UnitId,
+    player_id: PlayerId,
+    type_id: UnitTypeId,
+    pos: ExactPos,
+) -> Unit {
+    let unit_type = db.unit_type(type_id);
+    Unit {
+        id: id,
+        player_id: player_id,
+        pos: pos,
+        type_id: type_id,
+        passenger_ids: Vec::new(),
+        attached_unit_id: None,
+        move_points: Some(unit_type.move_points),
+        attack_points: Some(unit_type.attack_points),
+        reactive_attack_points: Some(unit_type.reactive_attack_points),
+        reaction_fire_mode: ReactionFireMode::Normal,
+        count: unit_type.count,
+        morale: unit_type.max_morale,
+        experience: 0,
+        is_alive: true,
+        is_loaded: false,
+        is_attached: false,
+    }
+}
+
 pub fn is_commandable(player_id: PlayerId, unit: &Unit) -> bool {
     unit.is_alive && unit.player_id == player_id
         && !is_loaded_or_attached(unit)
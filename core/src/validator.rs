@@ -0,0 +1,278 @@
+use std::{fmt};
+use std::collections::{HashMap, VecDeque};
+use game_state::{State};
+use map::{Map, Terrain};
+use dir::{Dir, dirs};
+use sector::{SectorId};
+use object::{ObjectClass};
+use player::{PlayerId};
+use position::{self, MapPos, SlotId};
+
+/// Sides' total travel distances to the sectors
+/// may differ by this many percent
+const DISTANCE_TOLERANCE_PERCENT: i32 = 25;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// No ground path from the player's reinforcement sectors
+    UnreachableSector{sector_id: SectorId, player_id: PlayerId},
+
+    /// `Sector::center` would fail on this sector
+    SectorCenterOutside{sector_id: SectorId, center: MapPos},
+
+    /// Buildings share a slot or don't fit into the tile
+    OverlappingBuildings{pos: MapPos},
+
+    ReinforcementSectorOnWater{pos: MapPos},
+
+    /// Sums of the shortest distances (in tiles) from
+    /// the players' reinforcement sectors to all the sectors
+    AsymmetricDistances{distances: Vec<(PlayerId, i32)>},
+}
+
+impl Problem {
+    /// Warnings don't break the game, they only make it unfair
+    pub fn is_error(&self) -> bool {
+        match *self {
+            Problem::AsymmetricDistances{..} => false,
+            _ => true,
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Problem::UnreachableSector{sector_id, player_id} => write!(
+                f, "Sector {} is unreachable for player {}",
+                sector_id.id, player_id.id),
+            Problem::SectorCenterOutside{sector_id, center} => write!(
+                f, "Center {} of sector {} is outside of the sector",
+                center, sector_id.id),
+            Problem::OverlappingBuildings{pos} => write!(
+                f, "Buildings overlap in {}", pos),
+            Problem::ReinforcementSectorOnWater{pos} => write!(
+                f, "Reinforcement sector in {} is on water", pos),
+            Problem::AsymmetricDistances{ref distances} => {
+                write!(f, "Travel distances to the sectors differ:")?;
+                for &(player_id, distance) in distances {
+                    write!(f, " player {}: {}", player_id.id, distance)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+fn is_passable(state: &State, pos: MapPos) -> bool {
+    *state.map().tile(pos) != Terrain::Water
+        || position::is_bridge_at(state, pos)
+}
+
+fn reinforcement_sectors(state: &State, player_id: PlayerId) -> Vec<MapPos> {
+    let mut positions: Vec<_> = state.objects().values()
+        .filter(|object| {
+            object.class == ObjectClass::ReinforcementSector
+                && object.owner_id == Some(player_id)
+        })
+        .map(|object| object.pos.map_pos)
+        .collect();
+    positions.sort_by_key(|pos| (pos.v.y, pos.v.x));
+    positions
+}
+
+/// Ground distances in tiles from the nearest start tile,
+/// None for the tiles that can't be reached
+fn travel_distances(state: &State, start: &[MapPos]) -> Map<Option<i32>> {
+    let map = state.map();
+    let mut distances = Map::new(map.size());
+    let mut queue = VecDeque::new();
+    for &pos in start {
+        if is_passable(state, pos) {
+            *distances.tile_mut(pos) = Some(0);
+            queue.push_back(pos);
+        }
+    }
+    while let Some(pos) = queue.pop_front() {
+        let distance = distances.tile(pos).unwrap();
+        for dir in dirs() {
+            let next_pos = Dir::get_neighbour_pos(pos, dir);
+            if !map.is_inboard(next_pos)
+                || distances.tile(next_pos).is_some()
+                || !is_passable(state, next_pos)
+            {
+                continue;
+            }
+            *distances.tile_mut(next_pos) = Some(distance + 1);
+            queue.push_back(next_pos);
+        }
+    }
+    distances
+}
+
+fn check_sectors(state: &State, problems: &mut Vec<Problem>) {
+    let mut sector_ids: Vec<_> = state.sectors().keys().cloned().collect();
+    sector_ids.sort_by_key(|id| id.id);
+    let mut total_distances = Vec::new();
+    for &player_id in &[PlayerId{id: 0}, PlayerId{id: 1}] {
+        let start = reinforcement_sectors(state, player_id);
+        if start.is_empty() {
+            // test maps without reinforcements
+            continue;
+        }
+        let distances = travel_distances(state, &start);
+        let mut total_distance = 0;
+        for &sector_id in &sector_ids {
+            let sector = &state.sectors()[&sector_id];
+            let distance = sector.positions.iter()
+                .filter_map(|&pos| *distances.tile(pos))
+                .min();
+            match distance {
+                Some(distance) => total_distance += distance,
+                None => problems.push(Problem::UnreachableSector {
+                    sector_id: sector_id,
+                    player_id: player_id,
+                }),
+            }
+        }
+        total_distances.push((player_id, total_distance));
+    }
+    if total_distances.len() == 2 {
+        let a = total_distances[0].1;
+        let b = total_distances[1].1;
+        let max = if a > b { a } else { b };
+        if (a - b).abs() * 100 > max * DISTANCE_TOLERANCE_PERCENT {
+            problems.push(Problem::AsymmetricDistances {
+                distances: total_distances,
+            });
+        }
+    }
+    for &sector_id in &sector_ids {
+        let sector = &state.sectors()[&sector_id];
+        let center = sector.mean_pos();
+        if !sector.positions.contains(&center) {
+            problems.push(Problem::SectorCenterOutside {
+                sector_id: sector_id,
+                center: center,
+            });
+        }
+    }
+}
+
+fn check_objects(state: &State, problems: &mut Vec<Problem>) {
+    let map = state.map();
+    let mut ids: Vec<_> = state.objects().keys().cloned().collect();
+    ids.sort();
+    let mut building_slots: HashMap<MapPos, Vec<SlotId>> = HashMap::new();
+    for id in ids {
+        let object = &state.objects()[&id];
+        let pos = object.pos.map_pos;
+        match object.class {
+            ObjectClass::Building => {
+                building_slots.entry(pos).or_insert_with(Vec::new)
+                    .push(object.pos.slot_id);
+            },
+            ObjectClass::ReinforcementSector => {
+                if *map.tile(pos) == Terrain::Water {
+                    problems.push(Problem::ReinforcementSectorOnWater {
+                        pos: pos,
+                    });
+                }
+            },
+            ObjectClass::Road |
+            ObjectClass::Smoke |
+            ObjectClass::Bridge |
+            ObjectClass::Trench |
+            ObjectClass::Minefield => {},
+        }
+    }
+    let mut positions: Vec<_> = building_slots.keys().cloned().collect();
+    positions.sort_by_key(|pos| (pos.v.y, pos.v.x));
+    for pos in positions {
+        let slots = &building_slots[&pos];
+        let slots_count = position::get_slots_count(map, pos);
+        let is_overlapping = slots.iter().enumerate().any(|(i, slot_id)| {
+            let is_duplicate = slots[..i].contains(slot_id);
+            let is_out = match *slot_id {
+                SlotId::Id(n) => n as i32 >= slots_count,
+                SlotId::WholeTile => slots.len() > 1,
+                SlotId::TwoTiles(_) | SlotId::Air => true,
+            };
+            is_duplicate || is_out
+        });
+        if is_overlapping {
+            problems.push(Problem::OverlappingBuildings{pos: pos});
+        }
+    }
+}
+
+/// Finds mistakes in the map of a freshly loaded full state.
+/// Mistakes that break the loading itself are `LoadMapError`s.
+pub fn validate(state: &State) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    check_sectors(state, &mut problems);
+    check_objects(state, &mut problems);
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use db::{Db};
+    use game_state::{self, State, LoadMapError};
+    use options::{Options, GameType};
+    use mapgen::{MapGenOptions};
+    use types::{Size2};
+    use super::{validate};
+
+    fn load_state(map_name: &str) -> State {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: map_name.to_owned(),
+            players_count: 2,
+        };
+        State::new_full(Rc::new(Db::new()), &options)
+            .unwrap_or_else(|err| panic!("{}: {}", map_name, err))
+    }
+
+    #[test]
+    fn test_shipped_maps_have_no_errors() {
+        for &map_name in game_state::MAP_NAMES {
+            let state = load_state(map_name);
+            for problem in validate(&state) {
+                assert!(!problem.is_error(), "{}: {}", map_name, problem);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generated_maps_are_fair() {
+        for seed in 0 .. 10 {
+            let map_name = MapGenOptions {
+                seed: seed,
+                size: Size2{w: 16, h: 16},
+                density: 30,
+            }.to_map_name();
+            let problems = validate(&load_state(&map_name));
+            assert!(problems.is_empty(), "{}: {:?}", map_name, problems);
+        }
+    }
+
+    #[test]
+    fn test_broken_maps_are_load_errors() {
+        let options = |map_name: &str| Options {
+            game_type: GameType::Hotseat,
+            map_name: map_name.to_owned(),
+            players_count: 2,
+        };
+        let db = Rc::new(Db::new());
+        match State::new_full(db.clone(), &options("no_such_map")) {
+            Err(LoadMapError::UnknownMapName) => {},
+            _ => panic!("Expected an unknown map name error"),
+        }
+        match State::new_full(db.clone(), &options("no_such_file.map")) {
+            Err(LoadMapError::BadMapFile(_)) => {},
+            _ => panic!("Expected a bad map file error"),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use unit::{Unit};
+use event::{AttackInfo};
+
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+pub enum Veterancy {
+    Green,
+    Regular,
+    Veteran,
+    Elite,
+}
+
+impl Veterancy {
+    pub fn to_str(&self) -> &str {
+        match *self {
+            Veterancy::Green => "green",
+            Veterancy::Regular => "regular",
+            Veterancy::Veteran => "veteran",
+            Veterancy::Elite => "elite",
+        }
+    }
+
+    pub fn weapon_skill_bonus(&self) -> i32 {
+        match *self {
+            Veterancy::Green | Veterancy::Regular => 0,
+            Veterancy::Veteran => 1,
+            Veterancy::Elite => 2,
+        }
+    }
+
+    pub fn morale_recovery_bonus(&self) -> i32 {
+        match *self {
+            Veterancy::Green => 0,
+            Veterancy::Regular => 2,
+            Veterancy::Veteran | Veterancy::Elite => 5,
+        }
+    }
+
+    pub fn reaction_fire_bonus(&self) -> i32 {
+        match *self {
+            Veterancy::Green | Veterancy::Regular => 0,
+            Veterancy::Veteran => 1,
+            Veterancy::Elite => 2,
+        }
+    }
+}
+
+// TODO: get from config
+const EXPERIENCE_PER_KILL: i32 = 2;
+const EXPERIENCE_FOR_DESTROYED_UNIT: i32 = 5;
+const EXPERIENCE_FOR_SURVIVED_ATTACK: i32 = 1;
+
+pub fn veterancy(experience: i32) -> Veterancy {
+    if experience >= 60 {
+        Veterancy::Elite
+    } else if experience >= 30 {
+        Veterancy::Veteran
+    } else if experience >= 10 {
+        Veterancy::Regular
+    } else {
+        Veterancy::Green
+    }
+}
+
+pub fn unit_veterancy(unit: &Unit) -> Veterancy {
+    veterancy(unit.experience)
+}
+
+pub fn attacker_experience(attack_info: &AttackInfo, defender: &Unit) -> i32 {
+    let mut experience = attack_info.killed * EXPERIENCE_PER_KILL;
+    if defender.count - attack_info.killed <= 0 {
+        experience += EXPERIENCE_FOR_DESTROYED_UNIT;
+    }
+    experience
+}
+
+pub fn defender_experience(attack_info: &AttackInfo, defender: &Unit) -> i32 {
+    if defender.count - attack_info.killed > 0 {
+        EXPERIENCE_FOR_SURVIVED_ATTACK
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use veterancy::{Veterancy, veterancy};
+
+    #[test]
+    fn test_veterancy_thresholds() {
+        assert_eq!(veterancy(0), Veterancy::Green);
+        assert_eq!(veterancy(9), Veterancy::Green);
+        assert_eq!(veterancy(10), Veterancy::Regular);
+        assert_eq!(veterancy(30), Veterancy::Veteran);
+        assert_eq!(veterancy(59), Veterancy::Veteran);
+        assert_eq!(veterancy(60), Veterancy::Elite);
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::{HashMap};
+use game_state::{State};
+use sector::{Sector, SectorId};
+use player::{PlayerId};
+use unit::{UnitId};
+use event::{CoreEvent};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Condition {
+    /// Get `State::target_score` victory points
+    TargetScore,
+
+    /// Own all these sectors at the end of this many own turns in a row
+    HoldSectors{sector_ids: Vec<SectorId>, turns: i32},
+
+    /// Destroy this enemy unit
+    DestroyUnit{unit_id: UnitId},
+
+    /// Still be in the game after this many own turns
+    Survive{turns: i32},
+
+    /// Destroy all enemy units that are on the map
+    EliminateEnemies,
+}
+
+/// Scenario-defined way for a player to win the game
+#[derive(Clone, Debug, PartialEq)]
+pub struct Objective {
+    pub player_id: PlayerId,
+    pub condition: Condition,
+
+    /// Own turns in a row that the condition has held
+    /// for (`HoldSectors`) or simply own turns (`Survive`)
+    pub turns: i32,
+}
+
+impl Objective {
+    pub fn new(player_id: PlayerId, condition: Condition) -> Objective {
+        Objective {
+            player_id: player_id,
+            condition: condition,
+            turns: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameOverReason {
+    TargetScore,
+    SectorsHeld,
+    UnitDestroyed,
+    Survived,
+    EnemiesEliminated,
+    TurnLimit,
+}
+
+impl GameOverReason {
+    pub fn to_str(&self) -> &str {
+        match *self {
+            GameOverReason::TargetScore => "target score reached",
+            GameOverReason::SectorsHeld => "sectors held",
+            GameOverReason::UnitDestroyed => "enemy unit destroyed",
+            GameOverReason::Survived => "survived",
+            GameOverReason::EnemiesEliminated => "enemies eliminated",
+            GameOverReason::TurnLimit => "turn limit reached",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GameResult {
+    /// None in case of a draw
+    pub winner_id: Option<PlayerId>,
+
+    pub reason: GameOverReason,
+
+    /// The last played turn
+    pub turn: i32,
+}
+
+pub fn are_sectors_held(
+    sectors: &HashMap<SectorId, Sector>,
+    player_id: PlayerId,
+    sector_ids: &[SectorId],
+) -> bool {
+    sector_ids.iter().all(|id| sectors[id].owner_id == Some(player_id))
+}
+
+fn has_alive_units(state: &State, player_id: PlayerId) -> bool {
+    state.units().any(|(_, unit)| {
+        unit.player_id == player_id && unit.is_alive
+    })
+}
+
+/// Is the condition fulfilled by this (already applied) event
+fn is_achieved(
+    state: &State,
+    objective: &Objective,
+    event: &CoreEvent,
+) -> bool {
+    let player_id = objective.player_id;
+    match (&objective.condition, event) {
+        (&Condition::TargetScore, &CoreEvent::VictoryPoint{..}) => {
+            state.score()[&player_id] >= state.target_score()
+        },
+        (&Condition::HoldSectors{turns, ..}, &CoreEvent::EndTurn{old_id, ..}) |
+        (&Condition::Survive{turns}, &CoreEvent::EndTurn{old_id, ..}) => {
+            old_id == player_id && objective.turns >= turns
+        },
+        (&Condition::DestroyUnit{unit_id}, &CoreEvent::AttackUnit{ref attack_info}) => {
+            let defender = state.unit_or_killed_unit(attack_info.defender_id);
+            let is_destroyed = state.unit_opt(defender.id)
+                .map_or(true, |unit| !unit.is_alive);
+            defender.id == unit_id
+                && defender.player_id != player_id
+                && is_destroyed
+        },
+        (&Condition::EliminateEnemies, &CoreEvent::AttackUnit{ref attack_info}) => {
+            let defender = state.unit_or_killed_unit(attack_info.defender_id);
+            defender.player_id != player_id
+                && !has_alive_units(state, defender.player_id)
+        },
+        _ => false,
+    }
+}
+
+fn reason(condition: &Condition) -> GameOverReason {
+    match *condition {
+        Condition::TargetScore => GameOverReason::TargetScore,
+        Condition::HoldSectors{..} => GameOverReason::SectorsHeld,
+        Condition::DestroyUnit{..} => GameOverReason::UnitDestroyed,
+        Condition::Survive{..} => GameOverReason::Survived,
+        Condition::EliminateEnemies => GameOverReason::EnemiesEliminated,
+    }
+}
+
+/// `EndTurn` event may have already started the next turn
+fn last_played_turn(state: &State, event: &CoreEvent) -> i32 {
+    match *event {
+        CoreEvent::EndTurn{turn, new_id, ..} if new_id == PlayerId{id: 0} => turn - 1,
+        _ => state.turn(),
+    }
+}
+
+/// Player with the best score wins when the time is out
+fn best_score_player_id(state: &State) -> Option<PlayerId> {
+    let mut scores: Vec<_> = state.score().iter()
+        .map(|(&id, &score)| (score, id))
+        .collect();
+    scores.sort_by(|a, b| b.0.cmp(&a.0));
+    if scores.len() > 1 && scores[0].0 == scores[1].0 {
+        None
+    } else {
+        scores.first().map(|&(_, id)| id)
+    }
+}
+
+/// Returns `GameOver` event if the last event has fulfilled some
+/// objective or if the last turn is over
+pub fn check_objectives(
+    state: &State,
+    event: &CoreEvent,
+) -> Option<CoreEvent> {
+    if state.game_result().is_some() {
+        return None;
+    }
+    let turn = last_played_turn(state, event);
+    for objective in state.objectives() {
+        if is_achieved(state, objective, event) {
+            return Some(CoreEvent::GameOver {
+                result: GameResult {
+                    winner_id: Some(objective.player_id),
+                    reason: reason(&objective.condition),
+                    turn: turn,
+                },
+            });
+        }
+    }
+    if let CoreEvent::EndTurn{turn, ..} = *event {
+        if let Some(turn_limit) = state.turn_limit() {
+            if turn > turn_limit {
+                return Some(CoreEvent::GameOver {
+                    result: GameResult {
+                        winner_id: best_score_player_id(state),
+                        reason: GameOverReason::TurnLimit,
+                        turn: turn_limit,
+                    },
+                });
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::{Rc};
+    use cgmath::{Vector2};
+    use db::{Db};
+    use game_state::{State};
+    use options::{Options, GameType};
+    use player::{PlayerId};
+    use position::{MapPos, ExactPos, SlotId};
+    use sector::{SectorId};
+    use unit::{UnitId, new_unit};
+    use event::{CoreEvent, AttackInfo, FireMode};
+    use super::{
+        Objective,
+        Condition,
+        GameResult,
+        GameOverReason,
+        check_objectives,
+        is_achieved,
+    };
+
+    fn end_turn(state: &mut State, old_id: i32, new_id: i32) -> Option<CoreEvent> {
+        let new_id = PlayerId{id: new_id};
+        let event = CoreEvent::EndTurn {
+            old_id: PlayerId{id: old_id},
+            new_id: new_id,
+            turn: state.next_turn(new_id),
+        };
+        state.apply_event(&event);
+        check_objectives(state, &event)
+    }
+
+    fn set_sector_owner(state: &mut State, sector_id: i32, owner_id: Option<PlayerId>) {
+        state.apply_event(&CoreEvent::SectorOwnerChanged {
+            sector_id: SectorId{id: sector_id},
+            new_owner_id: owner_id,
+        });
+    }
+
+    #[test]
+    fn test_sectors_must_be_held_for_turns() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map06".to_owned(),
+            players_count: 2,
+        };
+        let mut state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        let player_id = PlayerId{id: 0};
+        set_sector_owner(&mut state, 0, Some(player_id));
+        set_sector_owner(&mut state, 1, Some(player_id));
+        for _ in 0..2 {
+            assert!(end_turn(&mut state, 0, 1).is_none());
+            assert!(end_turn(&mut state, 1, 0).is_none());
+        }
+        // losing a sector resets the counter
+        set_sector_owner(&mut state, 1, None);
+        assert!(end_turn(&mut state, 0, 1).is_none());
+        assert!(end_turn(&mut state, 1, 0).is_none());
+        set_sector_owner(&mut state, 1, Some(player_id));
+        for _ in 0..2 {
+            assert!(end_turn(&mut state, 0, 1).is_none());
+            assert!(end_turn(&mut state, 1, 0).is_none());
+        }
+        let event = end_turn(&mut state, 0, 1);
+        let expected_result = GameResult {
+            winner_id: Some(player_id),
+            reason: GameOverReason::SectorsHeld,
+            turn: 6,
+        };
+        match event {
+            Some(CoreEvent::GameOver{result}) => assert_eq!(result, expected_result),
+            _ => panic!("Expected GameOver, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn test_turn_limit() {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map06".to_owned(),
+            players_count: 2,
+        };
+        let mut state = State::new_full(Rc::new(Db::new()), &options).unwrap();
+        let turn_limit = state.turn_limit().unwrap();
+        state.apply_event(&CoreEvent::VictoryPoint {
+            player_id: PlayerId{id: 1},
+            pos: MapPos{v: Vector2{x: 0, y: 0}},
+            count: 1,
+        });
+        for _ in 0..turn_limit - 1 {
+            assert!(end_turn(&mut state, 0, 1).is_none());
+            assert!(end_turn(&mut state, 1, 0).is_none());
+        }
+        assert!(end_turn(&mut state, 0, 1).is_none());
+        let event = end_turn(&mut state, 1, 0);
+        let expected_result = GameResult {
+            winner_id: Some(PlayerId{id: 1}),
+            reason: GameOverReason::TurnLimit,
+            turn: turn_limit,
+        };
+        match event {
+            Some(CoreEvent::GameOver{result}) => assert_eq!(result, expected_result),
+            _ => panic!("Expected GameOver, got {:?}", event),
+        }
+    }
+
+    fn new_state() -> State {
+        let options = Options {
+            game_type: GameType::Hotseat,
+            map_name: "map01".to_owned(),
+            players_count: 2,
+        };
+        State::new_full(Rc::new(Db::new()), &options).unwrap()
+    }
+
+    fn create_unit(state: &mut State, id: i32, player_id: i32, x: i32) -> UnitId {
+        let id = UnitId{id: id};
+        let type_id = state.db().unit_type_id("soldier");
+        let pos = ExactPos {
+            map_pos: MapPos{v: Vector2{x: x, y: 0}},
+            slot_id: SlotId::Id(0),
+        };
+        let unit = new_unit(state.db(), id, PlayerId{id: player_id}, type_id, pos);
+        let cost = state.db().unit_type(type_id).cost;
+        state.apply_event(&CoreEvent::CreateUnit {
+            unit_info: unit,
+            cost: cost,
+        });
+        id
+    }
+
+    /// Mine-like attack without an attacker that kills `killed` soldiers
+    fn attack(state: &mut State, defender_id: UnitId, killed: i32) -> CoreEvent {
+        let event = CoreEvent::AttackUnit {
+            attack_info: AttackInfo {
+                attacker_id: None,
+                defender_id: defender_id,
+                mode: FireMode::Reactive,
+                killed: killed,
+                suppression: 0,
+                remove_move_points: false,
+                is_ambush: false,
+                is_inderect: false,
+                is_scattered: false,
+                leave_wrecks: false,
+                is_splash: false,
+            },
+        };
+        state.apply_event(&event);
+        event
+    }
+
+    #[test]
+    fn test_destroy_unit_needs_that_very_unit() {
+        let mut state = new_state();
+        let target_id = create_unit(&mut state, 0, 1, 0);
+        let other_id = create_unit(&mut state, 1, 1, 1);
+        let objective = Objective::new(
+            PlayerId{id: 0}, Condition::DestroyUnit{unit_id: target_id});
+        let count = state.unit(other_id).count;
+        let event = attack(&mut state, other_id, count);
+        assert!(!is_achieved(&state, &objective, &event));
+        let event = attack(&mut state, target_id, 1);
+        assert!(!is_achieved(&state, &objective, &event));
+        let count = state.unit(target_id).count;
+        let event = attack(&mut state, target_id, count);
+        assert!(is_achieved(&state, &objective, &event));
+        // own target can't be destroyed to win
+        let objective = Objective::new(
+            PlayerId{id: 1}, Condition::DestroyUnit{unit_id: target_id});
+        assert!(!is_achieved(&state, &objective, &event));
+    }
+
+    #[test]
+    fn test_eliminate_enemies_needs_every_enemy_unit() {
+        let mut state = new_state();
+        let first_id = create_unit(&mut state, 0, 1, 0);
+        let second_id = create_unit(&mut state, 1, 1, 1);
+        create_unit(&mut state, 2, 0, 2);
+        let objective = Objective::new(
+            PlayerId{id: 0}, Condition::EliminateEnemies);
+        let count = state.unit(first_id).count;
+        let event = attack(&mut state, first_id, count);
+        assert!(!is_achieved(&state, &objective, &event));
+        let count = state.unit(second_id).count;
+        let event = attack(&mut state, second_id, count);
+        assert!(is_achieved(&state, &objective, &event));
+        let objective = Objective::new(
+            PlayerId{id: 1}, Condition::EliminateEnemies);
+        assert!(!is_achieved(&state, &objective, &event));
+    }
+}
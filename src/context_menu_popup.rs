@@ -12,7 +12,7 @@ use core::db::{Db};
 use core::check::{check_command};
 use core::attack;
 use core::event::Command as CoreCommand;
-use core::event::{ReactionFireMode, MoveMode};
+use core::event::{ReactionFireMode, MoveMode, FireMode};
 use types::{Time, ScreenPos};
 use screen::{Screen, ScreenCommand, EventStatus};
 use context::{Context};
@@ -20,34 +20,32 @@ use gui::{ButtonManager, Button, ButtonId, is_tap, basic_text_size};
 use player_info::{PlayerInfo};
 use reinforcements_popup;
 
-fn can_unload_unit(
+fn can_unload_units(
     db: &Db,
     state: &State,
     transporter_id: UnitId,
     pos: MapPos,
-) -> Option<ExactPos> {
+) -> Vec<(UnitId, ExactPos)> {
     let transporter = state.unit(transporter_id);
-    let passenger_id = match transporter.passenger_id {
-        Some(id) => id,
-        None => return None,
-    };
-    let type_id = state.unit(passenger_id).type_id;
-    let unit_type = db.unit_type(type_id);
-    let exact_pos = match position::get_free_exact_pos(state, unit_type, pos) {
-        Some(pos) => pos,
-        None => return None,
-    };
     let player_id = transporter.player_id;
-    let command = CoreCommand::UnloadUnit {
-        transporter_id: transporter_id,
-        passenger_id: passenger_id,
-        pos: exact_pos,
-    };
-    if check_command(db, player_id, state, &command).is_ok() {
-        Some(exact_pos)
-    } else {
-        None
+    let mut unloads = Vec::new();
+    for &passenger_id in &transporter.passenger_ids {
+        let type_id = state.unit(passenger_id).type_id;
+        let unit_type = db.unit_type(type_id);
+        let exact_pos = match position::get_free_exact_pos(state, unit_type, pos) {
+            Some(pos) => pos,
+            None => continue,
+        };
+        let command = CoreCommand::UnloadUnit {
+            transporter_id: transporter_id,
+            passenger_id: passenger_id,
+            pos: exact_pos,
+        };
+        if check_command(db, player_id, state, &command).is_ok() {
+            unloads.push((passenger_id, exact_pos));
+        }
     }
+    unloads
 }
 
 fn can_detach_unit(
@@ -89,18 +87,20 @@ pub fn get_options(
     let mut options = Options::new();
     let player_id = core.player_id();
     let unit_ids = position::get_unit_ids_at(state, pos);
-    for object in state.objects_at(pos) {
-        if object.class != ObjectClass::ReinforcementSector
-            || object.owner_id != Some(player_id)
-        {
-            continue;
-        }
+    let is_reinforcement_pos = if state.is_deployment_phase() {
+        state.deployment_zone(player_id).contains(&pos)
+    } else {
+        state.objects_at(pos).any(|object| {
+            object.class == ObjectClass::ReinforcementSector
+                && object.owner_id == Some(player_id)
+        })
+    };
+    if is_reinforcement_pos {
         let reinforcement_options = reinforcements_popup::get_options(
             db, state, player_id, pos);
-        if reinforcement_options == reinforcements_popup::Options::new() {
-            continue;
+        if reinforcement_options != reinforcements_popup::Options::new() {
+            options.reinforcements_pos = Some(pos);
         }
-        options.reinforcements_pos = Some(pos);
     }
     let selected_unit_id = match selected_unit_id {
         Some(id) => id,
@@ -150,7 +150,8 @@ pub fn get_options(
         } else {
             let attacker = state.unit(selected_unit_id);
             let defender = state.unit(unit_id);
-            let hit_chance = attack::hit_chance(db, state, attacker, defender);
+            let hit_chance = attack::hit_chance(
+                db, state, attacker, defender, FireMode::Active);
             let attack_command = CoreCommand::AttackUnit {
                 attacker_id: attacker.id,
                 defender_id: defender.id,
@@ -166,9 +167,31 @@ pub fn get_options(
     }).is_ok() {
         options.smoke_pos = Some(pos);
     }
-    if let Some(pos) = can_unload_unit(db, state, selected_unit_id, pos) {
-        options.unload_pos = Some(pos);
+    if check_command(db, player_id, state, &CoreCommand::BuildBridge {
+        unit_id: selected_unit_id,
+        pos: pos,
+    }).is_ok() {
+        options.build_bridge_pos = Some(pos);
+    }
+    if check_command(db, player_id, state, &CoreCommand::DigTrench {
+        unit_id: selected_unit_id,
+        pos: pos,
+    }).is_ok() {
+        options.dig_trench_pos = Some(pos);
     }
+    if check_command(db, player_id, state, &CoreCommand::LayMines {
+        unit_id: selected_unit_id,
+        pos: pos,
+    }).is_ok() {
+        options.lay_mines_pos = Some(pos);
+    }
+    if check_command(db, player_id, state, &CoreCommand::Clear {
+        unit_id: selected_unit_id,
+        pos: pos,
+    }).is_ok() {
+        options.clear_pos = Some(pos);
+    }
+    options.unloads = can_unload_units(db, state, selected_unit_id, pos);
     if let Some(pos) = can_detach_unit(db, state, selected_unit_id, pos) {
         options.detach_pos = Some(pos);
     }
@@ -208,11 +231,15 @@ pub enum Command {
     Attack{id: UnitId},
     LoadUnit{passenger_id: UnitId},
     Attach{attached_unit_id: UnitId},
-    UnloadUnit{pos: ExactPos},
+    UnloadUnit{passenger_id: UnitId, pos: ExactPos},
     Detach{pos: ExactPos},
     EnableReactionFire{id: UnitId},
     DisableReactionFire{id: UnitId},
     Smoke{pos: MapPos},
+    BuildBridge{pos: MapPos},
+    DigTrench{pos: MapPos},
+    LayMines{pos: MapPos},
+    Clear{pos: MapPos},
     CallReiforcements{pos: MapPos},
 }
 
@@ -224,9 +251,13 @@ pub struct Options {
     attaches: Vec<UnitId>,
     move_pos: Option<ExactPos>,
     hunt_pos: Option<ExactPos>,
-    unload_pos: Option<ExactPos>,
+    unloads: Vec<(UnitId, ExactPos)>,
     detach_pos: Option<ExactPos>,
     smoke_pos: Option<MapPos>,
+    build_bridge_pos: Option<MapPos>,
+    dig_trench_pos: Option<MapPos>,
+    lay_mines_pos: Option<MapPos>,
+    clear_pos: Option<MapPos>,
     enable_reaction_fire: Option<UnitId>,
     disable_reaction_fire: Option<UnitId>,
     reinforcements_pos: Option<MapPos>,
@@ -241,9 +272,13 @@ impl Options {
             attaches: Vec::new(),
             move_pos: None,
             hunt_pos: None,
-            unload_pos: None,
+            unloads: Vec::new(),
             detach_pos: None,
             smoke_pos: None,
+            build_bridge_pos: None,
+            dig_trench_pos: None,
+            lay_mines_pos: None,
+            clear_pos: None,
             enable_reaction_fire: None,
             disable_reaction_fire: None,
             reinforcements_pos: None,
@@ -266,11 +301,15 @@ pub struct ContextMenuPopup {
     attack_button_ids: HashMap<ButtonId, UnitId>,
     load_button_ids: HashMap<ButtonId, UnitId>,
     attach_button_ids: HashMap<ButtonId, UnitId>,
+    unload_button_ids: HashMap<ButtonId, (UnitId, ExactPos)>,
     move_button_id: Option<ButtonId>,
     hunt_button_id: Option<ButtonId>,
-    unload_unit_button_id: Option<ButtonId>,
     detach_button_id: Option<ButtonId>,
     smoke_button_id: Option<ButtonId>,
+    build_bridge_button_id: Option<ButtonId>,
+    dig_trench_button_id: Option<ButtonId>,
+    lay_mines_button_id: Option<ButtonId>,
+    clear_button_id: Option<ButtonId>,
     enable_reaction_fire_button_id: Option<ButtonId>,
     disable_reaction_fire_button_id: Option<ButtonId>,
     call_reinforcements_button_id: Option<ButtonId>,
@@ -290,11 +329,15 @@ impl ContextMenuPopup {
         let mut attack_button_ids = HashMap::new();
         let mut load_button_ids = HashMap::new();
         let mut attach_button_ids = HashMap::new();
+        let mut unload_button_ids = HashMap::new();
         let mut move_button_id = None;
         let mut hunt_button_id = None;
-        let mut unload_unit_button_id = None;
         let mut detach_button_id = None;
         let mut smoke_button_id = None;
+        let mut build_bridge_button_id = None;
+        let mut dig_trench_button_id = None;
+        let mut lay_mines_button_id = None;
+        let mut clear_button_id = None;
         let mut enable_reaction_fire_button_id = None;
         let mut disable_reaction_fire_button_id = None;
         let mut call_reinforcements_button_id = None;
@@ -357,9 +400,11 @@ impl ContextMenuPopup {
                 Button::new(context, "[reinforcements]", pos)));
             pos.v.y -= vstep;
         }
-        if options.unload_pos.is_some() {
-            unload_unit_button_id = Some(button_manager.add_button(
-                Button::new(context, "[unload]", pos)));
+        for &(unit_id, unload_pos) in &options.unloads {
+            let unit_type = db.unit_type(state.unit(unit_id).type_id);
+            let button_id = button_manager.add_button(
+                Button::new(context, &format!("[unload <{}>]", unit_type.name), pos));
+            unload_button_ids.insert(button_id, (unit_id, unload_pos));
             pos.v.y -= vstep;
         }
         if options.detach_pos.is_some() {
@@ -372,6 +417,26 @@ impl ContextMenuPopup {
                 Button::new(context, "[smoke]", pos)));
             pos.v.y -= vstep;
         }
+        if options.build_bridge_pos.is_some() {
+            build_bridge_button_id = Some(button_manager.add_button(
+                Button::new(context, "[build bridge]", pos)));
+            pos.v.y -= vstep;
+        }
+        if options.dig_trench_pos.is_some() {
+            dig_trench_button_id = Some(button_manager.add_button(
+                Button::new(context, "[dig trench]", pos)));
+            pos.v.y -= vstep;
+        }
+        if options.lay_mines_pos.is_some() {
+            lay_mines_button_id = Some(button_manager.add_button(
+                Button::new(context, "[lay mines]", pos)));
+            pos.v.y -= vstep;
+        }
+        if options.clear_pos.is_some() {
+            clear_button_id = Some(button_manager.add_button(
+                Button::new(context, "[clear]", pos)));
+            pos.v.y -= vstep;
+        }
         let diff = Vector2 {
             x: pos.v.x + max_width(&button_manager) - context.win_size().w,
             y: pos.v.y + vstep,
@@ -391,11 +456,15 @@ impl ContextMenuPopup {
             attack_button_ids: attack_button_ids,
             load_button_ids: load_button_ids,
             attach_button_ids: attach_button_ids,
+            unload_button_ids: unload_button_ids,
             move_button_id: move_button_id,
             hunt_button_id: hunt_button_id,
-            unload_unit_button_id: unload_unit_button_id,
             detach_button_id: detach_button_id,
             smoke_button_id: smoke_button_id,
+            build_bridge_button_id: build_bridge_button_id,
+            dig_trench_button_id: dig_trench_button_id,
+            lay_mines_button_id: lay_mines_button_id,
+            clear_button_id: clear_button_id,
             enable_reaction_fire_button_id: enable_reaction_fire_button_id,
             disable_reaction_fire_button_id: disable_reaction_fire_button_id,
             call_reinforcements_button_id: call_reinforcements_button_id,
@@ -448,6 +517,13 @@ impl ContextMenuPopup {
             });
             return;
         }
+        if let Some(&(unit_id, pos)) = self.unload_button_ids.get(&button_id) {
+            self.return_command(context, Command::UnloadUnit {
+                passenger_id: unit_id,
+                pos: pos,
+            });
+            return;
+        }
         let id = Some(button_id);
         if id == self.move_button_id {
             self.return_command(context, Command::Move {
@@ -457,10 +533,6 @@ impl ContextMenuPopup {
             self.return_command(context, Command::Hunt {
                 pos: self.options.move_pos.unwrap(),
             });
-        } else if id == self.unload_unit_button_id {
-            self.return_command(context, Command::UnloadUnit {
-                pos: self.options.unload_pos.unwrap(),
-            });
         } else if id == self.detach_button_id {
             self.return_command(context, Command::Detach {
                 pos: self.options.detach_pos.unwrap(),
@@ -469,6 +541,22 @@ impl ContextMenuPopup {
             self.return_command(context, Command::Smoke {
                 pos: self.options.smoke_pos.unwrap(),
             });
+        } else if id == self.build_bridge_button_id {
+            self.return_command(context, Command::BuildBridge {
+                pos: self.options.build_bridge_pos.unwrap(),
+            });
+        } else if id == self.dig_trench_button_id {
+            self.return_command(context, Command::DigTrench {
+                pos: self.options.dig_trench_pos.unwrap(),
+            });
+        } else if id == self.lay_mines_button_id {
+            self.return_command(context, Command::LayMines {
+                pos: self.options.lay_mines_pos.unwrap(),
+            });
+        } else if id == self.clear_button_id {
+            self.return_command(context, Command::Clear {
+                pos: self.options.clear_pos.unwrap(),
+            });
         } else if id == self.enable_reaction_fire_button_id {
             self.return_command(context, Command::EnableReactionFire {
                 id: self.options.enable_reaction_fire.unwrap(),
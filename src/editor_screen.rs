@@ -0,0 +1,404 @@
+use std::rc::{Rc};
+use std::f32::consts::{PI};
+use cgmath::{self, Vector2, Rad};
+use glutin::{self, VirtualKeyCode, Event, MouseButton};
+use glutin::ElementState::{Released};
+use core::db::{Db};
+use core::game_state::{State};
+use core::map::{Terrain};
+use core::position::{MapPos};
+use core::sector::{SectorId};
+use core::map_file::{self, MapFile};
+use screen::{Screen, ScreenCommand, EventStatus};
+use context::{Context};
+use gui::{ButtonManager, Button, ButtonId, is_tap};
+use scene::{Scene, SceneNode};
+use mesh_manager::{MeshIdManager, MeshManager};
+use message_screen::{MessageScreen};
+use texture::{Texture, load_texture};
+use tactical_screen::{make_scene};
+use player_info::{get_initial_camera_pos, get_max_camera_pos};
+use camera::{Camera};
+use types::{Time, ScreenPos};
+use geom;
+use gen;
+use pick;
+use fs;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tool {
+    Terrain(Terrain),
+    Building,
+    BigBuilding,
+    Road,
+    Sector,
+    ReinforcementSector,
+    Eraser,
+}
+
+impl Tool {
+    fn to_str(&self) -> &str {
+        match *self {
+            Tool::Terrain(Terrain::Plain) => "plain",
+            Tool::Terrain(Terrain::Trees) => "trees",
+            Tool::Terrain(Terrain::Water) => "water",
+            Tool::Terrain(Terrain::City) => "city",
+            Tool::Building => "building",
+            Tool::BigBuilding => "big building",
+            Tool::Road => "road",
+            Tool::Sector => "sector",
+            Tool::ReinforcementSector => "reinforcement sector",
+            Tool::Eraser => "erase",
+        }
+    }
+}
+
+const TOOLS: &'static [Tool] = &[
+    Tool::Terrain(Terrain::Plain),
+    Tool::Terrain(Terrain::Trees),
+    Tool::Terrain(Terrain::Water),
+    Tool::Building,
+    Tool::BigBuilding,
+    Tool::Road,
+    Tool::Sector,
+    Tool::ReinforcementSector,
+    Tool::Eraser,
+];
+
+pub struct EditorScreen {
+    db: Rc<Db>,
+    map_file: MapFile,
+
+    /// Full state of the edited map that is drawn and picked.
+    /// Rebuilt after every change.
+    state: State,
+
+    camera: Camera,
+    scene: Scene,
+    mesh_ids: MeshIdManager,
+    meshes: MeshManager,
+    floor_tex: Texture,
+    sector_tex: Texture,
+    button_manager: ButtonManager,
+    tool_button_ids: Vec<(ButtonId, Tool)>,
+    button_new_sector_id: ButtonId,
+    button_score_up_id: ButtonId,
+    button_score_down_id: ButtonId,
+    button_save_id: ButtonId,
+    label_info_id: ButtonId,
+    tool: Tool,
+
+    /// Sector that gets the tiles painted with `Tool::Sector`
+    sector_id: SectorId,
+
+    /// The last tile of the road that is being drawn
+    road_end: Option<MapPos>,
+}
+
+impl EditorScreen {
+    pub fn new(context: &mut Context, map_file: MapFile) -> EditorScreen {
+        let db = Rc::new(Db::new());
+        let state = State::new_from_map_file(db.clone(), &map_file);
+        let map_size = state.map().size();
+        let mut camera = Camera::new(context.win_size());
+        camera.set_max_pos(get_max_camera_pos(map_size));
+        camera.set_pos(get_initial_camera_pos(map_size));
+        let mut meshes = MeshManager::new();
+        let mesh_ids = MeshIdManager::new(context, &mut meshes, &state);
+        let floor_tex = load_texture(context, &fs::load("hex.png").into_inner());
+        let sector_tex = load_texture(context, &fs::load("chess_grid.png").into_inner());
+        let mut button_manager = ButtonManager::new();
+        let mut pos = ScreenPos{v: Vector2{x: 10, y: 10}};
+        let mut tool_button_ids = Vec::new();
+        let mut ystep = 0;
+        for &tool in TOOLS {
+            let text = format!("[{}]", tool.to_str());
+            let button = Button::new(context, &text, pos);
+            ystep = button.size().h;
+            tool_button_ids.push((button_manager.add_button(button), tool));
+            pos.v.y += ystep;
+        }
+        pos.v.y += ystep;
+        let button_new_sector_id = button_manager.add_button(
+            Button::new(context, "[new sector]", pos));
+        pos.v.y += ystep;
+        let button_score_up_id = button_manager.add_button(
+            Button::new(context, "[score +]", pos));
+        pos.v.x += button_manager.buttons()[&button_score_up_id].size().w;
+        let button_score_down_id = button_manager.add_button(
+            Button::new(context, "[score -]", pos));
+        pos.v.x = 10;
+        pos.v.y += ystep * 2;
+        let button_save_id = button_manager.add_button(
+            Button::new(context, "[save]", pos));
+        let label_info_id = button_manager.add_button(
+            Button::new_small(context, "tool", ScreenPos{v: Vector2{x: 0, y: 0}}));
+        let sector_id = map_file.new_sector_id();
+        let mut screen = EditorScreen {
+            db: db,
+            map_file: map_file,
+            state: state,
+            camera: camera,
+            scene: Scene::new(),
+            mesh_ids: mesh_ids,
+            meshes: meshes,
+            floor_tex: floor_tex,
+            sector_tex: sector_tex,
+            button_manager: button_manager,
+            tool_button_ids: tool_button_ids,
+            button_new_sector_id: button_new_sector_id,
+            button_score_up_id: button_score_up_id,
+            button_score_down_id: button_score_down_id,
+            button_save_id: button_save_id,
+            label_info_id: label_info_id,
+            tool: Tool::Terrain(Terrain::Trees),
+            sector_id: sector_id,
+            road_end: None,
+        };
+        screen.scene = make_scene(&screen.state, &screen.mesh_ids);
+        screen.update_info_label(context);
+        screen
+    }
+
+    fn info_text(&self) -> String {
+        let mut text = format!(
+            "tool: {}, target score: {}",
+            self.tool.to_str(),
+            self.map_file.target_score.n,
+        );
+        match self.tool {
+            Tool::Sector => {
+                text.push_str(&format!(", sector {}", self.sector_id.id));
+            },
+            Tool::Road => if let Some(pos) = self.road_end {
+                text.push_str(&format!(", road from ({}, {})", pos.v.x, pos.v.y));
+            },
+            _ => {},
+        }
+        text
+    }
+
+    fn update_info_label(&mut self, context: &mut Context) {
+        let vp_pos = ScreenPos{v: Vector2 {
+            x: context.win_size().w - 10,
+            y: context.win_size().h - 10,
+        }};
+        let mut label = Button::new_small(context, &self.info_text(), vp_pos);
+        let mut pos = label.pos();
+        pos.v.y -= label.size().h;
+        pos.v.x -= label.size().w;
+        label.set_pos(pos);
+        self.button_manager.remove_button(self.label_info_id);
+        self.label_info_id = self.button_manager.add_button(label);
+    }
+
+    /// Regenerates everything that depends on the map
+    fn update_scene(&mut self, context: &mut Context) {
+        self.state = State::new_from_map_file(self.db.clone(), &self.map_file);
+        let map_mesh = gen::generate_map_mesh(
+            context, &self.state, self.floor_tex.clone());
+        self.meshes.set(self.mesh_ids.map_mesh_id, map_mesh);
+        let water_mesh = gen::generate_water_mesh(
+            context, &self.state, self.floor_tex.clone());
+        self.meshes.set(self.mesh_ids.water_mesh_id, water_mesh);
+        let sectors = self.state.sectors();
+        self.mesh_ids.sector_mesh_ids.retain(|id, _| sectors.contains_key(id));
+        for (&id, sector) in sectors {
+            let mesh = gen::generate_sector_mesh(
+                context, sector, self.sector_tex.clone());
+            match self.mesh_ids.sector_mesh_ids.get(&id).cloned() {
+                Some(mesh_id) => self.meshes.set(mesh_id, mesh),
+                None => {
+                    let mesh_id = self.meshes.add(mesh);
+                    self.mesh_ids.sector_mesh_ids.insert(id, mesh_id);
+                },
+            }
+        }
+        self.scene = make_scene(&self.state, &self.mesh_ids);
+    }
+
+    fn use_tool(&mut self, context: &mut Context, pos: MapPos) {
+        match self.tool {
+            Tool::Terrain(terrain) => self.map_file.set_terrain(pos, terrain),
+            Tool::Building => {
+                self.map_file.add_building(pos);
+            },
+            Tool::BigBuilding => {
+                self.map_file.add_big_building(pos);
+            },
+            Tool::Road => {
+                if let Some(road_end) = self.road_end {
+                    self.map_file.add_road(road_end, pos);
+                }
+                // roads are drawn tile by tile
+                self.road_end = Some(pos);
+            },
+            Tool::Sector => self.map_file.toggle_sector_tile(self.sector_id, pos),
+            Tool::ReinforcementSector => {
+                self.map_file.toggle_reinforcement_sector(pos);
+            },
+            Tool::Eraser => self.map_file.clear_tile(pos),
+        }
+        self.update_scene(context);
+        self.update_info_label(context);
+    }
+
+    fn save(&mut self, context: &mut Context) {
+        let text = match self.map_file.save(map_file::EDITOR_FILE_NAME) {
+            Ok(()) => format!("Saved to {}", map_file::EDITOR_FILE_NAME),
+            Err(err) => format!("Can`t save: {}", err),
+        };
+        let screen = Box::new(MessageScreen::new(context, &text));
+        context.add_command(ScreenCommand::PushScreen(screen));
+    }
+
+    fn handle_event_button_press(&mut self, context: &mut Context, button_id: ButtonId) {
+        let tool = self.tool_button_ids.iter()
+            .find(|&&(id, _)| id == button_id)
+            .map(|&(_, tool)| tool);
+        if let Some(tool) = tool {
+            self.tool = tool;
+            self.road_end = None;
+        } else if button_id == self.button_new_sector_id {
+            self.tool = Tool::Sector;
+            self.sector_id = self.map_file.new_sector_id();
+        } else if button_id == self.button_score_up_id {
+            self.map_file.target_score.n += 1;
+        } else if button_id == self.button_score_down_id {
+            if self.map_file.target_score.n > 1 {
+                self.map_file.target_score.n -= 1;
+            }
+        } else if button_id == self.button_save_id {
+            self.save(context);
+        } else if button_id == self.label_info_id {
+            return;
+        } else {
+            panic!("Bad button id: {}", button_id.id);
+        }
+        self.update_info_label(context);
+    }
+
+    fn handle_event_lmb_release(&mut self, context: &mut Context) {
+        if !is_tap(context) {
+            return;
+        }
+        if let Some(button_id) = self.button_manager.get_clicked_button_id(context) {
+            self.handle_event_button_press(context, button_id);
+        } else if let Some(pos) = pick::pick_tile(context, &self.state, &self.camera) {
+            self.use_tool(context, pos);
+        }
+    }
+
+    fn handle_event_mouse_move(&mut self, context: &Context, pos: ScreenPos) {
+        let diff = pos.v - context.mouse().pos.v;
+        if context.mouse().is_left_button_pressed {
+            let camera_move_speed = geom::HEX_EX_RADIUS * 12.0;
+            let per_x_pixel = camera_move_speed / (context.win_size().w as f32);
+            let per_y_pixel = camera_move_speed / (context.win_size().h as f32);
+            self.camera.move_in_direction(Rad(PI), diff.x as f32 * per_x_pixel);
+            self.camera.move_in_direction(Rad(PI * 1.5), diff.y as f32 * per_y_pixel);
+        } else if context.mouse().is_right_button_pressed {
+            let per_x_pixel = PI / (context.win_size().w as f32);
+            let per_y_pixel = (PI / 4.0) / (context.win_size().h as f32);
+            self.camera.add_horizontal_angle(Rad(diff.x as f32 * per_x_pixel));
+            self.camera.add_vertical_angle(Rad(diff.y as f32 * per_y_pixel));
+        }
+    }
+
+    fn handle_event_key_press(&mut self, context: &mut Context, key: VirtualKeyCode) {
+        let s = geom::HEX_EX_RADIUS;
+        match key {
+            VirtualKeyCode::Q | VirtualKeyCode::Escape => {
+                context.add_command(ScreenCommand::PopScreen);
+            },
+            VirtualKeyCode::W | VirtualKeyCode::Up => {
+                self.camera.move_in_direction(Rad(PI * 1.5), s);
+            },
+            VirtualKeyCode::S | VirtualKeyCode::Down => {
+                self.camera.move_in_direction(Rad(PI * 0.5), s);
+            },
+            VirtualKeyCode::D | VirtualKeyCode::Right => {
+                self.camera.move_in_direction(Rad(PI * 0.0), s);
+            },
+            VirtualKeyCode::A | VirtualKeyCode::Left => {
+                self.camera.move_in_direction(Rad(PI * 1.0), s);
+            },
+            VirtualKeyCode::Subtract | VirtualKeyCode::Key1 => {
+                self.camera.change_zoom(1.3);
+            },
+            VirtualKeyCode::Equals | VirtualKeyCode::Key2 => {
+                self.camera.change_zoom(0.7);
+            },
+            _ => {},
+        }
+    }
+
+    fn draw_scene_node(
+        &self,
+        context: &mut Context,
+        node: &SceneNode,
+        m: cgmath::Matrix4<f32>,
+    ) {
+        let tr_mat = cgmath::Matrix4::from_translation(node.pos.v);
+        let rot_mat = cgmath::Matrix4::from(cgmath::Matrix3::from_angle_z(node.rot));
+        let m = m * tr_mat * rot_mat;
+        if let Some(mesh_id) = node.mesh_id {
+            context.set_mvp(m);
+            context.set_basic_color(node.color);
+            context.draw_mesh(self.meshes.get(mesh_id));
+        }
+        for node in &node.children {
+            self.draw_scene_node(context, node, m);
+        }
+    }
+
+    fn draw(&mut self, context: &mut Context) {
+        context.clear();
+        let m = self.camera.mat();
+        for node in self.scene.nodes().values() {
+            if !(node.color[3] < 1.0) {
+                self.draw_scene_node(context, node, m);
+            }
+        }
+        for layer in self.scene.transparent_node_ids().values() {
+            for &node_id in layer {
+                self.draw_scene_node(context, self.scene.node(node_id), m);
+            }
+        }
+        context.set_basic_color([0.0, 0.0, 0.0, 1.0]);
+        self.button_manager.draw(context);
+    }
+}
+
+impl Screen for EditorScreen {
+    fn tick(&mut self, context: &mut Context, _: Time) {
+        self.draw(context);
+    }
+
+    fn handle_event(&mut self, context: &mut Context, event: &Event) -> EventStatus {
+        match *event {
+            Event::Resized(..) => {
+                self.camera.regenerate_projection_mat(context.win_size());
+            },
+            Event::MouseMoved(x, y) => {
+                let pos = ScreenPos{v: Vector2{x: x as i32, y: y as i32}};
+                self.handle_event_mouse_move(context, pos);
+            },
+            Event::MouseInput(Released, MouseButton::Left) => {
+                self.handle_event_lmb_release(context);
+            },
+            Event::KeyboardInput(Released, _, Some(key)) => {
+                self.handle_event_key_press(context, key);
+            },
+            Event::Touch(glutin::Touch{location: (x, y), phase, ..}) => {
+                let pos = ScreenPos{v: Vector2{x: x as i32, y: y as i32}};
+                self.handle_event_mouse_move(context, pos);
+                if phase == glutin::TouchPhase::Ended {
+                    self.handle_event_lmb_release(context);
+                }
+            },
+            _ => {},
+        }
+        EventStatus::Handled
+    }
+}
@@ -1,13 +1,15 @@
 use std::f32::consts::{PI};
 use rand::{thread_rng, Rng};
 use cgmath::{Vector3, Rad};
-use core::game_state::{State};
+use core::game_state::{State, ReinforcementPoints};
 use core::unit::{Unit, UnitId};
 use core::sector::{SectorId};
 use core::position::{MapPos, ExactPos};
 use core::event::{FireMode, AttackInfo, ReactionFireMode};
 use core::player::{PlayerId};
-use core::object::{ObjectId};
+use core::object::{self, ObjectId, Object, ObjectClass};
+use core::morale::{morale_state, unit_morale_state};
+use core::veterancy::{veterancy, unit_veterancy};
 use types::{WorldPos, Time, Speed};
 use mesh::{MeshId};
 use geom::{self, vec3_z};
@@ -89,6 +91,24 @@ impl EventVisualizer for EventEndTurnVisualizer {
     fn end(&mut self, _: &mut Scene, _: &State) {}
 }
 
+pub struct EventGameOverVisualizer;
+
+impl EventGameOverVisualizer {
+    pub fn new() -> Box<EventVisualizer> {
+        Box::new(EventGameOverVisualizer)
+    }
+}
+
+impl EventVisualizer for EventGameOverVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
 fn try_to_fix_attached_unit_pos(
     scene: &mut Scene,
     transporter_id: UnitId,
@@ -241,7 +261,12 @@ impl EventAttackUnitVisualizer {
         let move_helper = MoveHelper::new(from, to, speed);
         let mut shell_move = None;
         let mut shell_node_id = None;
-        if let Some(attacker_id) = attack_info.attacker_id {
+        // splash results share the shell with the primary attack
+        let attacker_id = match attack_info.attacker_id {
+            Some(id) if !attack_info.is_splash => Some(id),
+            _ => None,
+        };
+        if let Some(attacker_id) = attacker_id {
             let attacker_node_id = scene.unit_id_to_node_id(attacker_id);
             let attacker_pos = scene.node(attacker_node_id).pos;
             let attacker_map_pos = state.unit(attacker_id).pos.map_pos;
@@ -262,17 +287,21 @@ impl EventAttackUnitVisualizer {
         if attack_info.is_ambush {
             map_text.add_text(defender.pos.map_pos, "Ambushed");
         };
+        if attack_info.is_scattered {
+            map_text.add_text(defender.pos.map_pos, "scattered");
+        }
         let is_target_destroyed = defender.count - attack_info.killed <= 0;
         if attack_info.killed > 0 {
             map_text.add_text(
                 defender.pos.map_pos,
                 &format!("-{}", attack_info.killed),
             );
-        } else {
+        } else if !attack_info.is_splash {
             map_text.add_text(defender.pos.map_pos, "miss");
         }
-        let is_target_suppressed = defender.morale < 50
-            && defender.morale + attack_info.suppression >= 50;
+        let old_morale_state = unit_morale_state(defender);
+        let new_morale_state = morale_state(
+            defender.morale - attack_info.suppression);
         if is_target_destroyed {
             if let Some(attached_unit_id) = defender.attached_unit_id {
                 let attached_unit = state.unit(attached_unit_id);
@@ -292,8 +321,9 @@ impl EventAttackUnitVisualizer {
                 defender.pos.map_pos,
                 &format!("morale: -{}", attack_info.suppression),
             );
-            if is_target_suppressed {
-                map_text.add_text(defender.pos.map_pos, "suppressed");
+            if new_morale_state != old_morale_state {
+                map_text.add_text(
+                    defender.pos.map_pos, new_morale_state.to_str());
             }
         }
         Box::new(EventAttackUnitVisualizer {
@@ -577,6 +607,56 @@ impl EventVisualizer for EventSetReactionFireModeVisualizer {
     fn end(&mut self, _: &mut Scene, _: &State) {}
 }
 
+#[derive(Clone, Debug)]
+pub struct EventExperienceVisualizer;
+
+impl EventExperienceVisualizer {
+    pub fn new(
+        state: &State,
+        unit_id: UnitId,
+        count: i32,
+        map_text: &mut MapTextManager,
+    ) -> Box<EventVisualizer> {
+        let unit = state.unit(unit_id);
+        let old_veterancy = unit_veterancy(unit);
+        let new_veterancy = veterancy(unit.experience + count);
+        if new_veterancy != old_veterancy {
+            let text = format!("promoted: {}", new_veterancy.to_str());
+            map_text.add_text(unit.pos.map_pos, &text);
+        }
+        Box::new(EventExperienceVisualizer)
+    }
+}
+
+impl EventVisualizer for EventExperienceVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
+#[derive(Clone, Debug)]
+pub struct EventBonusReinforcementPointsVisualizer;
+
+impl EventBonusReinforcementPointsVisualizer {
+    pub fn new() -> Box<EventVisualizer> {
+        Box::new(EventBonusReinforcementPointsVisualizer)
+    }
+}
+
+impl EventVisualizer for EventBonusReinforcementPointsVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
 #[derive(Clone, Debug)]
 pub struct EventSectorOwnerChangedVisualizer;
 
@@ -622,6 +702,101 @@ impl EventVisualizer for EventSectorOwnerChangedVisualizer {
     fn end(&mut self, _: &mut Scene, _: &State) {}
 }
 
+pub struct EventShellMissedVisualizer;
+
+impl EventShellMissedVisualizer {
+    pub fn new(
+        pos: MapPos,
+        map_text: &mut MapTextManager,
+    ) -> Box<EventVisualizer> {
+        map_text.add_text(pos, "scattered");
+        map_text.add_text(pos, "miss");
+        Box::new(EventShellMissedVisualizer)
+    }
+}
+
+impl EventVisualizer for EventShellMissedVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
+#[derive(Clone, Debug)]
+pub struct EventSectorIncomeChangedVisualizer;
+
+impl EventSectorIncomeChangedVisualizer {
+    pub fn new(
+        state: &State,
+        sector_id: SectorId,
+        income: ReinforcementPoints,
+        map_text: &mut MapTextManager,
+    ) -> Box<EventVisualizer> {
+        let pos = state.sectors()[&sector_id].center();
+        let text = format!("Sector {}: income changed: {}", sector_id.id, income.n);
+        map_text.add_text(pos, &text);
+        Box::new(EventSectorIncomeChangedVisualizer)
+    }
+}
+
+impl EventVisualizer for EventSectorIncomeChangedVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
+/// The message itself is shown by `TacticalScreen` in a separate screen
+#[derive(Clone, Debug)]
+pub struct EventMessageVisualizer;
+
+impl EventMessageVisualizer {
+    pub fn new() -> Box<EventVisualizer> {
+        Box::new(EventMessageVisualizer)
+    }
+}
+
+impl EventVisualizer for EventMessageVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
+#[derive(Clone, Debug)]
+pub struct EventRevealAreaVisualizer;
+
+impl EventRevealAreaVisualizer {
+    pub fn new(
+        area: &[MapPos],
+        map_text: &mut MapTextManager,
+    ) -> Box<EventVisualizer> {
+        for &pos in area {
+            map_text.add_text(pos, "revealed");
+        }
+        Box::new(EventRevealAreaVisualizer)
+    }
+}
+
+impl EventVisualizer for EventRevealAreaVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
 #[derive(Clone, Debug)]
 pub struct EventVictoryPointVisualizer {
     time: Time,
@@ -756,6 +931,153 @@ impl EventVisualizer for EventRemoveSmokeVisualizer {
     }
 }
 
+fn construction_color(class: ObjectClass, is_finished: bool) -> [f32; 4] {
+    let mut color = match class {
+        ObjectClass::Trench => [0.4, 0.3, 0.2, 1.0],
+        ObjectClass::Minefield => [0.8, 0.1, 0.1, 0.5],
+        _ => [1.0, 1.0, 1.0, 1.0],
+    };
+    if !is_finished {
+        color[3] = 0.4;
+    }
+    color
+}
+
+/// Scene node of a bridge, a trench or a minefield
+pub fn construction_node(
+    pos: MapPos,
+    class: ObjectClass,
+    mesh_id: MeshId,
+    is_finished: bool,
+) -> SceneNode {
+    let mut world_pos = geom::map_pos_to_world_pos(pos);
+    world_pos.v.z += 0.02; // TODO: layers
+    let rot = thread_rng().gen_range(0, 6) as f32 * PI / 3.0 + PI / 6.0;
+    SceneNode {
+        pos: world_pos,
+        rot: Rad(rot),
+        mesh_id: Some(mesh_id),
+        color: construction_color(class, is_finished),
+        children: Vec::new(),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EventBuildVisualizer;
+
+impl EventBuildVisualizer {
+    pub fn new(
+        state: &State,
+        scene: &mut Scene,
+        object_id: ObjectId,
+        pos: MapPos,
+        class: ObjectClass,
+        mesh_id: MeshId,
+        map_text: &mut MapTextManager,
+    ) -> Box<EventVisualizer> {
+        let build_turns_left = match state.objects().get(&object_id) {
+            Some(object) => object.build_turns_left - 1,
+            None => {
+                let build_turns_left = object::build_turns(class) - 1;
+                let is_finished = build_turns_left == 0;
+                scene.add_object(object_id, construction_node(
+                    pos, class, mesh_id, is_finished));
+                build_turns_left
+            },
+        };
+        let name = match class {
+            ObjectClass::Bridge => "bridge",
+            ObjectClass::Trench => "trench",
+            ObjectClass::Minefield => "minefield",
+            _ => unreachable!(),
+        };
+        if build_turns_left == 0 {
+            map_text.add_text(pos, &format!("{} is ready", name));
+            let node_ids = scene.object_id_to_node_id(object_id).clone();
+            for node_id in node_ids {
+                scene.node_mut(node_id).color = construction_color(class, true);
+            }
+        } else {
+            let text = format!("{}: {} turns left", name, build_turns_left);
+            map_text.add_text(pos, &text);
+        }
+        Box::new(EventBuildVisualizer)
+    }
+}
+
+impl EventVisualizer for EventBuildVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
+#[derive(Clone, Debug)]
+pub struct EventClearVisualizer;
+
+impl EventClearVisualizer {
+    pub fn new(
+        scene: &mut Scene,
+        pos: MapPos,
+        wreck_ids: &[UnitId],
+        object_ids: &[ObjectId],
+        map_text: &mut MapTextManager,
+    ) -> Box<EventVisualizer> {
+        map_text.add_text(pos, "cleared");
+        for &unit_id in wreck_ids {
+            if scene.unit_id_to_node_id_opt(unit_id).is_some() {
+                scene.remove_unit(unit_id);
+            }
+        }
+        for &object_id in object_ids {
+            scene.remove_object(object_id);
+        }
+        Box::new(EventClearVisualizer)
+    }
+}
+
+impl EventVisualizer for EventClearVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
+#[derive(Clone, Debug)]
+pub struct EventShowObjectVisualizer;
+
+impl EventShowObjectVisualizer {
+    pub fn new(
+        scene: &mut Scene,
+        object_id: ObjectId,
+        object: &Object,
+        mesh_id: MeshId,
+        map_text: &mut MapTextManager,
+    ) -> Box<EventVisualizer> {
+        let pos = object.pos.map_pos;
+        map_text.add_text(pos, "mines!");
+        scene.add_object(object_id, construction_node(
+            pos, object.class, mesh_id, object.is_finished()));
+        Box::new(EventShowObjectVisualizer)
+    }
+}
+
+impl EventVisualizer for EventShowObjectVisualizer {
+    fn is_finished(&self) -> bool {
+        true
+    }
+
+    fn draw(&mut self, _: &mut Scene, _: Time) {}
+
+    fn end(&mut self, _: &mut Scene, _: &State) {}
+}
+
 pub struct EventAttachVisualizer {
     transporter_id: UnitId,
     attached_unit_id: UnitId,
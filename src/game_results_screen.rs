@@ -1,26 +1,12 @@
 use cgmath::{Vector2};
 use glutin::{self, Event, MouseButton, VirtualKeyCode};
 use glutin::ElementState::{Released};
-use core::player::{PlayerId};
-use core::game_state::{State, Score};
+use core::game_state::{State};
 use screen::{Screen, ScreenCommand, EventStatus};
 use context::{Context};
 use gui::{ButtonManager, Button, is_tap};
 use types::{ScreenPos, Time};
 
-fn winner_id(state: &State) -> PlayerId {
-    // TODO: `CoreEvent::GameEnd` event?
-    let mut winner_id = PlayerId{id: 0};
-    let mut winner_score = Score{n: 0};
-    for (&id, &score) in state.score() {
-        if score.n > winner_score.n {
-            winner_id = id;
-            winner_score = score;
-        }
-    }
-    winner_id
-}
-
 #[derive(Clone, Debug)]
 pub struct GameResultsScreen {
     button_manager: ButtonManager,
@@ -32,11 +18,18 @@ impl GameResultsScreen {
         let wh = context.win_size().h;
         let mut pos = ScreenPos{v: Vector2{x: 10, y: wh -10}};
         pos.v.y -= wh / 10; // TODO: magic num
-        let winner_index = winner_id(state);
-        let str = format!("Player {} wins!", winner_index.id);
+        let result = state.game_result().expect("Game is not over");
+        let str = match result.winner_id {
+            Some(winner_id) => format!("Player {} wins!", winner_id.id),
+            None => "Draw!".to_owned(),
+        };
         let title_button = Button::new(context, &str, pos);
         pos.v.y -= title_button.size().h; // TODO: autolayout
         let _ = button_manager.add_button(title_button);
+        let str = format!("{} on turn {}", result.reason.to_str(), result.turn);
+        let reason_button = Button::new(context, &str, pos);
+        pos.v.y -= reason_button.size().h;
+        let _ = button_manager.add_button(reason_button);
         for (player_index, player_score) in state.score() {
             let str = format!("Player {}: {} VPs", player_index.id, player_score.n);
             let button = Button::new(context, &str, pos);
@@ -39,6 +39,8 @@ mod context_menu_popup;
 mod reinforcements_popup;
 mod main_menu_screen;
 mod end_turn_screen;
+mod message_screen;
+mod editor_screen;
 mod game_results_screen;
 mod context;
 mod text;
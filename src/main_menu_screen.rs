@@ -1,34 +1,103 @@
+use std::rc::{Rc};
+use std::path::{Path};
 use cgmath::{Vector2};
+use rand::{thread_rng, Rng};
 use glutin::{self, Event, MouseButton, VirtualKeyCode};
 use glutin::ElementState::{Released};
 use screen::{Screen, ScreenCommand, EventStatus};
 use tactical_screen::{TacticalScreen};
+use message_screen::{MessageScreen};
+use editor_screen::{EditorScreen};
 use core::options::{Options, GameType};
+use core::campaign::{self, CampaignProgress};
+use core::mapgen::{MapGenOptions};
+use core::map_file::{self, MapFile};
+use core::game_state::{State, LoadMapError};
+use core::db::{Db};
 use context::{Context};
 use gui::{ButtonManager, Button, ButtonId, is_tap};
-use types::{ScreenPos, Time};
+use types::{ScreenPos, Time, Size2};
+
+/// Name of the main menu entry for a generated map
+const RANDOM_MAP_NAME: &'static str = "random";
+
+const RANDOM_MAP_SIZES: &'static [Size2] = &[
+    Size2{w: 10, h: 12},
+    Size2{w: 14, h: 16},
+    Size2{w: 20, h: 20},
+    Size2{w: 26, h: 28},
+];
+
+/// Percent of free land covered by forests
+const RANDOM_MAP_DENSITIES: &'static [i32] = &[10, 30, 50];
+
+fn random_map_seed() -> u32 {
+    thread_rng().gen_range(0, 10000)
+}
+
+fn map_size_text(index: usize) -> String {
+    let size = RANDOM_MAP_SIZES[index];
+    format!("[random map size: {}x{}]", size.w, size.h)
+}
+
+fn map_density_text(index: usize) -> String {
+    format!("[random map forests: {}%]", RANDOM_MAP_DENSITIES[index])
+}
+
+fn map_seed_text(seed: u32) -> String {
+    format!("[random map seed: {}]", seed)
+}
+
+/// Shows why the map can't be loaded instead of the screen that needs it
+fn push_screen_or_error<S: Screen + 'static>(
+    context: &mut Context,
+    screen: Result<S, LoadMapError>,
+) {
+    let screen: Box<Screen> = match screen {
+        Ok(screen) => Box::new(screen),
+        Err(err) => Box::new(MessageScreen::new(context, &err.to_string())),
+    };
+    context.add_command(ScreenCommand::PushScreen(screen));
+}
 
 #[derive(Clone, Debug)]
 pub struct MainMenuScreen {
     button_start_hotseat_id: ButtonId,
     button_start_vs_ai_id: ButtonId,
+    button_campaign_id: ButtonId,
+    button_editor_id: ButtonId,
     button_map_id: ButtonId,
+    button_map_size_id: ButtonId,
+    button_map_density_id: ButtonId,
+    button_map_seed_id: ButtonId,
     button_manager: ButtonManager,
     map_names: Vec<&'static str>,
     selected_map_index: usize,
+    selected_map_size_index: usize,
+    selected_map_density_index: usize,
+    map_seed: u32,
 }
 
 impl MainMenuScreen {
     pub fn new(context: &mut Context) -> MainMenuScreen {
-        let map_names = vec![
+        let mut map_names = vec![
             "map01",
             "map02",
             "map03",
             "map04",
             "map05",
+            "map06",
+            "map07",
             "map_fov_bug_test",
+            RANDOM_MAP_NAME,
         ];
+        if Path::new(map_file::EDITOR_FILE_NAME).exists() {
+            map_names.push(map_file::EDITOR_FILE_NAME);
+        }
         let selected_map_index = 0;
+        let selected_map_size_index = 1;
+        let selected_map_density_index = 1;
+        let map_seed = random_map_seed();
         let mut button_manager = ButtonManager::new();
         // TODO: Use relative coords in ScreenPos - x: [0.0, 1.0], y: [0.0, 1.0]
         // TODO: Add analog of Qt::Alignment
@@ -46,22 +115,86 @@ impl MainMenuScreen {
             "[start human vs ai]",
             button_pos,
         ));
+        button_pos.v.y += vstep;
+        let button_campaign_id = button_manager.add_button(Button::new(
+            context,
+            "[campaign]",
+            button_pos,
+        ));
+        button_pos.v.y += vstep;
+        let button_editor_id = button_manager.add_button(Button::new(
+            context,
+            "[edit map]",
+            button_pos,
+        ));
         button_pos.v.y += vstep * 2;
         let button_map_id = button_manager.add_button(Button::new(
             context,
             &format!("[map: {}]", map_names[selected_map_index]),
             button_pos,
         ));
+        button_pos.v.y += vstep;
+        let button_map_size_id = button_manager.add_button(Button::new(
+            context,
+            &map_size_text(selected_map_size_index),
+            button_pos,
+        ));
+        button_pos.v.y += vstep;
+        let button_map_density_id = button_manager.add_button(Button::new(
+            context,
+            &map_density_text(selected_map_density_index),
+            button_pos,
+        ));
+        button_pos.v.y += vstep;
+        let button_map_seed_id = button_manager.add_button(Button::new(
+            context,
+            &map_seed_text(map_seed),
+            button_pos,
+        ));
         MainMenuScreen {
             button_manager: button_manager,
             button_start_hotseat_id: button_start_hotseat_id,
             button_start_vs_ai_id: button_start_vs_ai_id,
+            button_campaign_id: button_campaign_id,
+            button_editor_id: button_editor_id,
             button_map_id: button_map_id,
+            button_map_size_id: button_map_size_id,
+            button_map_density_id: button_map_density_id,
+            button_map_seed_id: button_map_seed_id,
             map_names: map_names,
             selected_map_index: selected_map_index,
+            selected_map_size_index: selected_map_size_index,
+            selected_map_density_index: selected_map_density_index,
+            map_seed: map_seed,
+        }
+    }
+
+    fn map_name(&self) -> String {
+        let map_name = self.map_names[self.selected_map_index];
+        if map_name == RANDOM_MAP_NAME {
+            MapGenOptions {
+                seed: self.map_seed,
+                size: RANDOM_MAP_SIZES[self.selected_map_size_index],
+                density: RANDOM_MAP_DENSITIES[self.selected_map_density_index],
+            }.to_map_name()
+        } else {
+            map_name.to_string()
         }
     }
 
+    /// Buttons can't change their text so they are recreated
+    fn replace_button(
+        &mut self,
+        context: &mut Context,
+        button_id: ButtonId,
+        text: &str,
+    ) -> ButtonId {
+        let pos = self.button_manager.buttons()[&button_id].pos();
+        let button = Button::new(context, text, pos);
+        self.button_manager.remove_button(button_id);
+        self.button_manager.add_button(button)
+    }
+
     fn handle_event_lmb_release(&mut self, context: &mut Context) {
         if !is_tap(context) {
             return;
@@ -76,31 +209,60 @@ impl MainMenuScreen {
         context: &mut Context,
         button_id: ButtonId
     ) {
-        let map_name = self.map_names[self.selected_map_index].to_string();
         let mut core_options = Options {
             game_type: GameType::Hotseat,
-            map_name: map_name,
+            map_name: self.map_name(),
             players_count: 2,
         };
         if button_id == self.button_start_hotseat_id {
-            let tactical_screen = Box::new(
-                TacticalScreen::new(context, &core_options));
-            context.add_command(ScreenCommand::PushScreen(tactical_screen));
+            let tactical_screen = TacticalScreen::new(context, &core_options);
+            push_screen_or_error(context, tactical_screen);
         } else if button_id == self.button_start_vs_ai_id {
             core_options.game_type = GameType::SingleVsAi;
-            let tactical_screen = Box::new(
-                TacticalScreen::new(context, &core_options));
-            context.add_command(ScreenCommand::PushScreen(tactical_screen));
+            let tactical_screen = TacticalScreen::new(context, &core_options);
+            push_screen_or_error(context, tactical_screen);
+        } else if button_id == self.button_campaign_id {
+            // start a new campaign if there's no save or the old one is won
+            let db = Db::new();
+            let progress = CampaignProgress::load(&db, campaign::SAVE_FILE_NAME).ok()
+                .and_then(|progress| progress.map_name().map(|_| progress))
+                .unwrap_or_else(CampaignProgress::new);
+            let tactical_screen = TacticalScreen::new_campaign_mission(context, progress);
+            push_screen_or_error(context, tactical_screen);
+        } else if button_id == self.button_editor_id {
+            // the selected map is the starting point
+            let editor_screen = State::new_full(Rc::new(Db::new()), &core_options)
+                .map(|state| EditorScreen::new(context, MapFile::from_state(&state)));
+            push_screen_or_error(context, editor_screen);
         } else if button_id == self.button_map_id {
             self.selected_map_index += 1;
             if self.selected_map_index == self.map_names.len() {
                 self.selected_map_index = 0;
             }
-            let text = &format!("[map: {}]", self.map_names[self.selected_map_index]);
-            let pos = self.button_manager.buttons()[&self.button_map_id].pos();
-            let button_map = Button::new(context, text, pos);
-            self.button_manager.remove_button(self.button_map_id);
-            self.button_map_id = self.button_manager.add_button(button_map);
+            let text = format!("[map: {}]", self.map_names[self.selected_map_index]);
+            let id = self.button_map_id;
+            self.button_map_id = self.replace_button(context, id, &text);
+        } else if button_id == self.button_map_size_id {
+            self.selected_map_size_index += 1;
+            if self.selected_map_size_index == RANDOM_MAP_SIZES.len() {
+                self.selected_map_size_index = 0;
+            }
+            let text = map_size_text(self.selected_map_size_index);
+            let id = self.button_map_size_id;
+            self.button_map_size_id = self.replace_button(context, id, &text);
+        } else if button_id == self.button_map_density_id {
+            self.selected_map_density_index += 1;
+            if self.selected_map_density_index == RANDOM_MAP_DENSITIES.len() {
+                self.selected_map_density_index = 0;
+            }
+            let text = map_density_text(self.selected_map_density_index);
+            let id = self.button_map_density_id;
+            self.button_map_density_id = self.replace_button(context, id, &text);
+        } else if button_id == self.button_map_seed_id {
+            self.map_seed = random_map_seed();
+            let text = map_seed_text(self.map_seed);
+            let id = self.button_map_seed_id;
+            self.button_map_seed_id = self.replace_button(context, id, &text);
         } else {
             panic!("Bad button id: {}", button_id.id);
         }
@@ -25,6 +25,9 @@ pub struct MeshIdManager {
     pub water_mesh_id: MeshId,
     pub selection_marker_mesh_id: MeshId,
     pub smoke_mesh_id: MeshId,
+    pub bridge_mesh_id: MeshId,
+    pub trench_mesh_id: MeshId,
+    pub minefield_mesh_id: MeshId,
     pub fow_tile_mesh_id: MeshId,
     pub reinforcement_sector_tile_mesh_id: MeshId,
     pub sector_mesh_ids: HashMap<SectorId, MeshId>,
@@ -53,6 +56,8 @@ impl MeshIdManager {
         }
         let selection_marker_mesh_id = meshes.add(get_selection_mesh(context));
         let smoke_mesh_id = meshes.add(gen::get_one_tile_mesh(context, smoke_tex));
+        let trench_mesh_id = meshes.add(gen::get_one_tile_mesh(context, floor_tex.clone()));
+        let minefield_mesh_id = meshes.add(gen::get_one_tile_mesh(context, floor_tex.clone()));
         let fow_tile_mesh_id = meshes.add(gen::get_one_tile_mesh(context, floor_tex));
         let reinforcement_sector_tile_mesh_id = meshes.add(
             gen::get_one_tile_mesh(context, reinforcement_sector_tex));
@@ -67,6 +72,7 @@ impl MeshIdManager {
         let trees_mesh_id = meshes.add(load_object_mesh(context, "trees"));
         let shell_mesh_id = meshes.add(gen::get_shell_mesh(context));
         let road_mesh_id = meshes.add(gen::get_road_mesh(context));
+        let bridge_mesh_id = meshes.add(gen::get_road_mesh(context));
         let marker_mesh_id = meshes.add(gen::get_marker(context, "white.png"));
         let walkable_mesh_id = meshes.add(gen::empty_mesh(context));
         let targets_mesh_id = meshes.add(gen::empty_mesh(context));
@@ -85,6 +91,9 @@ impl MeshIdManager {
             water_mesh_id: water_mesh_id,
             selection_marker_mesh_id: selection_marker_mesh_id,
             smoke_mesh_id: smoke_mesh_id,
+            bridge_mesh_id: bridge_mesh_id,
+            trench_mesh_id: trench_mesh_id,
+            minefield_mesh_id: minefield_mesh_id,
             fow_tile_mesh_id: fow_tile_mesh_id,
             reinforcement_sector_tile_mesh_id: reinforcement_sector_tile_mesh_id,
             sector_mesh_ids: sector_mesh_ids,
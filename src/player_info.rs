@@ -1,7 +1,7 @@
 use std::collections::{HashMap};
 use std::rc::{Rc};
 use cgmath::{Vector2, Vector3};
-use core::game_state::{State};
+use core::game_state::{State, LoadMapError};
 use core::movement::{Pathfinder};
 use core::map::{Map};
 use core::db::{Db};
@@ -14,12 +14,12 @@ use scene::{Scene, NodeId};
 use geom;
 use camera::Camera;
 
-fn get_initial_camera_pos(map_size: Size2) -> WorldPos {
+pub fn get_initial_camera_pos(map_size: Size2) -> WorldPos {
     let pos = get_max_camera_pos(map_size);
     WorldPos{v: Vector3{x: pos.v.x / 2.0, y: pos.v.y / 2.0, z: 0.0}}
 }
 
-fn get_max_camera_pos(map_size: Size2) -> WorldPos {
+pub fn get_max_camera_pos(map_size: Size2) -> WorldPos {
     let map_pos = MapPos{v: Vector2{x: map_size.w, y: map_size.h - 1}};
     let pos = geom::map_pos_to_world_pos(map_pos);
     WorldPos{v: Vector3{x: -pos.v.x, y: -pos.v.y, z: 0.0}}
@@ -57,8 +57,12 @@ pub struct PlayerInfoManager {
 }
 
 impl PlayerInfoManager {
-    pub fn new(db: Rc<Db>, context: &Context, options: &Options) -> PlayerInfoManager {
-        let state = State::new_partial(db.clone(), options, PlayerId{id: 0});
+    pub fn new(
+        db: Rc<Db>,
+        context: &Context,
+        options: &Options,
+    ) -> Result<PlayerInfoManager, LoadMapError> {
+        let state = State::new_partial(db.clone(), options, PlayerId{id: 0})?;
         let map_size = state.map().size();
         let mut m = HashMap::new();
         let mut camera = Camera::new(context.win_size());
@@ -72,7 +76,7 @@ impl PlayerInfoManager {
             fow_info: FowInfo::new(map_size),
         });
         if options.game_type == GameType::Hotseat {
-            let state2 = State::new_partial(db.clone(), options, PlayerId{id: 1});
+            let state2 = State::new_partial(db.clone(), options, PlayerId{id: 1})?;
             m.insert(PlayerId{id: 1}, PlayerInfo {
                 game_state: state2,
                 pathfinder: Pathfinder::new(db, map_size),
@@ -81,7 +85,7 @@ impl PlayerInfoManager {
                 fow_info: FowInfo::new(map_size),
             });
         }
-        PlayerInfoManager{info: m}
+        Ok(PlayerInfoManager{info: m})
     }
 
     pub fn get(&self, player_id: PlayerId) -> &PlayerInfo {
@@ -8,15 +8,19 @@ use glutin::{self, VirtualKeyCode, Event, MouseButton, TouchPhase};
 use glutin::ElementState::{Released};
 use core;
 use core::map::{Terrain};
-use core::game_state::{State};
+use core::game_state::{State, LoadMapError};
 use core::event::{CoreEvent, Command, MoveMode, ReactionFireMode};
 use core::player::{PlayerId};
 use core::object::{Object, ObjectClass};
+use core::options::{GameType};
 use core::options::Options as CoreOptions;
+use core::campaign::{self, CampaignProgress};
 use core::position::{self, MapPos, ExactPos, SlotId};
 use core::unit::{UnitId, UnitTypeId};
 use core::misc::{opt_rx_collect};
 use core::print_info::{print_pos_info};
+use core::morale::{unit_morale_state};
+use core::veterancy::{unit_veterancy};
 use gui::{ButtonManager, Button, ButtonId, is_tap};
 use scene::{Scene, NodeId, SceneNode};
 use event_visualizer;
@@ -33,6 +37,7 @@ use screen::{Screen, ScreenCommand, EventStatus};
 use context_menu_popup::{self, ContextMenuPopup};
 use reinforcements_popup::{self, ReinforcementsPopup};
 use end_turn_screen::{EndTurnScreen};
+use message_screen::{MessageScreen};
 use game_results_screen::{GameResultsScreen};
 use types::{Time, ScreenPos, WorldPos};
 use gen;
@@ -46,18 +51,35 @@ fn score_text(state: &State) -> String {
     let target_score = state.target_score();
     let score = state.score();
     // TODO: get rid of magic num
-    format!("P0:{}/{}, P1:{}/{}",
+    let text = format!("P0:{}/{}, P1:{}/{}",
         score[&PlayerId{id: 0}].n,
         target_score.n,
         score[&PlayerId{id: 1}].n,
         target_score.n,
-    )
+    );
+    if state.is_deployment_phase() {
+        return format!("deployment, {}", text);
+    }
+    match state.turn_limit() {
+        Some(turn_limit) => format!("turn {}/{}, {}", state.turn(), turn_limit, text),
+        None => format!("turn {}, {}", state.turn(), text),
+    }
 }
 
 fn reinforcement_points_text(state: &State, player_id: PlayerId) -> String {
     let rp = state.reinforcement_points()[&player_id].n;
-    let rp_per_turn = 10; // TODO: magic num
-    format!("reinforcements: {} (+{})", rp, rp_per_turn)
+    let income = state.income(player_id);
+    let mut text = format!(
+        "reinforcements: {} (+{}: base {}",
+        rp,
+        income.total().n,
+        income.base.n,
+    );
+    for &(sector_id, points) in &income.sectors {
+        text.push_str(&format!(", sector {} +{}", sector_id.id, points.n));
+    }
+    text.push(')');
+    text
 }
 
 fn building_mesh_id(mesh_ids: &MeshIdManager, object: &Object) -> MeshId {
@@ -69,6 +91,15 @@ fn building_mesh_id(mesh_ids: &MeshIdManager, object: &Object) -> MeshId {
     }
 }
 
+fn construction_mesh_id(mesh_ids: &MeshIdManager, class: ObjectClass) -> MeshId {
+    match class {
+        ObjectClass::Bridge => mesh_ids.bridge_mesh_id,
+        ObjectClass::Trench => mesh_ids.trench_mesh_id,
+        ObjectClass::Minefield => mesh_ids.minefield_mesh_id,
+        _ => panic!("{:?} is not a construction", class),
+    }
+}
+
 fn wireframe_building_mesh_id(mesh_ids: &MeshIdManager, object: &Object) -> MeshId {
     let slot_id = object.pos.slot_id;
     match slot_id {
@@ -155,7 +186,7 @@ impl Gui {
     }
 }
 
-fn make_scene(state: &State, mesh_ids: &MeshIdManager) -> Scene {
+pub fn make_scene(state: &State, mesh_ids: &MeshIdManager) -> Scene {
     let mut scene = Scene::new();
     let map = state.map();
     scene.add_node(SceneNode {
@@ -255,6 +286,16 @@ fn make_scene(state: &State, mesh_ids: &MeshIdManager) -> Scene {
                     children: Vec::new(),
                 });
             }
+            ObjectClass::Bridge |
+            ObjectClass::Trench |
+            ObjectClass::Minefield => {
+                scene.add_object(object_id, event_visualizer::construction_node(
+                    object.pos.map_pos,
+                    object.class,
+                    construction_mesh_id(mesh_ids, object.class),
+                    object.is_finished(),
+                ));
+            }
             ObjectClass::Smoke => unimplemented!(),
         }
     }
@@ -275,13 +316,19 @@ pub struct TacticalScreen {
     selection_manager: SelectionManager,
     context_menu_popup_rx: Option<Receiver<context_menu_popup::Command>>,
     reinforcements_popup_rx: Option<Receiver<(UnitTypeId, ExactPos)>>,
+
+    /// Some if this game is a campaign mission
+    campaign_progress: Option<CampaignProgress>,
 }
 
 impl TacticalScreen {
-    pub fn new(context: &mut Context, core_options: &CoreOptions) -> TacticalScreen {
-        let core = core::Core::new(core_options);
+    pub fn new(
+        context: &mut Context,
+        core_options: &CoreOptions,
+    ) -> Result<TacticalScreen, LoadMapError> {
+        let core = core::Core::new(core_options)?;
         let mut player_info = PlayerInfoManager::new(
-            core.db().clone(), context, core_options);
+            core.db().clone(), context, core_options)?;
         let mut meshes = MeshManager::new();
         let mesh_ids = MeshIdManager::new(
             context,
@@ -310,9 +357,26 @@ impl TacticalScreen {
             map_text_manager: map_text_manager,
             context_menu_popup_rx: None,
             reinforcements_popup_rx: None,
+            campaign_progress: None,
         };
         screen.regenerate_fow();
-        screen
+        Ok(screen)
+    }
+
+    pub fn new_campaign_mission(
+        context: &mut Context,
+        mut progress: CampaignProgress,
+    ) -> Result<TacticalScreen, LoadMapError> {
+        let map_name = progress.map_name().expect("Campaign is over");
+        let core_options = CoreOptions {
+            game_type: GameType::SingleVsAi,
+            map_name: map_name.to_owned(),
+            players_count: 2,
+        };
+        let mut screen = TacticalScreen::new(context, &core_options)?;
+        screen.core.add_core_forces(PlayerId{id: 0}, &mut progress);
+        screen.campaign_progress = Some(progress);
+        Ok(screen)
     }
 
     fn show_reinforcements_menu(&mut self, context: &mut Context, pos: MapPos) {
@@ -505,7 +569,7 @@ impl TacticalScreen {
                 let unit = state.unit(unit_id);
                 let unit_type = self.core.db().unit_type(unit.type_id);
                 // TODO: core.rs: print_unit_info
-                format!("MP={}/{}, AP={}/{}, RAP={}/{}, C={}, M={}",
+                format!("MP={}/{}, AP={}/{}, RAP={}/{}, C={}, M={}/{} ({}), XP={} ({})",
                     if let Some(mp) = unit.move_points { mp.n } else { 0 },
                     unit_type.move_points.n,
                     if let Some(ap) = unit.attack_points { ap.n } else { 0 },
@@ -514,6 +578,10 @@ impl TacticalScreen {
                     unit_type.reactive_attack_points.n,
                     unit.count,
                     unit.morale,
+                    unit_type.max_morale,
+                    unit_morale_state(unit).to_str(),
+                    unit.experience,
+                    unit_veterancy(unit).to_str(),
                 )
                 // TODO: print info about unit type and weapon
             };
@@ -759,7 +827,10 @@ impl TacticalScreen {
             CoreEvent::EndTurn{..} => {
                 event_visualizer::EventEndTurnVisualizer::new()
             },
-            CoreEvent::CreateUnit{ref unit_info} => {
+            CoreEvent::GameOver{..} => {
+                event_visualizer::EventGameOverVisualizer::new()
+            },
+            CoreEvent::CreateUnit{ref unit_info, ..} => {
                 let mesh_id = self.unit_type_visual_info
                     .get(unit_info.type_id).mesh_id;
                 event_visualizer::EventCreateUnitVisualizer::new(
@@ -780,6 +851,12 @@ impl TacticalScreen {
                     &mut self.map_text_manager,
                 )
             },
+            CoreEvent::ShellMissed{pos, ..} => {
+                event_visualizer::EventShellMissedVisualizer::new(
+                    pos,
+                    &mut self.map_text_manager,
+                )
+            },
             CoreEvent::ShowUnit{ref unit_info, ..} => {
                 let mesh_id = self.unit_type_visual_info
                     .get(unit_info.type_id).mesh_id;
@@ -870,6 +947,14 @@ impl TacticalScreen {
                     &mut self.map_text_manager,
                 )
             }
+            CoreEvent::SectorIncomeChanged{sector_id, income} => {
+                event_visualizer::EventSectorIncomeChangedVisualizer::new(
+                    state,
+                    sector_id,
+                    income,
+                    &mut self.map_text_manager,
+                )
+            }
             CoreEvent::VictoryPoint{pos, count, ..} => {
                 event_visualizer::EventVictoryPointVisualizer::new(
                     pos,
@@ -877,6 +962,17 @@ impl TacticalScreen {
                     &mut self.map_text_manager,
                 )
             }
+            CoreEvent::BonusReinforcementPoints{..} => {
+                event_visualizer::EventBonusReinforcementPointsVisualizer::new()
+            }
+            CoreEvent::Experience{unit_id, count} => {
+                event_visualizer::EventExperienceVisualizer::new(
+                    state,
+                    unit_id,
+                    count,
+                    &mut self.map_text_manager,
+                )
+            }
             CoreEvent::Smoke{pos, unit_id, id} => {
                 event_visualizer::EventSmokeVisualizer::new(
                     scene,
@@ -894,6 +990,44 @@ impl TacticalScreen {
                     &mut self.map_text_manager,
                 )
             }
+            CoreEvent::Build{id, pos, class, ..} => {
+                event_visualizer::EventBuildVisualizer::new(
+                    state,
+                    scene,
+                    id,
+                    pos,
+                    class,
+                    construction_mesh_id(&self.mesh_ids, class),
+                    &mut self.map_text_manager,
+                )
+            }
+            CoreEvent::Clear{pos, ref wreck_ids, ref object_ids, ..} => {
+                event_visualizer::EventClearVisualizer::new(
+                    scene,
+                    pos,
+                    wreck_ids,
+                    object_ids,
+                    &mut self.map_text_manager,
+                )
+            }
+            CoreEvent::ShowObject{id, ref object} => {
+                event_visualizer::EventShowObjectVisualizer::new(
+                    scene,
+                    id,
+                    object,
+                    construction_mesh_id(&self.mesh_ids, object.class),
+                    &mut self.map_text_manager,
+                )
+            }
+            CoreEvent::Message{..} => {
+                event_visualizer::EventMessageVisualizer::new()
+            }
+            CoreEvent::RevealArea{ref area, ..} => {
+                event_visualizer::EventRevealAreaVisualizer::new(
+                    area,
+                    &mut self.map_text_manager,
+                )
+            }
             CoreEvent::Reveal{..} => unreachable!(),
         }
     }
@@ -935,15 +1069,29 @@ impl TacticalScreen {
         }
     }
 
-    fn check_game_end(&mut self, context: &mut Context) {
-        let state = self.current_state();
-        for score in state.score().values() {
-            if score.n >= state.target_score().n {
-                context.add_command(ScreenCommand::PopScreen);
-                let screen = Box::new(GameResultsScreen::new(context, self.current_state()));
-                context.add_command(ScreenCommand::PushScreen(screen));
-            }
+    /// Only won missions move the campaign forward
+    fn save_campaign_progress(&mut self) {
+        let mut progress = match self.campaign_progress.take() {
+            Some(progress) => progress,
+            None => return,
+        };
+        let player_id = PlayerId{id: 0};
+        let state = &self.player_info.get(player_id).game_state;
+        let winner_id = state.game_result().and_then(|result| result.winner_id);
+        if winner_id != Some(player_id) {
+            return;
         }
+        progress.complete_mission(state, player_id);
+        if let Err(err) = progress.save(campaign::SAVE_FILE_NAME) {
+            println!("Can`t save campaign progress: {}", err);
+        }
+    }
+
+    fn show_game_results(&mut self, context: &mut Context) {
+        self.save_campaign_progress();
+        context.add_command(ScreenCommand::PopScreen);
+        let screen = Box::new(GameResultsScreen::new(context, self.current_state()));
+        context.add_command(ScreenCommand::PushScreen(screen));
     }
 
     fn update_score_labels(&mut self, context: &mut Context) {
@@ -1006,9 +1154,19 @@ impl TacticalScreen {
             self.gui.button_manager.remove_button(label_id);
         }
         self.update_reinforcement_points_label(context);
-        if let Some(CoreEvent::VictoryPoint{..}) = self.event {
-            self.update_score_labels(context);
-            self.check_game_end(context);
+        match self.event {
+            Some(CoreEvent::VictoryPoint{..}) |
+            Some(CoreEvent::EndTurn{..}) => {
+                self.update_score_labels(context);
+            },
+            Some(CoreEvent::GameOver{..}) => {
+                self.show_game_results(context);
+            },
+            Some(CoreEvent::Message{ref text, ..}) => {
+                let screen = Box::new(MessageScreen::new(context, text));
+                context.add_command(ScreenCommand::PushScreen(screen));
+            },
+            _ => {},
         }
         self.regenerate_fow();
         self.event_visualizer = None;
@@ -1063,13 +1221,8 @@ impl TacticalScreen {
                     passenger_id: passenger_id,
                 });
             },
-            context_menu_popup::Command::UnloadUnit{pos} => {
+            context_menu_popup::Command::UnloadUnit{passenger_id, pos} => {
                 let selected_unit_id = self.selected_unit_id.unwrap();
-                let passenger_id = {
-                    let transporter = self.current_state()
-                        .unit(selected_unit_id);
-                    transporter.passenger_id.unwrap()
-                };
                 self.core.do_command(Command::UnloadUnit {
                     transporter_id: selected_unit_id,
                     passenger_id: passenger_id,
@@ -1108,6 +1261,34 @@ impl TacticalScreen {
                     pos: pos,
                 });
             },
+            context_menu_popup::Command::BuildBridge{pos} => {
+                let selected_unit_id = self.selected_unit_id.unwrap();
+                self.core.do_command(Command::BuildBridge {
+                    unit_id: selected_unit_id,
+                    pos: pos,
+                });
+            },
+            context_menu_popup::Command::DigTrench{pos} => {
+                let selected_unit_id = self.selected_unit_id.unwrap();
+                self.core.do_command(Command::DigTrench {
+                    unit_id: selected_unit_id,
+                    pos: pos,
+                });
+            },
+            context_menu_popup::Command::LayMines{pos} => {
+                let selected_unit_id = self.selected_unit_id.unwrap();
+                self.core.do_command(Command::LayMines {
+                    unit_id: selected_unit_id,
+                    pos: pos,
+                });
+            },
+            context_menu_popup::Command::Clear{pos} => {
+                let selected_unit_id = self.selected_unit_id.unwrap();
+                self.core.do_command(Command::Clear {
+                    unit_id: selected_unit_id,
+                    pos: pos,
+                });
+            },
             context_menu_popup::Command::CallReiforcements{pos} => {
                 self.show_reinforcements_menu(context, pos);
             },